@@ -0,0 +1,16 @@
+pub mod activity;
+pub mod af_screening;
+pub mod agreement;
+pub mod artefacts;
+pub mod beat_template;
+pub mod delineation;
+pub mod expression;
+pub mod filters;
+pub mod heart_rate;
+pub mod hrv;
+pub mod qrs_detection;
+pub mod resample;
+pub mod respiration;
+pub mod signal_quality;
+pub mod spectrum;
+pub mod statistics;
@@ -0,0 +1,259 @@
+use crate::analysis::filters::{filtfilt_missing, Biquad};
+use crate::analysis::resample::UniformSignal;
+
+/// band of the body movements the activity counts integrate, in Hz
+const COUNTS_BAND: (f64, f64) = (0.25, 2.5);
+
+/// band of the step frequency in Hz
+const STEP_BAND: (f64, f64) = (0.5, 3.0);
+
+/// smallest peak of the band passed vector magnitude which counts as a step, in g
+const STEP_THRESHOLD: f64 = 0.05;
+
+/// shortest and longest interval between the steps of a walk, in seconds
+const STEP_INTERVAL: (f64, f64) = (0.25, 2.0);
+
+/// fewer consecutive steps are no walk, e.g. shifting the weight
+const MIN_STEPS: usize = 4;
+
+/// frequencies below this (in Hz) are taken as the direction of gravity
+const GRAVITY_CUTOFF: f64 = 0.5;
+
+/// The accelerometer axis along the body when standing upright
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Axis::X => "X",
+            Axis::Y => "Y",
+            Axis::Z => "Z",
+        }
+    }
+}
+
+/// Body posture from the tilt of the upright axis against gravity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Posture {
+    /// tilted less than 30°
+    Upright,
+    /// tilted 30° to 60°
+    Reclined,
+    /// tilted more than 60°
+    Lying,
+}
+
+impl Posture {
+    pub const ALL: [Posture; 3] = [Posture::Upright, Posture::Reclined, Posture::Lying];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Posture::Upright => "Upright",
+            Posture::Reclined => "Reclined",
+            Posture::Lying => "Lying",
+        }
+    }
+
+    pub fn from_tilt(tilt: f64) -> Posture {
+        if tilt < 30.0 {
+            Posture::Upright
+        } else if tilt < 60.0 {
+            Posture::Reclined
+        } else {
+            Posture::Lying
+        }
+    }
+}
+
+/// Measures of movement and posture derived from the three axes of an accelerometer
+#[derive(Clone, Debug)]
+pub struct Activity {
+    /// length of the acceleration vector in g
+    pub magnitude: UniformSignal,
+    /// angle between the upright axis and gravity in degrees
+    pub tilt: UniformSignal,
+    /// Euclidean norm minus one g, negative values set to zero, mean per epoch in mg
+    pub enmo: Vec<(f64, f64)>,
+    /// rectified band passed vector magnitude integrated per epoch in mg·s
+    pub counts: Vec<(f64, f64)>,
+    /// positions of the detected steps
+    pub steps: Vec<f64>,
+    /// steps per minute in each epoch
+    pub cadence: Vec<(f64, f64)>,
+}
+
+impl Activity {
+    /// Share of the time in each posture
+    pub fn posture_shares(&self) -> Vec<(Posture, f64)> {
+        let tilts: Vec<f64> = self
+            .tilt
+            .samples
+            .iter()
+            .copied()
+            .filter(|t| t.is_finite())
+            .collect();
+        let n = tilts.len().max(1) as f64;
+        Posture::ALL
+            .into_iter()
+            .map(|posture| {
+                let count = tilts
+                    .iter()
+                    .filter(|t| Posture::from_tilt(**t) == posture)
+                    .count();
+                (posture, count as f64 / n)
+            })
+            .collect()
+    }
+}
+
+/// Derive the activity measures from the axes `x`, `y` and `z` in units of `units_per_g`,
+/// with values per epoch of `epoch` seconds. `y` and `z` are taken at the sample times of `x`.
+///
+/// The activity counts approximate the counts of hip-worn actigraphs, but are not calibrated
+/// to any device; they are only comparable between recordings of the same sensor.
+pub fn activity(
+    x: &UniformSignal,
+    y: &UniformSignal,
+    z: &UniformSignal,
+    units_per_g: f64,
+    epoch: f64,
+    upright_axis: Axis,
+) -> Activity {
+    let fs = x.samples_per_second;
+    let at_x = |signal: &UniformSignal| -> Vec<f64> {
+        (0..x.samples.len())
+            .map(|idx| signal.samples[signal.index(x.position(idx))] / units_per_g)
+            .collect()
+    };
+    let axes = [at_x(x), at_x(y), at_x(z)];
+    let magnitude: Vec<f64> = (0..x.samples.len())
+        .map(|idx| axes.iter().map(|a| a[idx].powi(2)).sum::<f64>().sqrt())
+        .collect();
+
+    let gravity: Vec<Vec<f64>> = axes
+        .iter()
+        .map(|a| filtfilt_missing(&[Biquad::low_pass(GRAVITY_CUTOFF, fs)], a))
+        .collect();
+    let upright = match upright_axis {
+        Axis::X => 0,
+        Axis::Y => 1,
+        Axis::Z => 2,
+    };
+    let tilt: Vec<f64> = (0..x.samples.len())
+        .map(|idx| {
+            let norm = gravity.iter().map(|g| g[idx].powi(2)).sum::<f64>().sqrt();
+            (gravity[upright][idx].abs() / norm)
+                .min(1.0)
+                .acos()
+                .to_degrees()
+        })
+        .collect();
+
+    let band_pass = |(low, high): (f64, f64)| {
+        filtfilt_missing(
+            &[Biquad::high_pass(low, fs), Biquad::low_pass(high, fs)],
+            &magnitude,
+        )
+    };
+    let movement = band_pass(COUNTS_BAND);
+    let steps = detect_steps(&band_pass(STEP_BAND), fs);
+
+    let epoch_samples = ((epoch * fs).round() as usize).max(1);
+    let center = |idx: usize| x.position(idx) + epoch / 2.0;
+    let epochs: Vec<usize> = (0..magnitude.len())
+        .step_by(epoch_samples)
+        .filter(|first| first + epoch_samples <= magnitude.len())
+        // epochs with dropouts are left out
+        .filter(|first| {
+            magnitude[*first..first + epoch_samples]
+                .iter()
+                .all(|v| v.is_finite())
+        })
+        .collect();
+    let enmo = epochs
+        .iter()
+        .map(|first| {
+            let values = &magnitude[*first..first + epoch_samples];
+            let mean = values.iter().map(|v| (v - 1.0).max(0.0)).sum::<f64>() / values.len() as f64;
+            (center(*first), mean * 1E3)
+        })
+        .collect();
+    let counts = epochs
+        .iter()
+        .map(|first| {
+            let values = &movement[*first..first + epoch_samples];
+            (
+                center(*first),
+                values.iter().map(|v| v.abs()).sum::<f64>() / fs * 1E3,
+            )
+        })
+        .collect();
+    let step_positions: Vec<f64> = steps.iter().map(|idx| x.position(*idx)).collect();
+    let cadence = epochs
+        .iter()
+        .map(|first| {
+            let (start, end) = (x.position(*first), x.position(first + epoch_samples));
+            let n = step_positions
+                .iter()
+                .filter(|p| **p >= start && **p < end)
+                .count();
+            (center(*first), n as f64 * 60.0 / epoch)
+        })
+        .collect();
+
+    let uniform = |samples: Vec<f64>| UniformSignal {
+        samples,
+        samples_per_second: fs,
+        start: x.start,
+    };
+    Activity {
+        magnitude: uniform(magnitude),
+        tilt: uniform(tilt),
+        enmo,
+        counts,
+        steps: step_positions,
+        cadence,
+    }
+}
+
+/// Indices of the steps in the band passed vector magnitude: peaks above the threshold
+/// in runs of regularly spaced peaks
+fn detect_steps(signal: &[f64], samples_per_second: f64) -> Vec<usize> {
+    let min_distance = (STEP_INTERVAL.0 * samples_per_second).round() as usize;
+    let max_distance = (STEP_INTERVAL.1 * samples_per_second).round() as usize;
+    let mut peaks: Vec<usize> = vec![];
+    for n in 1..signal.len().saturating_sub(1) {
+        if signal[n] < STEP_THRESHOLD || signal[n] <= signal[n - 1] || signal[n] < signal[n + 1] {
+            continue;
+        }
+        match peaks.last_mut() {
+            Some(last) if n - *last < min_distance => {
+                if signal[n] > signal[*last] {
+                    *last = n;
+                }
+            }
+            _ => peaks.push(n),
+        }
+    }
+    // keep the runs of at least MIN_STEPS peaks which are at most max_distance apart
+    let mut steps = vec![];
+    let mut run: Vec<usize> = vec![];
+    for peak in peaks {
+        if run.last().is_some_and(|last| peak - last > max_distance) {
+            if run.len() >= MIN_STEPS {
+                steps.append(&mut run);
+            }
+            run.clear();
+        }
+        run.push(peak);
+    }
+    if run.len() >= MIN_STEPS {
+        steps.append(&mut run);
+    }
+    steps
+}
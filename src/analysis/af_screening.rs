@@ -0,0 +1,204 @@
+use crate::analysis::hrv::{standard_deviation, RrSeries};
+use crate::analysis::qrs_detection::MAX_RR;
+
+/// number of bins of the RR histogram for the Shannon entropy
+const ENTROPY_BINS: usize = 16;
+
+/// Parameters of the RR irregularity screening
+#[derive(Clone, Copy, Debug)]
+pub struct AfScreeningSettings {
+    /// RR intervals per window
+    pub window: usize,
+    /// RR intervals the window moves per step
+    pub step: usize,
+    /// windows with a higher RMSSD divided by the mean RR are irregular
+    pub nrmssd_threshold: f64,
+    /// windows with a higher normalised Shannon entropy of the RR histogram are irregular
+    pub entropy_threshold: f64,
+    /// shorter runs of irregular windows in seconds are no episode
+    pub min_episode: f64,
+}
+
+impl Default for AfScreeningSettings {
+    fn default() -> Self {
+        AfScreeningSettings {
+            window: 64,
+            step: 16,
+            nrmssd_threshold: 0.1,
+            entropy_threshold: 0.7,
+            min_episode: 30.0,
+        }
+    }
+}
+
+/// Irregularity measures of one window of RR intervals
+#[derive(Clone, Copy, Debug)]
+pub struct AfWindow {
+    pub start: f64,
+    pub end: f64,
+    /// coefficient of variation, standard deviation divided by the mean RR
+    pub cov: f64,
+    /// RMSSD divided by the mean RR
+    pub nrmssd: f64,
+    /// Shannon entropy of the RR histogram, divided by its maximum
+    pub entropy: f64,
+    /// the number of turning points is as expected for a random series
+    pub random: bool,
+    pub irregular: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AfScreening {
+    pub windows: Vec<AfWindow>,
+    /// start and end of the runs of irregular windows lasting at least the minimum episode
+    pub episodes: Vec<(f64, f64)>,
+    /// time covered by the scored windows in seconds
+    pub analysed_duration: f64,
+    /// time in episodes in seconds
+    pub episode_duration: f64,
+}
+
+impl AfScreening {
+    /// Share of the analysed time in episodes in percent
+    pub fn burden(&self) -> f64 {
+        if self.analysed_duration > 0.0 {
+            100.0 * self.episode_duration / self.analysed_duration
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Score sliding windows of an RR series (intervals in ms) for irregularity as in atrial
+/// fibrillation, after Dash S, Chon KH, Lu S, Raeder EA: "Automatic Real Time Detection of
+/// Atrial Fibrillation", Ann Biomed Eng 37(9), 2009.
+///
+/// A window is irregular if its normalised RMSSD and the Shannon entropy of its RR histogram
+/// exceed the thresholds and its turning points are consistent with a random series. The
+/// shortest and longest sixteenth of the intervals are left out of the measures, so single
+/// ectopic beats don't make a window irregular.
+pub fn screen_af(series: &RrSeries, settings: &AfScreeningSettings) -> AfScreening {
+    let window = settings.window.max(8);
+    let step = settings.step.max(1);
+    let n = series.len();
+    let mut screening = AfScreening::default();
+    if n < window {
+        return screening;
+    }
+    for first in (0..=n - window).step_by(step) {
+        let rr = &series.intervals[first..first + window];
+        let positions = &series.positions[first..first + window];
+        // the window starts where its first interval starts
+        let start = positions[0] - rr[0] / 1E3;
+        let end = positions[window - 1];
+        let duration = rr.iter().sum::<f64>() / 1E3;
+        // intervals are missing if the window is longer than the sum of its intervals
+        if rr
+            .iter()
+            .any(|rr| !(rr.is_finite() && *rr > 0.0 && *rr < MAX_RR * 1E3))
+            || end - start > 1.1 * duration + 1.0
+        {
+            continue;
+        }
+        screening
+            .windows
+            .push(score_window(rr, start, end, settings));
+    }
+    screening.analysed_duration =
+        union_duration(screening.windows.iter().map(|w| (w.start, w.end)));
+    screening.episodes = merge(
+        screening
+            .windows
+            .iter()
+            .filter(|w| w.irregular)
+            .map(|w| (w.start, w.end)),
+    )
+    .into_iter()
+    .filter(|(start, end)| end - start >= settings.min_episode)
+    .collect();
+    screening.episode_duration = screening.episodes.iter().map(|(s, e)| e - s).sum();
+    screening
+}
+
+fn score_window(rr: &[f64], start: f64, end: f64, settings: &AfScreeningSettings) -> AfWindow {
+    let mean = rr.iter().sum::<f64>() / rr.len() as f64;
+    let cov = standard_deviation(rr) / mean;
+
+    // without the extremes, the differences of the remaining intervals in their order
+    let trim = rr.len() / 16;
+    let mut sorted = rr.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let (low, high) = (sorted[trim], sorted[rr.len() - 1 - trim]);
+    let trimmed: Vec<f64> = rr
+        .iter()
+        .copied()
+        .filter(|rr| *rr >= low && *rr <= high)
+        .collect();
+    let trimmed_mean = trimmed.iter().sum::<f64>() / trimmed.len() as f64;
+    let differences: Vec<f64> = trimmed.windows(2).map(|w| w[1] - w[0]).collect();
+    let rmssd =
+        (differences.iter().map(|d| d * d).sum::<f64>() / differences.len().max(1) as f64).sqrt();
+    let nrmssd = rmssd / trimmed_mean;
+    let entropy = shannon_entropy(&trimmed);
+
+    // turning points of a random series: mean (2n - 4) / 3, variance (16n - 29) / 90
+    let turning_points = rr
+        .windows(3)
+        .filter(|w| (w[1] > w[0] && w[1] > w[2]) || (w[1] < w[0] && w[1] < w[2]))
+        .count() as f64;
+    let n = rr.len() as f64;
+    let expected = (2.0 * n - 4.0) / 3.0;
+    let deviation = ((16.0 * n - 29.0) / 90.0).sqrt();
+    let random = (turning_points - expected).abs() <= 1.96 * deviation;
+
+    AfWindow {
+        start,
+        end,
+        cov,
+        nrmssd,
+        entropy,
+        random,
+        irregular: nrmssd > settings.nrmssd_threshold
+            && entropy > settings.entropy_threshold
+            && random,
+    }
+}
+
+/// Shannon entropy of a histogram of the values between their minimum and maximum,
+/// divided by the entropy of a uniform distribution
+fn shannon_entropy(values: &[f64]) -> f64 {
+    let min = values.iter().fold(f64::INFINITY, |a, b| a.min(*b));
+    let max = values.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b));
+    if max <= min {
+        return 0.0;
+    }
+    let mut counts = [0usize; ENTROPY_BINS];
+    values.iter().for_each(|v| {
+        let idx = ((v - min) / (max - min) * ENTROPY_BINS as f64) as usize;
+        counts[idx.min(ENTROPY_BINS - 1)] += 1;
+    });
+    let n = values.len() as f64;
+    -counts
+        .iter()
+        .filter(|c| **c > 0)
+        .map(|c| *c as f64 / n)
+        .map(|p| p * p.ln())
+        .sum::<f64>()
+        / (ENTROPY_BINS as f64).ln()
+}
+
+/// Overlapping ranges, sorted by start, merged into disjoint ranges
+fn merge(ranges: impl Iterator<Item = (f64, f64)>) -> Vec<(f64, f64)> {
+    let mut merged: Vec<(f64, f64)> = vec![];
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn union_duration(ranges: impl Iterator<Item = (f64, f64)>) -> f64 {
+    merge(ranges).iter().map(|(start, end)| end - start).sum()
+}
@@ -0,0 +1,76 @@
+use crate::data_structures::DrawableChannel;
+
+/// Agreement between two measurements of the same quantity after
+/// Bland JM, Altman DG: "Statistical methods for assessing agreement between two methods
+/// of clinical measurement", Lancet 1986.
+#[derive(Clone, Copy, Debug)]
+pub struct Agreement {
+    pub n: usize,
+    /// mean of the differences a - b
+    pub bias: f64,
+    /// standard deviation of the differences
+    pub sd: f64,
+    pub lower_limit: f64,
+    pub upper_limit: f64,
+    pub mean_absolute_error: f64,
+    pub correlation: f64,
+}
+
+/// A pair of values of both channels at the same position
+#[derive(Clone, Copy, Debug)]
+pub struct Pair {
+    pub position: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl Pair {
+    pub fn mean(&self) -> f64 {
+        (self.a + self.b) / 2.0
+    }
+
+    pub fn difference(&self) -> f64 {
+        self.a - self.b
+    }
+}
+
+/// Pair every point of `b` with the interpolated value of channel `a` at the same position
+pub fn paired_values(a: &mut dyn DrawableChannel, b: &[(f64, f64)]) -> Vec<Pair> {
+    b.iter()
+        .filter(|(_, y)| y.is_finite())
+        .filter_map(|(x, y)| {
+            a.value_at(*x).filter(|v| v.is_finite()).map(|v| Pair {
+                position: *x,
+                a: v,
+                b: *y,
+            })
+        })
+        .collect()
+}
+
+pub fn bland_altman(pairs: &[Pair]) -> Option<Agreement> {
+    if pairs.len() < 2 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let differences: Vec<f64> = pairs.iter().map(|p| p.difference()).collect();
+    let bias = differences.iter().sum::<f64>() / n;
+    let sd = (differences.iter().map(|d| (d - bias).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+    let mean_absolute_error = differences.iter().map(|d| d.abs()).sum::<f64>() / n;
+
+    let mean_a = pairs.iter().map(|p| p.a).sum::<f64>() / n;
+    let mean_b = pairs.iter().map(|p| p.b).sum::<f64>() / n;
+    let covariance: f64 = pairs.iter().map(|p| (p.a - mean_a) * (p.b - mean_b)).sum();
+    let var_a: f64 = pairs.iter().map(|p| (p.a - mean_a).powi(2)).sum();
+    let var_b: f64 = pairs.iter().map(|p| (p.b - mean_b).powi(2)).sum();
+
+    Some(Agreement {
+        n: pairs.len(),
+        bias,
+        sd,
+        lower_limit: bias - 1.96 * sd,
+        upper_limit: bias + 1.96 * sd,
+        mean_absolute_error,
+        correlation: covariance / (var_a * var_b).sqrt(),
+    })
+}
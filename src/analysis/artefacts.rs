@@ -0,0 +1,320 @@
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::hrv::RrSeries;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArtefactKind {
+    Ectopic,
+    /// interval spanning a beat which was not detected
+    Missed,
+    /// one of two intervals split by a false beat
+    Extra,
+    Long,
+    Short,
+}
+
+impl ArtefactKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArtefactKind::Ectopic => "ectopic",
+            ArtefactKind::Missed => "missed beat",
+            ArtefactKind::Extra => "extra beat",
+            ArtefactKind::Long => "long",
+            ArtefactKind::Short => "short",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ArtefactDetection {
+    /// Lipponen JA, Tarvainen MP: "A robust algorithm for heart rate variability time series
+    /// artefact correction using novel beat classification", J Med Eng Technol 43(3), 2019
+    LipponenTarvainen,
+    /// intervals deviating from the local median by more than the given fraction
+    Threshold(f64),
+}
+
+impl ArtefactDetection {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ArtefactDetection::LipponenTarvainen => "Lipponen-Tarvainen",
+            ArtefactDetection::Threshold(_) => "Threshold",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CorrectionMethod {
+    /// remove the interval from the series
+    Delete,
+    /// replace the interval by the interpolation of the neighbouring valid intervals
+    Interpolate,
+}
+
+impl CorrectionMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CorrectionMethod::Delete => "Delete",
+            CorrectionMethod::Interpolate => "Interpolate",
+        }
+    }
+}
+
+/// A corrected RR interval, `corrected` is None if the interval was deleted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Correction {
+    pub position: f64,
+    pub kind: ArtefactKind,
+    pub original: f64,
+    pub corrected: Option<f64>,
+}
+
+/// The corrections applied to the RR series of one channel, sorted by position
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CorrectionLog {
+    pub corrections: Vec<Correction>,
+}
+
+/// intervals are matched by position with this tolerance in seconds when a log is applied
+const POSITION_TOLERANCE: f64 = 1E-3;
+
+impl CorrectionLog {
+    /// Detect the artefacts of `series` and correct them with `method`
+    pub fn new(
+        series: &RrSeries,
+        detection: ArtefactDetection,
+        method: CorrectionMethod,
+    ) -> CorrectionLog {
+        let artefacts = detect_artefacts(series, detection);
+        let is_artefact = |idx: usize| artefacts.iter().any(|(i, _)| *i == idx);
+        let corrections = artefacts
+            .iter()
+            .map(|(idx, kind)| {
+                let corrected = match method {
+                    CorrectionMethod::Delete => None,
+                    CorrectionMethod::Interpolate => {
+                        let previous = (0..*idx).rev().find(|i| !is_artefact(*i));
+                        let next = (idx + 1..series.len()).find(|i| !is_artefact(*i));
+                        interpolate(series, *idx, previous, next)
+                    }
+                };
+                Correction {
+                    position: series.positions[*idx],
+                    kind: *kind,
+                    original: series.intervals[*idx],
+                    corrected,
+                }
+            })
+            .collect();
+        CorrectionLog { corrections }
+    }
+
+    /// The corrected series, intervals without a matching position are left unchanged.
+    /// The interval after a deleted one doesn't follow the previous interval anymore.
+    pub fn apply(&self, series: &RrSeries) -> RrSeries {
+        let mut corrected = RrSeries::default();
+        let mut follows_previous = true;
+        for ((position, rr), follows) in series
+            .positions
+            .iter()
+            .zip(series.intervals.iter())
+            .zip(series.follows_previous.iter())
+        {
+            follows_previous &= follows;
+            // the corrections are sorted by position
+            let idx = self
+                .corrections
+                .partition_point(|c| c.position < position - POSITION_TOLERANCE);
+            let correction = self
+                .corrections
+                .get(idx)
+                .filter(|c| (c.position - position).abs() < POSITION_TOLERANCE);
+            match correction {
+                Some(Correction {
+                    corrected: None, ..
+                }) => {
+                    follows_previous = false;
+                    continue;
+                }
+                Some(Correction {
+                    corrected: Some(value),
+                    ..
+                }) => corrected.push(*position, *value, follows_previous),
+                None => corrected.push(*position, *rr, follows_previous),
+            }
+            follows_previous = true;
+        }
+        corrected
+    }
+
+    /// Number of corrections of each kind
+    pub fn counts(&self) -> Vec<(ArtefactKind, usize)> {
+        [
+            ArtefactKind::Ectopic,
+            ArtefactKind::Missed,
+            ArtefactKind::Extra,
+            ArtefactKind::Long,
+            ArtefactKind::Short,
+        ]
+        .into_iter()
+        .map(|kind| {
+            (
+                kind,
+                self.corrections.iter().filter(|c| c.kind == kind).count(),
+            )
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect()
+    }
+}
+
+fn interpolate(
+    series: &RrSeries,
+    idx: usize,
+    previous: Option<usize>,
+    next: Option<usize>,
+) -> Option<f64> {
+    let value = |i: usize| (series.positions[i], series.intervals[i]);
+    match (previous.map(value), next.map(value)) {
+        (Some((x0, y0)), Some((x1, y1))) if x1 > x0 => {
+            Some(y0 + (y1 - y0) * (series.positions[idx] - x0) / (x1 - x0))
+        }
+        (Some((_, y)), _) | (_, Some((_, y))) => Some(y),
+        (None, None) => None,
+    }
+}
+
+/// Indices and kinds of the artefacts in `series`
+pub fn detect_artefacts(
+    series: &RrSeries,
+    detection: ArtefactDetection,
+) -> Vec<(usize, ArtefactKind)> {
+    if series.len() < 3 {
+        return vec![];
+    }
+    match detection {
+        ArtefactDetection::LipponenTarvainen => lipponen_tarvainen(&series.intervals),
+        ArtefactDetection::Threshold(threshold) => {
+            let medians = moving_median(&series.intervals, 5);
+            series
+                .intervals
+                .iter()
+                .zip(medians.iter())
+                .enumerate()
+                .filter(|(_, (rr, median))| (*rr - *median).abs() > threshold * *median)
+                .map(|(idx, (rr, median))| {
+                    let kind = if rr > median {
+                        ArtefactKind::Long
+                    } else {
+                        ArtefactKind::Short
+                    };
+                    (idx, kind)
+                })
+                .collect()
+        }
+    }
+}
+
+fn lipponen_tarvainen(rr: &[f64]) -> Vec<(usize, ArtefactKind)> {
+    const C1: f64 = 0.13;
+    const C2: f64 = 0.17;
+    // the thresholds are 5.2 times the quartile deviation of the surrounding 91 beats
+    let threshold = |values: &[f64]| -> Vec<f64> {
+        let absolute: Vec<f64> = values.iter().map(|v| v.abs()).collect();
+        moving_quartile_deviation(&absolute, 45)
+            .iter()
+            .map(|qd| (5.2 * qd).max(f64::EPSILON))
+            .collect()
+    };
+
+    let mut drr: Vec<f64> = vec![0.0; rr.len()];
+    for i in 1..rr.len() {
+        drr[i] = rr[i] - rr[i - 1];
+    }
+    drr[0] = drr[1..].iter().sum::<f64>() / (rr.len() - 1) as f64;
+    let th1 = threshold(&drr);
+    let drrs: Vec<f64> = drr.iter().zip(th1.iter()).map(|(d, t)| d / t).collect();
+
+    let medians = moving_median(rr, 5);
+    let mrr: Vec<f64> = rr
+        .iter()
+        .zip(medians.iter())
+        .map(|(rr, median)| {
+            let m = rr - median;
+            // short intervals are weighted double
+            if m < 0.0 {
+                2.0 * m
+            } else {
+                m
+            }
+        })
+        .collect();
+    let th2 = threshold(&mrr);
+    let mrrs: Vec<f64> = mrr.iter().zip(th2.iter()).map(|(m, t)| m / t).collect();
+
+    let at = |values: &[f64], idx: usize| values.get(idx).copied().unwrap_or(0.0);
+    let mut artefacts = vec![];
+    let mut i = 0;
+    while i < rr.len() {
+        let s11 = drrs[i];
+        let (previous, next) = (
+            i.checked_sub(1).map(|j| drrs[j]).unwrap_or(0.0),
+            at(&drrs, i + 1),
+        );
+        let s12 = if s11 > 0.0 {
+            previous.max(next)
+        } else {
+            previous.min(next)
+        };
+        let s22 = if s11 >= 0.0 {
+            at(&drrs, i + 1).min(at(&drrs, i + 2))
+        } else {
+            at(&drrs, i + 1).max(at(&drrs, i + 2))
+        };
+
+        if (s11 > 1.0 && s12 < -C1 * s11 - C2) || (s11 < -1.0 && s12 > -C1 * s11 + C2) {
+            artefacts.push((i, ArtefactKind::Ectopic));
+        } else if (s11 > 1.0 && s22 < -1.0) || (s11 < -1.0 && s22 > 1.0) || mrrs[i].abs() > 3.0 {
+            if (rr[i] / 2.0 - medians[i]).abs() < th2[i] {
+                artefacts.push((i, ArtefactKind::Missed));
+            } else if i + 1 < rr.len() && (rr[i] + rr[i + 1] - medians[i]).abs() < th2[i] {
+                artefacts.push((i, ArtefactKind::Extra));
+                artefacts.push((i + 1, ArtefactKind::Extra));
+                i += 1;
+            } else if rr[i] > medians[i] {
+                artefacts.push((i, ArtefactKind::Long));
+            } else {
+                artefacts.push((i, ArtefactKind::Short));
+            }
+        }
+        i += 1;
+    }
+    artefacts
+}
+
+/// Median of `values[i - half_width..=i + half_width]` (truncated at the edges)
+fn moving_median(values: &[f64], half_width: usize) -> Vec<f64> {
+    (0..values.len())
+        .map(|i| {
+            let mut window = values
+                [i.saturating_sub(half_width)..(i + half_width + 1).min(values.len())]
+                .to_vec();
+            window.sort_by(|a, b| a.total_cmp(b));
+            window[window.len() / 2]
+        })
+        .collect()
+}
+
+/// Half of the interquartile range of `values[i - half_width..=i + half_width]`
+fn moving_quartile_deviation(values: &[f64], half_width: usize) -> Vec<f64> {
+    (0..values.len())
+        .map(|i| {
+            let mut window = values
+                [i.saturating_sub(half_width)..(i + half_width + 1).min(values.len())]
+                .to_vec();
+            window.sort_by(|a, b| a.total_cmp(b));
+            let quartile = |q: f64| window[((window.len() - 1) as f64 * q).round() as usize];
+            (quartile(0.75) - quartile(0.25)) / 2.0
+        })
+        .collect()
+}
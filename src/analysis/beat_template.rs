@@ -0,0 +1,141 @@
+use crate::analysis::resample::UniformSignal;
+
+/// Windows of a signal around each beat, aligned on the R peak
+#[derive(Clone, Debug, Default)]
+pub struct AlignedBeats {
+    /// time relative to the R peak in seconds, the same for all windows
+    pub offsets: Vec<f64>,
+    /// positions of the beats whose window lies completely within the signal without gaps
+    pub positions: Vec<f64>,
+    /// the samples of the window of each beat, parallel to `positions`
+    pub windows: Vec<Vec<f64>>,
+}
+
+impl AlignedBeats {
+    /// Cut windows from `pre` seconds before to `post` seconds after each beat out of `signal`
+    pub fn new(signal: &UniformSignal, beats: &[f64], pre: f64, post: f64) -> AlignedBeats {
+        let pre_samples = (pre * signal.samples_per_second).round() as i64;
+        let post_samples = (post * signal.samples_per_second).round() as i64;
+        let mut aligned = AlignedBeats {
+            offsets: (-pre_samples..=post_samples)
+                .map(|n| n as f64 / signal.samples_per_second)
+                .collect(),
+            ..Default::default()
+        };
+        beats.iter().for_each(|position| {
+            let center = ((position - signal.start) * signal.samples_per_second).round() as i64;
+            let (first, last) = (center - pre_samples, center + post_samples);
+            if first < 0 || last >= signal.samples.len() as i64 {
+                return;
+            }
+            let window = &signal.samples[first as usize..=last as usize];
+            if window.iter().all(|v| v.is_finite()) {
+                aligned.positions.push(*position);
+                aligned.windows.push(window.to_vec());
+            }
+        });
+        aligned
+    }
+
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TemplateKind {
+    Median,
+    Average,
+}
+
+impl TemplateKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TemplateKind::Median => "Median",
+            TemplateKind::Average => "Average",
+        }
+    }
+}
+
+/// Representative beat, with the range of 95 % of the beats (2.5th to 97.5th percentile)
+#[derive(Clone, Debug, Default)]
+pub struct BeatTemplate {
+    pub offsets: Vec<f64>,
+    pub values: Vec<f64>,
+    pub lower: Vec<f64>,
+    pub upper: Vec<f64>,
+    /// number of beats the template was calculated from
+    pub n_beats: usize,
+}
+
+/// Template of the beats for which `included` is true
+pub fn beat_template(
+    beats: &AlignedBeats,
+    included: &[bool],
+    kind: TemplateKind,
+) -> Option<BeatTemplate> {
+    let windows: Vec<&Vec<f64>> = beats
+        .windows
+        .iter()
+        .zip(included.iter())
+        .filter(|(_, included)| **included)
+        .map(|(window, _)| window)
+        .collect();
+    if windows.is_empty() {
+        return None;
+    }
+    let mut template = BeatTemplate {
+        offsets: beats.offsets.clone(),
+        n_beats: windows.len(),
+        ..Default::default()
+    };
+    let percentile =
+        |sorted: &[f64], q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+    for idx in 0..beats.offsets.len() {
+        let mut column: Vec<f64> = windows.iter().map(|w| w[idx]).collect();
+        column.sort_by(|a, b| a.total_cmp(b));
+        template.values.push(match kind {
+            TemplateKind::Median => percentile(&column, 0.5),
+            TemplateKind::Average => column.iter().sum::<f64>() / column.len() as f64,
+        });
+        template.lower.push(percentile(&column, 0.025));
+        template.upper.push(percentile(&column, 0.975));
+    }
+    Some(template)
+}
+
+/// Pearson correlation of two windows of the same length
+pub fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len().min(b.len()) as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let (mut covariance, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    a.iter().zip(b.iter()).for_each(|(a, b)| {
+        covariance += (a - mean_a) * (b - mean_b);
+        var_a += (a - mean_a).powi(2);
+        var_b += (b - mean_b).powi(2);
+    });
+    if var_a > 0.0 && var_b > 0.0 {
+        covariance / (var_a * var_b).sqrt()
+    } else {
+        0.0
+    }
+}
+
+/// Correlation of each beat with the median template of all beats, outliers like ectopic
+/// beats, noise or misdetections correlate poorly
+pub fn template_correlations(beats: &AlignedBeats) -> Vec<f64> {
+    let Some(template) = beat_template(beats, &vec![true; beats.len()], TemplateKind::Median)
+    else {
+        return vec![];
+    };
+    beats
+        .windows
+        .iter()
+        .map(|window| correlation(window, &template.values))
+        .collect()
+}
@@ -0,0 +1,300 @@
+use crate::analysis::beat_template::BeatTemplate;
+use crate::analysis::qrs_detection::MAX_RR;
+use crate::analysis::resample::UniformSignal;
+
+/// The wave boundaries of a beat
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wave {
+    POnset,
+    POffset,
+    QrsOnset,
+    QrsOffset,
+    TEnd,
+}
+
+impl Wave {
+    pub const ALL: [Wave; 5] = [
+        Wave::POnset,
+        Wave::POffset,
+        Wave::QrsOnset,
+        Wave::QrsOffset,
+        Wave::TEnd,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Wave::POnset => "P onset",
+            Wave::POffset => "P offset",
+            Wave::QrsOnset => "QRS onset",
+            Wave::QrsOffset => "QRS offset",
+            Wave::TEnd => "T end",
+        }
+    }
+}
+
+/// Positions of the wave boundaries of one beat, None if they couldn't be found
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Fiducials {
+    pub r_peak: f64,
+    pub p_onset: Option<f64>,
+    pub p_offset: Option<f64>,
+    pub qrs_onset: Option<f64>,
+    pub qrs_offset: Option<f64>,
+    pub t_end: Option<f64>,
+}
+
+impl Fiducials {
+    pub fn get(&self, wave: Wave) -> Option<f64> {
+        match wave {
+            Wave::POnset => self.p_onset,
+            Wave::POffset => self.p_offset,
+            Wave::QrsOnset => self.qrs_onset,
+            Wave::QrsOffset => self.qrs_offset,
+            Wave::TEnd => self.t_end,
+        }
+    }
+
+    pub fn set(&mut self, wave: Wave, position: f64) {
+        let value = Some(position);
+        match wave {
+            Wave::POnset => self.p_onset = value,
+            Wave::POffset => self.p_offset = value,
+            Wave::QrsOnset => self.qrs_onset = value,
+            Wave::QrsOffset => self.qrs_offset = value,
+            Wave::TEnd => self.t_end = value,
+        }
+    }
+
+    /// PR, QRS and QT interval in seconds, `rr` is the preceding RR interval used for QTc
+    pub fn intervals(&self, rr: Option<f64>) -> Intervals {
+        let difference = |a: Option<f64>, b: Option<f64>| Some(b? - a?).filter(|d| *d > 0.0);
+        Intervals {
+            rr,
+            pr: difference(self.p_onset, self.qrs_onset),
+            qrs: difference(self.qrs_onset, self.qrs_offset),
+            qt: difference(self.qrs_onset, self.t_end),
+        }
+    }
+}
+
+/// Intervals of one beat in seconds
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Intervals {
+    pub rr: Option<f64>,
+    pub pr: Option<f64>,
+    pub qrs: Option<f64>,
+    pub qt: Option<f64>,
+}
+
+impl Intervals {
+    pub fn qtc(&self, formula: QtcFormula) -> Option<f64> {
+        Some(formula.apply(self.qt?, self.rr?))
+    }
+}
+
+/// Heart rate correction of the QT interval
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QtcFormula {
+    Bazett,
+    Fridericia,
+    Framingham,
+    Hodges,
+}
+
+impl QtcFormula {
+    pub const ALL: [QtcFormula; 4] = [
+        QtcFormula::Bazett,
+        QtcFormula::Fridericia,
+        QtcFormula::Framingham,
+        QtcFormula::Hodges,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QtcFormula::Bazett => "Bazett",
+            QtcFormula::Fridericia => "Fridericia",
+            QtcFormula::Framingham => "Framingham",
+            QtcFormula::Hodges => "Hodges",
+        }
+    }
+
+    /// Corrected QT in seconds for `qt` and `rr` in seconds
+    pub fn apply(&self, qt: f64, rr: f64) -> f64 {
+        match self {
+            QtcFormula::Bazett => qt / rr.sqrt(),
+            QtcFormula::Fridericia => qt / rr.cbrt(),
+            QtcFormula::Framingham => qt + 0.154 * (1.0 - rr),
+            // 1.75 ms per bpm above 60 bpm
+            QtcFormula::Hodges => qt + 1.75E-3 * (60.0 / rr - 60.0),
+        }
+    }
+}
+
+/// Delineate each beat of `signal`, the beats must be sorted
+pub fn delineate_beats(signal: &UniformSignal, beats: &[f64]) -> Vec<Fiducials> {
+    let rr = |a: f64, b: f64| Some(b - a).filter(|rr| *rr > 0.0 && *rr < MAX_RR);
+    beats
+        .iter()
+        .enumerate()
+        .map(|(idx, r_peak)| {
+            let previous = idx.checked_sub(1).and_then(|i| rr(beats[i], *r_peak));
+            let next = beats.get(idx + 1).and_then(|next| rr(*r_peak, *next));
+            let r_index = signal.index(*r_peak);
+            let offsets = delineate(
+                &signal.samples,
+                signal.samples_per_second,
+                r_index,
+                next.or(previous).unwrap_or(1.0),
+            );
+            let shift = |offset: Option<f64>| offset.map(|o| signal.position(r_index) + o);
+            Fiducials {
+                r_peak: *r_peak,
+                p_onset: shift(offsets.p_onset),
+                p_offset: shift(offsets.p_offset),
+                qrs_onset: shift(offsets.qrs_onset),
+                qrs_offset: shift(offsets.qrs_offset),
+                t_end: shift(offsets.t_end),
+            }
+        })
+        .collect()
+}
+
+/// Intervals of each beat, with the RR interval to the preceding beat
+pub fn beat_intervals(fiducials: &[Fiducials]) -> Vec<Intervals> {
+    fiducials
+        .iter()
+        .enumerate()
+        .map(|(idx, f)| {
+            let rr = idx
+                .checked_sub(1)
+                .map(|previous| f.r_peak - fiducials[previous].r_peak)
+                .filter(|rr| *rr > 0.0 && *rr < MAX_RR);
+            f.intervals(rr)
+        })
+        .collect()
+}
+
+/// Delineate a beat template, the positions are relative to the R peak
+pub fn delineate_template(template: &BeatTemplate, rr: f64) -> Option<Fiducials> {
+    let r_index = template.offsets.iter().position(|o| *o >= 0.0)?;
+    let samples_per_second = 1.0 / (template.offsets.get(1)? - template.offsets.first()?);
+    Some(delineate(&template.values, samples_per_second, r_index, rr))
+}
+
+/// Find the wave boundaries around the R peak at `r_index`, positions relative to the R peak.
+///
+/// The QRS boundaries are where the slope falls below a fraction of the steepest QRS slope,
+/// the T end is where the tangent at the steepest point of the descending T limb crosses the
+/// isoelectric level, and the P boundaries are where the P wave falls below a fraction of its
+/// amplitude. `rr` limits the search windows.
+pub fn delineate(samples: &[f64], samples_per_second: f64, r_index: usize, rr: f64) -> Fiducials {
+    let mut fiducials = Fiducials::default();
+    let n = samples.len();
+    if r_index >= n || n < 3 {
+        return fiducials;
+    }
+    let ms = |ms: f64| (ms * samples_per_second / 1E3).round() as usize;
+    let offset = |idx: usize| Some((idx as f64 - r_index as f64) / samples_per_second);
+    let mut derivative = vec![0.0; n];
+    for i in 1..n - 1 {
+        derivative[i] = (samples[i + 1] - samples[i - 1]) * samples_per_second / 2.0;
+    }
+    if derivative[r_index.saturating_sub(ms(150.0))..(r_index + ms(180.0)).min(n)]
+        .iter()
+        .any(|d| !d.is_finite())
+    {
+        return fiducials;
+    }
+    // QRS boundaries, the absolute slope is averaged over 20 ms to bridge the Q and S peaks
+    let half_width = ms(10.0).max(1);
+    let envelope = |i: usize| {
+        let window = &derivative[i.saturating_sub(half_width)..(i + half_width + 1).min(n)];
+        window.iter().map(|d| d.abs()).sum::<f64>() / window.len() as f64
+    };
+    let qrs_window = r_index.saturating_sub(ms(100.0))..(r_index + ms(120.0)).min(n);
+    let max_slope = qrs_window.map(envelope).fold(0.0f64, f64::max);
+    if max_slope <= 0.0 {
+        return fiducials;
+    }
+    // the threshold stays above the noise, estimated from the typical slope around the beat
+    let mut slopes: Vec<f64> = (r_index.saturating_sub(ms(400.0))..(r_index + ms(400.0)).min(n))
+        .step_by(half_width)
+        .map(envelope)
+        .collect();
+    slopes.sort_by(|a, b| a.total_cmp(b));
+    let threshold = (0.05 * max_slope).max(1.5 * slopes[slopes.len() / 2]);
+    let earliest = r_index.saturating_sub(ms(150.0));
+    let mut onset = r_index;
+    while onset > earliest && envelope(onset) >= threshold {
+        onset -= 1;
+    }
+    let latest = (r_index + ms(180.0)).min(n - 1);
+    let mut offset_idx = r_index;
+    while offset_idx < latest && envelope(offset_idx) >= threshold {
+        offset_idx += 1;
+    }
+    fiducials.qrs_onset = offset(onset);
+    fiducials.qrs_offset = offset(offset_idx);
+
+    // the isoelectric level is taken from the end of the PR segment
+    let pr_segment = &samples[onset.saturating_sub(ms(20.0))..=onset];
+    let baseline = pr_segment.iter().sum::<f64>() / pr_segment.len() as f64;
+    let r_amplitude = (samples[r_index] - baseline).abs();
+    let peak = |range: std::ops::Range<usize>| -> Option<usize> {
+        range
+            .filter(|i| *i < n && samples[*i].is_finite())
+            .max_by(|a, b| {
+                (samples[*a] - baseline)
+                    .abs()
+                    .total_cmp(&(samples[*b] - baseline).abs())
+            })
+    };
+
+    // T end
+    let t_limit = r_index + ms(1E3 * (0.7 * rr).clamp(0.35, 0.7));
+    if let Some(t_peak) = peak(offset_idx + ms(60.0)..t_limit.min(n)) {
+        let amplitude = samples[t_peak] - baseline;
+        // steepest point of the limb returning to the isoelectric level, on the signal
+        // averaged over 20 ms so noise doesn't tilt the tangent
+        let average = |values: &[f64], i: usize| {
+            let window = &values[i.saturating_sub(half_width)..(i + half_width + 1).min(n)];
+            window.iter().sum::<f64>() / window.len() as f64
+        };
+        let descent = (t_peak..t_limit.min(n)).max_by(|a, b| {
+            (-amplitude.signum() * average(&derivative, *a))
+                .total_cmp(&(-amplitude.signum() * average(&derivative, *b)))
+        });
+        if let Some(k) = descent.filter(|_| amplitude.abs() > 0.05 * r_amplitude) {
+            let slope = average(&derivative, k);
+            if slope.abs() > 0.0 {
+                let crossing =
+                    k as f64 + (baseline - average(samples, k)) * samples_per_second / slope;
+                let crossing = crossing.clamp(t_peak as f64, (t_limit.min(n) - 1) as f64);
+                fiducials.t_end = Some((crossing - r_index as f64) / samples_per_second);
+            }
+        }
+    }
+
+    // P wave
+    let p_start = onset
+        .saturating_sub(ms(300.0))
+        .max(r_index.saturating_sub(ms(1E3 * 0.6 * rr)));
+    let p_end = onset.saturating_sub(ms(20.0));
+    if let Some(p_peak) = peak(p_start..p_end) {
+        let amplitude = (samples[p_peak] - baseline).abs();
+        if amplitude > 0.05 * r_amplitude {
+            let within_wave = |i: usize| (samples[i] - baseline).abs() > 0.15 * amplitude;
+            let mut p_onset = p_peak;
+            while p_onset > p_start && within_wave(p_onset) {
+                p_onset -= 1;
+            }
+            let mut p_offset = p_peak;
+            while p_offset < onset && within_wave(p_offset) {
+                p_offset += 1;
+            }
+            fiducials.p_onset = offset(p_onset);
+            fiducials.p_offset = offset(p_offset);
+        }
+    }
+    fiducials
+}
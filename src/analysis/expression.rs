@@ -0,0 +1,569 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use snafu::prelude::*;
+
+use crate::analysis::filters::moving_median;
+use crate::analysis::resample::UniformSignal;
+
+#[derive(Debug, Snafu)]
+pub enum ExpressionError {
+    #[snafu(display("Unexpected '{found}' at position {position}"))]
+    Unexpected { found: String, position: usize },
+    #[snafu(display("Unexpected end of the formula"))]
+    UnexpectedEnd,
+    #[snafu(display("Unknown function {name}"))]
+    UnknownFunction { name: String },
+    #[snafu(display("{name} takes {expected} arguments"))]
+    ArgumentCount { name: String, expected: usize },
+    #[snafu(display("The window of {name} has to be a number of seconds"))]
+    NonConstantWindow { name: String },
+    #[snafu(display("Unknown channel {name}"))]
+    UnknownChannel { name: String },
+    #[snafu(display("The formula references no channel"))]
+    NoChannel,
+    #[snafu(display("The formula references its own result"))]
+    SelfReference,
+    #[snafu(display("The channels don't overlap in time"))]
+    NoOverlap,
+}
+
+/// A channel calculated from other channels, e.g. `sqrt(X^2 + Y^2 + Z^2)`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DerivedChannel {
+    pub name: String,
+    pub unit: String,
+    pub formula: String,
+    /// why the channel couldn't be calculated the last time, None if it could
+    #[serde(skip)]
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+impl Operator {
+    fn apply(&self, a: f64, b: f64) -> f64 {
+        match self {
+            Operator::Add => a + b,
+            Operator::Subtract => a - b,
+            Operator::Multiply => a * b,
+            Operator::Divide => a / b,
+            Operator::Power => a.powf(b),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Function {
+    Sqrt,
+    Abs,
+    Exp,
+    Ln,
+    Log10,
+    Sin,
+    Cos,
+    Min,
+    Max,
+    /// centered moving mean over a window in seconds
+    MovingMean,
+    /// centered moving median over a window in seconds
+    MovingMedian,
+    /// difference to the previous sample per second
+    Derivative,
+}
+
+impl Function {
+    const ALL: [Function; 12] = [
+        Function::Sqrt,
+        Function::Abs,
+        Function::Exp,
+        Function::Ln,
+        Function::Log10,
+        Function::Sin,
+        Function::Cos,
+        Function::Min,
+        Function::Max,
+        Function::MovingMean,
+        Function::MovingMedian,
+        Function::Derivative,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+            Function::Exp => "exp",
+            Function::Ln => "ln",
+            Function::Log10 => "log10",
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::MovingMean => "moving_mean",
+            Function::MovingMedian => "moving_median",
+            Function::Derivative => "derivative",
+        }
+    }
+
+    fn arguments(&self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::MovingMean | Function::MovingMedian => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Short help listing the syntax of the formulas
+pub const SYNTAX_HELP: &str = "Channels by name, e.g. X or ecg, or by label in quotes, \
+    e.g. \"HR (ecg) [bpm]\"\n\
+    Operators + - * / ^ and parentheses\n\
+    sqrt, abs, exp, ln, log10, sin, cos, min(a, b), max(a, b), derivative(x)\n\
+    moving_mean(x, 0.6s), moving_median(x, 500ms)";
+
+/// A parsed formula
+#[derive(Clone, Debug)]
+pub enum Expression {
+    Number(f64),
+    Channel(String),
+    Negate(Box<Expression>),
+    Binary(Operator, Box<Expression>, Box<Expression>),
+    Call(Function, Vec<Expression>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Quoted(String),
+    Operator(Operator),
+    Open,
+    Close,
+    Comma,
+}
+
+fn tokenize(formula: &str) -> Result<Vec<(usize, Token)>, ExpressionError> {
+    let chars: Vec<char> = formula.chars().collect();
+    let mut tokens = vec![];
+    let mut idx = 0;
+    while idx < chars.len() {
+        let c = chars[idx];
+        let start = idx;
+        idx += 1;
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            '+' => Token::Operator(Operator::Add),
+            '-' => Token::Operator(Operator::Subtract),
+            '*' => Token::Operator(Operator::Multiply),
+            '/' => Token::Operator(Operator::Divide),
+            '^' => Token::Operator(Operator::Power),
+            '(' => Token::Open,
+            ')' => Token::Close,
+            ',' => Token::Comma,
+            '"' => {
+                let Some(length) = chars[idx..].iter().position(|c| *c == '"') else {
+                    return UnexpectedEndSnafu.fail();
+                };
+                idx += length + 1;
+                Token::Quoted(chars[start + 1..idx - 1].iter().collect())
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                while idx < chars.len()
+                    && (chars[idx].is_ascii_digit()
+                        || chars[idx] == '.'
+                        || ((chars[idx] == 'e' || chars[idx] == 'E')
+                            && chars.get(idx + 1).is_some_and(|c| c.is_ascii_digit())))
+                {
+                    idx += if chars[idx].is_ascii_digit() || chars[idx] == '.' {
+                        1
+                    } else {
+                        2
+                    };
+                }
+                let text: String = chars[start..idx].iter().collect();
+                let value: f64 = text.parse().map_err(|_| ExpressionError::Unexpected {
+                    found: text.clone(),
+                    position: start,
+                })?;
+                // durations are given in seconds or milliseconds
+                let suffix: String = chars[idx..]
+                    .iter()
+                    .take_while(|c| c.is_alphanumeric() || **c == '_')
+                    .collect();
+                match suffix.as_str() {
+                    "" => Token::Number(value),
+                    "s" => {
+                        idx += 1;
+                        Token::Number(value)
+                    }
+                    "ms" => {
+                        idx += 2;
+                        Token::Number(value / 1E3)
+                    }
+                    _ => {
+                        return UnexpectedSnafu {
+                            found: suffix,
+                            position: idx,
+                        }
+                        .fail()
+                    }
+                }
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                while idx < chars.len() && (chars[idx].is_alphanumeric() || chars[idx] == '_') {
+                    idx += 1;
+                }
+                Token::Identifier(chars[start..idx].iter().collect())
+            }
+            c => {
+                return UnexpectedSnafu {
+                    found: c.to_string(),
+                    position: start,
+                }
+                .fail()
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of a formula
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next).map(|(_, token)| token)
+    }
+
+    fn advance(&mut self) -> Result<Token, ExpressionError> {
+        let (_, token) = self.tokens.get(self.next).context(UnexpectedEndSnafu)?;
+        self.next += 1;
+        Ok(token.clone())
+    }
+
+    fn unexpected<T>(&self) -> Result<T, ExpressionError> {
+        match self.tokens.get(self.next) {
+            Some((position, token)) => UnexpectedSnafu {
+                found: format!("{token:?}"),
+                position: *position,
+            }
+            .fail(),
+            None => UnexpectedEndSnafu.fail(),
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        if self.peek() == Some(&expected) {
+            self.next += 1;
+            Ok(())
+        } else {
+            self.unexpected()
+        }
+    }
+
+    /// sum := product (('+' | '-') product)*
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        let mut expression = self.product()?;
+        while let Some(Token::Operator(op @ (Operator::Add | Operator::Subtract))) = self.peek() {
+            let op = *op;
+            self.next += 1;
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.product()?));
+        }
+        Ok(expression)
+    }
+
+    /// product := unary (('*' | '/') unary)*
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        let mut expression = self.unary()?;
+        while let Some(Token::Operator(op @ (Operator::Multiply | Operator::Divide))) = self.peek()
+        {
+            let op = *op;
+            self.next += 1;
+            expression = Expression::Binary(op, Box::new(expression), Box::new(self.unary()?));
+        }
+        Ok(expression)
+    }
+
+    /// unary := '-' unary | power
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        if self.peek() == Some(&Token::Operator(Operator::Subtract)) {
+            self.next += 1;
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    /// power := atom ('^' unary)?, so `-2^2` is -4 and `2^-1` is 0.5
+    fn power(&mut self) -> Result<Expression, ExpressionError> {
+        let base = self.atom()?;
+        if self.peek() == Some(&Token::Operator(Operator::Power)) {
+            self.next += 1;
+            let exponent = self.unary()?;
+            return Ok(Expression::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    /// atom := number | channel | function '(' arguments ')' | '(' sum ')'
+    fn atom(&mut self) -> Result<Expression, ExpressionError> {
+        match self.advance()? {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Quoted(name) => Ok(Expression::Channel(name)),
+            Token::Identifier(name) if self.peek() == Some(&Token::Open) => {
+                let function = Function::ALL
+                    .into_iter()
+                    .find(|f| f.name() == name)
+                    .context(UnknownFunctionSnafu { name: name.clone() })?;
+                self.next += 1;
+                let mut arguments = vec![self.sum()?];
+                while self.peek() == Some(&Token::Comma) {
+                    self.next += 1;
+                    arguments.push(self.sum()?);
+                }
+                self.expect(Token::Close)?;
+                ensure!(
+                    arguments.len() == function.arguments(),
+                    ArgumentCountSnafu {
+                        name,
+                        expected: function.arguments()
+                    }
+                );
+                if matches!(function, Function::MovingMean | Function::MovingMedian)
+                    && !matches!(arguments[1], Expression::Number(_))
+                {
+                    return NonConstantWindowSnafu { name }.fail();
+                }
+                Ok(Expression::Call(function, arguments))
+            }
+            Token::Identifier(name) => Ok(Expression::Channel(name)),
+            Token::Open => {
+                let expression = self.sum()?;
+                self.expect(Token::Close)?;
+                Ok(expression)
+            }
+            _ => {
+                self.next -= 1;
+                self.unexpected()
+            }
+        }
+    }
+}
+
+/// Intermediate result: a constant or one value per sample of the common time grid
+enum Value {
+    Constant(f64),
+    Samples(Vec<f64>),
+}
+
+impl Value {
+    fn map(self, f: impl Fn(f64) -> f64) -> Value {
+        match self {
+            Value::Constant(v) => Value::Constant(f(v)),
+            Value::Samples(samples) => Value::Samples(samples.into_iter().map(f).collect()),
+        }
+    }
+
+    fn combine(self, other: Value, f: impl Fn(f64, f64) -> f64) -> Value {
+        match (self, other) {
+            (Value::Constant(a), Value::Constant(b)) => Value::Constant(f(a, b)),
+            (Value::Samples(a), Value::Constant(b)) => {
+                Value::Samples(a.into_iter().map(|a| f(a, b)).collect())
+            }
+            (Value::Constant(a), Value::Samples(b)) => {
+                Value::Samples(b.into_iter().map(|b| f(a, b)).collect())
+            }
+            (Value::Samples(a), Value::Samples(b)) => {
+                Value::Samples(a.into_iter().zip(b).map(|(a, b)| f(a, b)).collect())
+            }
+        }
+    }
+}
+
+impl Expression {
+    pub fn parse(formula: &str) -> Result<Expression, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(formula)?,
+            next: 0,
+        };
+        let expression = parser.sum()?;
+        if parser.peek().is_some() {
+            return parser.unexpected();
+        }
+        Ok(expression)
+    }
+
+    /// Names of the channels the expression references, without duplicates
+    pub fn channels(&self) -> Vec<String> {
+        let mut names = vec![];
+        self.collect_channels(&mut names);
+        names
+    }
+
+    fn collect_channels(&self, names: &mut Vec<String>) {
+        match self {
+            Expression::Number(_) => {}
+            Expression::Channel(name) => {
+                if !names.contains(name) {
+                    names.push(name.to_owned());
+                }
+            }
+            Expression::Negate(e) => e.collect_channels(names),
+            Expression::Binary(_, a, b) => {
+                a.collect_channels(names);
+                b.collect_channels(names);
+            }
+            Expression::Call(_, arguments) => {
+                arguments.iter().for_each(|a| a.collect_channels(names));
+            }
+        }
+    }
+
+    /// Evaluate the expression for the `inputs` by channel name. The inputs are linearly
+    /// interpolated to the sample times of the fastest one, over the range all of them cover.
+    pub fn evaluate(
+        &self,
+        inputs: &HashMap<String, UniformSignal>,
+    ) -> Result<UniformSignal, ExpressionError> {
+        let names = self.channels();
+        let mut signals = vec![];
+        for name in names.iter() {
+            signals.push(
+                inputs
+                    .get(name)
+                    .context(UnknownChannelSnafu { name: name.clone() })?,
+            );
+        }
+        ensure!(!signals.is_empty(), NoChannelSnafu);
+        let samples_per_second = signals
+            .iter()
+            .map(|s| s.samples_per_second)
+            .fold(0.0, f64::max);
+        let start = signals.iter().map(|s| s.start).fold(f64::MIN, f64::max);
+        let end = signals
+            .iter()
+            .map(|s| s.position(s.samples.len().saturating_sub(1)))
+            .fold(f64::MAX, f64::min);
+        ensure!(end > start, NoOverlapSnafu);
+        let grid = UniformSignal {
+            samples: vec![],
+            samples_per_second,
+            start,
+        };
+        let n = ((end - start) * samples_per_second).floor() as usize + 1;
+        let resampled: HashMap<&str, Vec<f64>> = names
+            .iter()
+            .zip(signals)
+            .map(|(name, signal)| {
+                let samples = (0..n)
+                    .map(|idx| interpolate(signal, grid.position(idx)))
+                    .collect();
+                (name.as_str(), samples)
+            })
+            .collect();
+        let samples = match self.value(&resampled, samples_per_second) {
+            Value::Constant(v) => vec![v; n],
+            Value::Samples(samples) => samples,
+        };
+        Ok(UniformSignal { samples, ..grid })
+    }
+
+    fn value(&self, inputs: &HashMap<&str, Vec<f64>>, samples_per_second: f64) -> Value {
+        let value = |e: &Expression| e.value(inputs, samples_per_second);
+        match self {
+            Expression::Number(v) => Value::Constant(*v),
+            Expression::Channel(name) => Value::Samples(inputs[name.as_str()].clone()),
+            Expression::Negate(e) => value(e).map(|v| -v),
+            Expression::Binary(op, a, b) => value(a).combine(value(b), |a, b| op.apply(a, b)),
+            Expression::Call(function, arguments) => {
+                let x = value(&arguments[0]);
+                let window = |e: &Expression| match e {
+                    Expression::Number(seconds) => {
+                        ((seconds * samples_per_second).round() as usize).max(1)
+                    }
+                    _ => 1,
+                };
+                match function {
+                    Function::Sqrt => x.map(f64::sqrt),
+                    Function::Abs => x.map(f64::abs),
+                    Function::Exp => x.map(f64::exp),
+                    Function::Ln => x.map(f64::ln),
+                    Function::Log10 => x.map(f64::log10),
+                    Function::Sin => x.map(f64::sin),
+                    Function::Cos => x.map(f64::cos),
+                    Function::Min => x.combine(value(&arguments[1]), f64::min),
+                    Function::Max => x.combine(value(&arguments[1]), f64::max),
+                    Function::MovingMean => match x {
+                        Value::Samples(samples) => {
+                            Value::Samples(moving_mean(&samples, window(&arguments[1])))
+                        }
+                        constant => constant,
+                    },
+                    Function::MovingMedian => match x {
+                        Value::Samples(samples) => {
+                            Value::Samples(moving_median(&samples, window(&arguments[1])))
+                        }
+                        constant => constant,
+                    },
+                    Function::Derivative => match x {
+                        Value::Samples(samples) => {
+                            let mut derivative: Vec<f64> = samples
+                                .windows(2)
+                                .map(|w| (w[1] - w[0]) * samples_per_second)
+                                .collect();
+                            derivative.insert(0, derivative.first().copied().unwrap_or(0.0));
+                            Value::Samples(derivative)
+                        }
+                        Value::Constant(_) => Value::Constant(0.0),
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Value of `signal` at `position`, linearly interpolated between the neighbouring samples
+fn interpolate(signal: &UniformSignal, position: f64) -> f64 {
+    let exact = (position - signal.start) * signal.samples_per_second;
+    let before = (exact.floor().max(0.0) as usize).min(signal.samples.len().saturating_sub(1));
+    let after = (before + 1).min(signal.samples.len() - 1);
+    let fraction = (exact - before as f64).clamp(0.0, 1.0);
+    signal.samples[before] * (1.0 - fraction) + signal.samples[after] * fraction
+}
+
+/// Mean of the finite values among the `window` samples centered on each sample, shorter at
+/// the edges. NaN where the window has no finite values, e.g. within a dropout.
+fn moving_mean(samples: &[f64], window: usize) -> Vec<f64> {
+    let mut cumulative = vec![(0.0, 0)];
+    for v in samples {
+        let (sum, count) = cumulative.last().copied().unwrap_or((0.0, 0));
+        cumulative.push(match v.is_finite() {
+            true => (sum + v, count + 1),
+            false => (sum, count),
+        });
+    }
+    (0..samples.len())
+        .map(|idx| {
+            let first = idx.saturating_sub(window / 2);
+            let last = (idx + window - window / 2).min(samples.len());
+            let count = cumulative[last].1 - cumulative[first].1;
+            match count {
+                0 => f64::NAN,
+                count => (cumulative[last].0 - cumulative[first].0) / count as f64,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,231 @@
+use std::f64::consts::PI;
+
+use serde::{Deserialize, Serialize};
+
+/// Second order IIR section, coefficients normalized to a0 = 1
+/// (see the Audio EQ Cookbook by Robert Bristow-Johnson)
+#[derive(Clone, Copy, Debug)]
+pub struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+}
+
+impl Biquad {
+    fn from_coefficients(b: [f64; 3], a: [f64; 3]) -> Biquad {
+        Biquad {
+            b: [b[0] / a[0], b[1] / a[0], b[2] / a[0]],
+            a: [a[1] / a[0], a[2] / a[0]],
+        }
+    }
+
+    /// Butterworth low-pass at `cutoff` Hz
+    pub fn low_pass(cutoff: f64, samples_per_second: f64) -> Biquad {
+        let (cos_w0, alpha) =
+            Self::prepare(cutoff, samples_per_second, std::f64::consts::FRAC_1_SQRT_2);
+        Biquad::from_coefficients(
+            [(1.0 - cos_w0) / 2.0, 1.0 - cos_w0, (1.0 - cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Butterworth high-pass at `cutoff` Hz
+    pub fn high_pass(cutoff: f64, samples_per_second: f64) -> Biquad {
+        let (cos_w0, alpha) =
+            Self::prepare(cutoff, samples_per_second, std::f64::consts::FRAC_1_SQRT_2);
+        Biquad::from_coefficients(
+            [(1.0 + cos_w0) / 2.0, -(1.0 + cos_w0), (1.0 + cos_w0) / 2.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    /// Notch at `frequency` Hz, e.g. to remove mains hum, `q` sets the width of the notch
+    pub fn notch(frequency: f64, samples_per_second: f64, q: f64) -> Biquad {
+        let (cos_w0, alpha) = Self::prepare(frequency, samples_per_second, q);
+        Biquad::from_coefficients(
+            [1.0, -2.0 * cos_w0, 1.0],
+            [1.0 + alpha, -2.0 * cos_w0, 1.0 - alpha],
+        )
+    }
+
+    fn prepare(frequency: f64, samples_per_second: f64, q: f64) -> (f64, f64) {
+        // keep the frequency below nyquist, otherwise the filter becomes unstable
+        let frequency = frequency.clamp(1E-6, 0.49 * samples_per_second);
+        let w0 = 2.0 * PI * frequency / samples_per_second;
+        (w0.cos(), w0.sin() / (2.0 * q))
+    }
+
+    /// Run the filter once over `signal` (direct form II transposed)
+    pub fn apply(&self, signal: &[f64]) -> Vec<f64> {
+        let mut z = [0.0, 0.0];
+        // start in the steady state for the first sample to avoid a step response
+        if let Some(first) = signal.first() {
+            let dc_gain = (self.b[0] + self.b[1] + self.b[2]) / (1.0 + self.a[0] + self.a[1]);
+            let y = first * dc_gain;
+            z[1] = self.b[2] * first - self.a[1] * y;
+            z[0] = self.b[1] * first - self.a[0] * y + z[1];
+        }
+        signal
+            .iter()
+            .map(|x| {
+                let y = self.b[0] * x + z[0];
+                z[0] = self.b[1] * x - self.a[0] * y + z[1];
+                z[1] = self.b[2] * x - self.a[1] * y;
+                y
+            })
+            .collect()
+    }
+}
+
+/// Apply the filters forward and backward, so the result has no phase shift
+pub fn filtfilt(filters: &[Biquad], signal: &[f64]) -> Vec<f64> {
+    let mut result = signal.to_vec();
+    filters.iter().for_each(|f| {
+        result = f.apply(&result);
+        result.reverse();
+        result = f.apply(&result);
+        result.reverse();
+    });
+    result
+}
+
+/// Median of the finite values among the `window` samples centered on each sample, removes
+/// spikes. NaN where the window has no finite values, e.g. within a dropout.
+pub fn moving_median(signal: &[f64], window: usize) -> Vec<f64> {
+    let half = window / 2;
+    if half == 0 || signal.is_empty() {
+        return signal.to_vec();
+    }
+    // the sorted finite content of the current window, updated sample by sample
+    let mut sorted: Vec<f64> = signal[..half.min(signal.len())]
+        .iter()
+        .copied()
+        .filter(|v| v.is_finite())
+        .collect();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let insert = |sorted: &mut Vec<f64>, value: f64| {
+        let idx = sorted.partition_point(|v| v.total_cmp(&value).is_lt());
+        sorted.insert(idx, value);
+    };
+    (0..signal.len())
+        .map(|n| {
+            if let Some(value) = signal.get(n + half).filter(|v| v.is_finite()) {
+                insert(&mut sorted, *value);
+            }
+            if n > half && signal[n - half - 1].is_finite() {
+                let value = signal[n - half - 1];
+                let idx = sorted.partition_point(|v| v.total_cmp(&value).is_lt());
+                sorted.remove(idx);
+            }
+            sorted.get(sorted.len() / 2).copied().unwrap_or(f64::NAN)
+        })
+        .collect()
+}
+
+/// How a filtered channel is drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FilterDisplay {
+    /// only the filtered signal
+    Replace,
+    /// the filtered signal over the faded raw signal
+    Overlay,
+}
+
+/// The filter chain of a channel, each stage is optional and runs without phase shift.
+/// Frequencies are in Hz, the moving median window is in seconds.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct FilterSettings {
+    pub enabled: bool,
+    pub moving_median: Option<f64>,
+    /// cutoff of the baseline wander removal
+    pub high_pass: Option<f64>,
+    /// mains frequency, 50 or 60 Hz
+    pub notch: Option<f64>,
+    pub band_pass: Option<(f64, f64)>,
+    pub low_pass: Option<f64>,
+    pub display: FilterDisplay,
+}
+
+impl Default for FilterSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            moving_median: None,
+            high_pass: None,
+            notch: None,
+            band_pass: None,
+            low_pass: None,
+            display: FilterDisplay::Replace,
+        }
+    }
+}
+
+impl FilterSettings {
+    /// true if the chain is enabled and has at least one stage
+    pub fn is_active(&self) -> bool {
+        self.enabled
+            && (self.moving_median.is_some()
+                || self.high_pass.is_some()
+                || self.notch.is_some()
+                || self.band_pass.is_some()
+                || self.low_pass.is_some())
+    }
+
+    /// Run the filter chain over `signal`, missing values (NaN) are kept
+    pub fn apply(&self, signal: &[f64], samples_per_second: f64) -> Vec<f64> {
+        let mut result = bridge_missing(signal);
+        if let Some(window) = self.moving_median {
+            result = moving_median(&result, (window * samples_per_second).round() as usize);
+        }
+        let mut biquads = vec![];
+        if let Some(cutoff) = self.high_pass {
+            biquads.push(Biquad::high_pass(cutoff, samples_per_second));
+        }
+        if let Some(frequency) = self.notch {
+            biquads.push(Biquad::notch(frequency, samples_per_second, 30.0));
+        }
+        if let Some((low, high)) = self.band_pass {
+            biquads.push(Biquad::high_pass(low, samples_per_second));
+            biquads.push(Biquad::low_pass(high, samples_per_second));
+        }
+        if let Some(cutoff) = self.low_pass {
+            biquads.push(Biquad::low_pass(cutoff, samples_per_second));
+        }
+        result = filtfilt(&biquads, &result);
+        restore_missing(&mut result, signal);
+        result
+    }
+}
+
+/// Replace missing values (NaN) with the last valid one, so they don't spread through filters
+fn bridge_missing(signal: &[f64]) -> Vec<f64> {
+    let mut last = signal
+        .iter()
+        .copied()
+        .find(|v| v.is_finite())
+        .unwrap_or(0.0);
+    signal
+        .iter()
+        .map(|v| {
+            if v.is_finite() {
+                last = *v;
+            }
+            last
+        })
+        .collect()
+}
+
+/// Set the values of `filtered` which are missing in `signal` back to NaN
+fn restore_missing(filtered: &mut [f64], signal: &[f64]) {
+    filtered
+        .iter_mut()
+        .zip(signal.iter())
+        .filter(|(_, v)| !v.is_finite())
+        .for_each(|(r, v)| *r = *v);
+}
+
+/// Like [filtfilt], but missing values (NaN) stay missing instead of spreading over the signal
+pub fn filtfilt_missing(biquads: &[Biquad], signal: &[f64]) -> Vec<f64> {
+    let mut result = filtfilt(biquads, &bridge_missing(signal));
+    restore_missing(&mut result, signal);
+    result
+}
@@ -0,0 +1,150 @@
+use crate::analysis::resample::median_sample_rate;
+
+/// the heart rate at a marked peak is the highest within this many seconds of the mark
+const PEAK_SEARCH: f64 = 10.0;
+
+/// the heart rate after the peak is averaged over this many seconds around the time point,
+/// so single outliers of a 1 Hz heart rate don't decide the recovery
+const RECOVERY_AVERAGE: f64 = 10.0;
+
+/// Heart rate zones as shares of the maximum heart rate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeartRateZones {
+    pub max_heart_rate: f64,
+    /// lower bound of each zone as share of the maximum heart rate, ascending
+    pub lower_bounds: [f64; 5],
+}
+
+impl Default for HeartRateZones {
+    /// the five zones of 50 to 100 % of the maximum heart rate used by Polar
+    fn default() -> Self {
+        HeartRateZones {
+            max_heart_rate: 190.0,
+            lower_bounds: [0.5, 0.6, 0.7, 0.8, 0.9],
+        }
+    }
+}
+
+impl HeartRateZones {
+    /// Zone of a heart rate, 0 below the first zone and 1 to 5 within the zones
+    pub fn zone(&self, heart_rate: f64) -> usize {
+        self.lower_bounds
+            .iter()
+            .filter(|bound| heart_rate >= **bound * self.max_heart_rate)
+            .count()
+    }
+
+    /// Heart rate range of a zone in bpm, zone 0 being below the first zone
+    pub fn range(&self, zone: usize) -> (f64, f64) {
+        let bound = |idx: usize| self.lower_bounds.get(idx).map(|b| b * self.max_heart_rate);
+        let lower = match zone {
+            0 => 0.0,
+            zone => bound(zone - 1).unwrap_or(f64::INFINITY),
+        };
+        (lower, bound(zone).unwrap_or(f64::INFINITY))
+    }
+}
+
+/// Heart rate statistics of one window
+#[derive(Clone, Copy, Debug)]
+pub struct HeartRateWindow {
+    pub start: f64,
+    pub end: f64,
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+/// Heart rate recovery after a peak of exercise
+#[derive(Clone, Copy, Debug)]
+pub struct Recovery {
+    pub peak_position: f64,
+    pub peak_heart_rate: f64,
+    /// heart rate one minute after the peak
+    pub after_1_min: Option<f64>,
+    /// heart rate two minutes after the peak
+    pub after_2_min: Option<f64>,
+}
+
+impl Recovery {
+    /// drop of the heart rate within one minute in bpm (HRR1)
+    pub fn hrr1(&self) -> Option<f64> {
+        self.after_1_min.map(|hr| self.peak_heart_rate - hr)
+    }
+
+    /// drop of the heart rate within two minutes in bpm (HRR2)
+    pub fn hrr2(&self) -> Option<f64> {
+        self.after_2_min.map(|hr| self.peak_heart_rate - hr)
+    }
+}
+
+/// Minimum, mean and maximum heart rate in consecutive windows of `window` seconds of the
+/// `points` sorted by position, windows without values are left out
+pub fn window_statistics(points: &[(f64, f64)], window: f64) -> Vec<HeartRateWindow> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return vec![];
+    };
+    let mut windows = vec![];
+    let mut start = first.0;
+    while start <= last.0 && window > 0.0 {
+        let end = start + window;
+        let first_idx = points.partition_point(|(x, _)| *x < start);
+        let last_idx = points.partition_point(|(x, _)| *x < end);
+        let values: Vec<f64> = points[first_idx..last_idx]
+            .iter()
+            .map(|(_, y)| *y)
+            .collect();
+        if !values.is_empty() {
+            windows.push(HeartRateWindow {
+                start,
+                end,
+                min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                mean: values.iter().sum::<f64>() / values.len() as f64,
+                max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            });
+        }
+        start = end;
+    }
+    windows
+}
+
+/// Seconds spent below the zones (index 0) and in each zone (index 1 to 5). Each value lasts
+/// until the next one, intervals longer than `gap_factor` times the median interval are gaps.
+pub fn time_in_zones(points: &[(f64, f64)], zones: &HeartRateZones, gap_factor: f64) -> [f64; 6] {
+    let mut times = [0.0; 6];
+    let Some(samples_per_second) = median_sample_rate(points) else {
+        return times;
+    };
+    let max_gap = gap_factor / samples_per_second;
+    for pair in points.windows(2) {
+        let duration = pair[1].0 - pair[0].0;
+        if duration <= max_gap {
+            times[zones.zone(pair[0].1)] += duration;
+        }
+    }
+    times
+}
+
+/// Heart rate recovery 1 and 2 minutes after the peak marked at `mark`, the peak being the
+/// highest heart rate within 10 s of the mark. None without heart rates near the mark.
+pub fn recovery(points: &[(f64, f64)], mark: f64) -> Option<Recovery> {
+    let (peak_position, peak_heart_rate) = points
+        .iter()
+        .filter(|(x, _)| (x - mark).abs() <= PEAK_SEARCH)
+        .copied()
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    let mean_around = |position: f64| {
+        let values: Vec<f64> = points
+            .iter()
+            .filter(|(x, _)| (x - position).abs() <= RECOVERY_AVERAGE / 2.0)
+            .map(|(_, y)| *y)
+            .collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+    };
+    Some(Recovery {
+        peak_position,
+        peak_heart_rate,
+        after_1_min: mean_around(peak_position + 60.0),
+        after_2_min: mean_around(peak_position + 120.0),
+    })
+}
@@ -0,0 +1,582 @@
+use crate::analysis::qrs_detection::{BeatList, MAX_RR};
+use crate::analysis::resample::resample_linear;
+use crate::analysis::spectrum::{lomb_scargle, welch, Spectrum};
+
+/// Series of RR intervals in milliseconds, each positioned at the beat which ends it
+#[derive(Clone, Debug, Default)]
+pub struct RrSeries {
+    pub positions: Vec<f64>,
+    pub intervals: Vec<f64>,
+    /// false if intervals were removed before the interval, so it doesn't follow the previous one
+    pub follows_previous: Vec<bool>,
+}
+
+impl RrSeries {
+    /// Create a series from the beats of an ECG with the sorted `gaps` (dropouts) of the ECG.
+    /// Intervals spanning a gap or longer than [MAX_RR] are left out.
+    pub fn from_beats(beats: &BeatList, gaps: &[(f64, f64)]) -> RrSeries {
+        let mut series = RrSeries::default();
+        let mut follows_previous = true;
+        for (position, rr) in beats.rr_intervals() {
+            let start = position - rr;
+            let idx = gaps.partition_point(|(_, end)| *end <= start);
+            let spans_gap = gaps
+                .get(idx)
+                .is_some_and(|(gap_start, _)| *gap_start < position);
+            if spans_gap || rr >= MAX_RR {
+                follows_previous = false;
+            } else {
+                series.push(position, rr * 1E3, follows_previous);
+                follows_previous = true;
+            }
+        }
+        series
+    }
+
+    /// Create a series from the points of an RR interval channel, `scale` converts the values to ms
+    pub fn from_points(points: &[(f64, f64)], scale: f64) -> RrSeries {
+        let mut series = RrSeries::default();
+        let mut follows_previous = true;
+        for (position, rr) in points {
+            if rr.is_finite() && *rr > 0.0 {
+                series.push(*position, rr * scale, follows_previous);
+                follows_previous = true;
+            } else {
+                follows_previous = false;
+            }
+        }
+        series
+    }
+
+    /// Append an interval, `follows_previous` is false if intervals were left out before it
+    pub fn push(&mut self, position: f64, interval: f64, follows_previous: bool) {
+        self.positions.push(position);
+        self.intervals.push(interval);
+        self.follows_previous.push(follows_previous);
+    }
+
+    /// The intervals ending between `start` and `end`
+    pub fn range(&self, start: f64, end: f64) -> RrSeries {
+        let first = self.positions.partition_point(|p| *p < start);
+        let last = self.positions.partition_point(|p| *p <= end);
+        RrSeries {
+            positions: self.positions[first..last].to_vec(),
+            intervals: self.intervals[first..last].to_vec(),
+            follows_previous: self.follows_previous[first..last].to_vec(),
+        }
+    }
+
+    /// The intervals which don't overlap any of the sorted `ranges`, e.g. unusable signal.
+    /// The interval after removed ones doesn't follow the previous interval.
+    pub fn without(&self, ranges: &[(f64, f64)]) -> RrSeries {
+        let mut series = RrSeries::default();
+        let mut follows_previous = true;
+        for ((position, rr), follows) in self
+            .positions
+            .iter()
+            .zip(self.intervals.iter())
+            .zip(self.follows_previous.iter())
+        {
+            follows_previous &= follows;
+            let start = position - rr / 1E3;
+            let idx = ranges.partition_point(|(_, end)| *end < start);
+            if ranges
+                .get(idx)
+                .is_some_and(|(range_start, _)| range_start <= position)
+            {
+                follows_previous = false;
+            } else {
+                series.push(*position, *rr, follows_previous);
+                follows_previous = true;
+            }
+        }
+        series
+    }
+
+    pub fn len(&self) -> usize {
+        self.intervals.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Indices of the intervals which directly follow the previous interval, so that
+    /// successive differences and Poincaré pairs don't span removed intervals
+    pub fn successive(&self) -> Vec<usize> {
+        (1..self.len())
+            .filter(|idx| self.follows_previous[*idx])
+            .collect()
+    }
+}
+
+/// Time-domain HRV measures as defined by the Task Force of the ESC and NASPE (1996),
+/// all durations in ms
+#[derive(Clone, Copy, Debug)]
+pub struct TimeDomainHrv {
+    pub n: usize,
+    pub mean_rr: f64,
+    pub mean_hr: f64,
+    pub sdnn: f64,
+    pub rmssd: f64,
+    pub sdsd: f64,
+    pub nn50: usize,
+    pub pnn50: f64,
+    pub triangular_index: f64,
+    pub tinn: f64,
+}
+
+impl TimeDomainHrv {
+    /// (name, value, unit) of all measures, e.g. for a results table or an export
+    pub fn rows(&self) -> Vec<(&'static str, String, &'static str)> {
+        vec![
+            ("RR intervals", self.n.to_string(), ""),
+            ("Mean RR", format!("{:.1}", self.mean_rr), "ms"),
+            ("Mean HR", format!("{:.1}", self.mean_hr), "bpm"),
+            ("SDNN", format!("{:.1}", self.sdnn), "ms"),
+            ("RMSSD", format!("{:.1}", self.rmssd), "ms"),
+            ("SDSD", format!("{:.1}", self.sdsd), "ms"),
+            ("NN50", self.nn50.to_string(), ""),
+            ("pNN50", format!("{:.2}", self.pnn50), "%"),
+            (
+                "HRV triangular index",
+                format!("{:.2}", self.triangular_index),
+                "",
+            ),
+            ("TINN", format!("{:.1}", self.tinn), "ms"),
+        ]
+    }
+}
+
+/// bin width of the RR histogram, 1/128 s as recommended by the Task Force
+const HISTOGRAM_BIN_WIDTH: f64 = 1000.0 / 128.0;
+
+pub fn time_domain(series: &RrSeries) -> Option<TimeDomainHrv> {
+    let rr = &series.intervals;
+    if rr.len() < 3 {
+        return None;
+    }
+    let n = rr.len() as f64;
+    let mean_rr = rr.iter().sum::<f64>() / n;
+    let mean_hr = rr.iter().map(|rr| 60_000.0 / rr).sum::<f64>() / n;
+    let sdnn = standard_deviation(rr);
+
+    let differences: Vec<f64> = series
+        .successive()
+        .iter()
+        .map(|idx| rr[*idx] - rr[idx - 1])
+        .collect();
+    if differences.is_empty() {
+        return None;
+    }
+    let rmssd = (differences.iter().map(|d| d * d).sum::<f64>() / differences.len() as f64).sqrt();
+    let sdsd = standard_deviation(&differences);
+    let nn50 = differences.iter().filter(|d| d.abs() > 50.0).count();
+    let pnn50 = nn50 as f64 / differences.len() as f64 * 100.0;
+
+    let histogram = histogram(rr);
+    let max_count = histogram.counts.iter().copied().max().unwrap_or(1);
+    let triangular_index = n / max_count as f64;
+    let tinn = tinn(&histogram);
+
+    Some(TimeDomainHrv {
+        n: rr.len(),
+        mean_rr,
+        mean_hr,
+        sdnn,
+        rmssd,
+        sdsd,
+        nn50,
+        pnn50,
+        triangular_index,
+        tinn,
+    })
+}
+
+/// sample standard deviation
+pub fn standard_deviation(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+}
+
+/// number of RR intervals per bin, starting at a multiple of the bin width
+struct Histogram {
+    counts: Vec<usize>,
+}
+
+fn histogram(rr: &[f64]) -> Histogram {
+    let min = rr.iter().fold(f64::INFINITY, |a, b| a.min(*b));
+    let max = rr.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b));
+    let start = (min / HISTOGRAM_BIN_WIDTH).floor() * HISTOGRAM_BIN_WIDTH;
+    let n_bins = ((max - start) / HISTOGRAM_BIN_WIDTH).floor() as usize + 1;
+    let mut counts = vec![0; n_bins];
+    rr.iter().for_each(|rr| {
+        let idx = ((rr - start) / HISTOGRAM_BIN_WIDTH) as usize;
+        counts[idx.min(n_bins - 1)] += 1;
+    });
+    Histogram { counts }
+}
+
+/// Triangular interpolation of the RR histogram: the base width M - N of the triangle
+/// which is zero outside of [N, M], peaks at the histogram mode and fits the histogram
+/// best in the least squares sense
+fn tinn(histogram: &Histogram) -> f64 {
+    let counts = &histogram.counts;
+    let (peak, peak_count) = counts
+        .iter()
+        .enumerate()
+        .max_by_key(|(_, count)| **count)
+        .map(|(idx, count)| (idx as f64 + 0.5, *count as f64))
+        .unwrap_or((0.0, 0.0));
+    let center = |idx: usize| idx as f64 + 0.5;
+    let triangle = |x: f64, n: f64, m: f64| {
+        if x <= n || x >= m {
+            0.0
+        } else if x <= peak {
+            peak_count * (x - n) / (peak - n)
+        } else {
+            peak_count * (m - x) / (m - peak)
+        }
+    };
+
+    // N and M on the bin edges, in bins relative to the start of the histogram
+    let peak_idx = peak.floor() as i64;
+    let mut best = (f64::INFINITY, 0.0);
+    for n in (-1..=peak_idx).map(|n| n as f64) {
+        for m in (peak_idx + 1..=counts.len() as i64 + 1).map(|m| m as f64) {
+            let error: f64 = counts
+                .iter()
+                .enumerate()
+                .map(|(idx, count)| (*count as f64 - triangle(center(idx), n, m)).powi(2))
+                .sum();
+            if error < best.0 {
+                best = (error, m - n);
+            }
+        }
+    }
+    best.1 * HISTOGRAM_BIN_WIDTH
+}
+
+/// Frequency bands of the Task Force (1996) in Hz
+pub const VLF_BAND: (f64, f64) = (0.0, 0.04);
+pub const LF_BAND: (f64, f64) = (0.04, 0.15);
+pub const HF_BAND: (f64, f64) = (0.15, 0.4);
+
+/// rate the RR series is resampled with for the FFT based spectrum
+const RESAMPLING_RATE: f64 = 4.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpectrumMethod {
+    /// Welch periodogram of the RR series resampled at 4 Hz
+    Welch,
+    /// Lomb-Scargle periodogram of the unevenly sampled RR series
+    LombScargle,
+}
+
+impl SpectrumMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SpectrumMethod::Welch => "Welch (FFT)",
+            SpectrumMethod::LombScargle => "Lomb-Scargle",
+        }
+    }
+}
+
+/// Frequency-domain HRV measures, powers in ms², relative powers and normalized units in %
+#[derive(Clone, Copy, Debug)]
+pub struct FrequencyDomainHrv {
+    pub vlf_power: f64,
+    pub lf_power: f64,
+    pub hf_power: f64,
+    pub total_power: f64,
+    pub vlf_relative: f64,
+    pub lf_relative: f64,
+    pub hf_relative: f64,
+    pub lf_normalized: f64,
+    pub hf_normalized: f64,
+    pub lf_hf_ratio: f64,
+    pub vlf_peak: Option<f64>,
+    pub lf_peak: Option<f64>,
+    pub hf_peak: Option<f64>,
+}
+
+impl FrequencyDomainHrv {
+    /// (name, value, unit) of all measures, e.g. for a results table or an export
+    pub fn rows(&self) -> Vec<(&'static str, String, &'static str)> {
+        let peak = |f: Option<f64>| f.map(|f| format!("{:.3}", f)).unwrap_or_default();
+        vec![
+            ("VLF power", format!("{:.0}", self.vlf_power), "ms²"),
+            ("LF power", format!("{:.0}", self.lf_power), "ms²"),
+            ("HF power", format!("{:.0}", self.hf_power), "ms²"),
+            ("Total power", format!("{:.0}", self.total_power), "ms²"),
+            ("VLF relative", format!("{:.1}", self.vlf_relative), "%"),
+            ("LF relative", format!("{:.1}", self.lf_relative), "%"),
+            ("HF relative", format!("{:.1}", self.hf_relative), "%"),
+            (
+                "LF normalized",
+                format!("{:.1}", self.lf_normalized),
+                "n.u.",
+            ),
+            (
+                "HF normalized",
+                format!("{:.1}", self.hf_normalized),
+                "n.u.",
+            ),
+            ("LF/HF", format!("{:.2}", self.lf_hf_ratio), ""),
+            ("VLF peak", peak(self.vlf_peak), "Hz"),
+            ("LF peak", peak(self.lf_peak), "Hz"),
+            ("HF peak", peak(self.hf_peak), "Hz"),
+        ]
+    }
+}
+
+/// Power spectrum of the RR series in ms²/Hz
+pub fn rr_spectrum(series: &RrSeries, method: SpectrumMethod) -> Option<Spectrum> {
+    if series.len() < 10 {
+        return None;
+    }
+    // relative positions keep the phases of the Lomb-Scargle periodogram precise
+    let first = series.positions[0];
+    let points: Vec<(f64, f64)> = series
+        .positions
+        .iter()
+        .zip(series.intervals.iter())
+        .map(|(position, rr)| (position - first, *rr))
+        .collect();
+    let spectrum = match method {
+        SpectrumMethod::Welch => {
+            let signal = resample_linear(&points, RESAMPLING_RATE);
+            let samples = remove_linear_trend(&signal.samples);
+            // segments of 256 s overlapping by 50 %
+            welch(&samples, RESAMPLING_RATE, 1024, 0.5, 2048)
+        }
+        SpectrumMethod::LombScargle => {
+            let frequencies: Vec<f64> = (1..=500).map(|k| k as f64 * 1E-3).collect();
+            lomb_scargle(&points, &frequencies)
+        }
+    };
+    (spectrum.frequencies.len() > 1).then_some(spectrum)
+}
+
+pub fn frequency_domain(spectrum: &Spectrum) -> FrequencyDomainHrv {
+    let vlf_power = spectrum.band_power(VLF_BAND.0, VLF_BAND.1);
+    let lf_power = spectrum.band_power(LF_BAND.0, LF_BAND.1);
+    let hf_power = spectrum.band_power(HF_BAND.0, HF_BAND.1);
+    let total_power = vlf_power + lf_power + hf_power;
+    FrequencyDomainHrv {
+        vlf_power,
+        lf_power,
+        hf_power,
+        total_power,
+        vlf_relative: vlf_power / total_power * 100.0,
+        lf_relative: lf_power / total_power * 100.0,
+        hf_relative: hf_power / total_power * 100.0,
+        lf_normalized: lf_power / (lf_power + hf_power) * 100.0,
+        hf_normalized: hf_power / (lf_power + hf_power) * 100.0,
+        lf_hf_ratio: lf_power / hf_power,
+        vlf_peak: spectrum.peak_frequency(VLF_BAND.0, VLF_BAND.1),
+        lf_peak: spectrum.peak_frequency(LF_BAND.0, LF_BAND.1),
+        hf_peak: spectrum.peak_frequency(HF_BAND.0, HF_BAND.1),
+    }
+}
+
+/// Subtract the least squares line from the samples
+fn remove_linear_trend(samples: &[f64]) -> Vec<f64> {
+    let n = samples.len() as f64;
+    let mean_x = (n - 1.0) / 2.0;
+    let mean_y = samples.iter().sum::<f64>() / n;
+    let (covariance, variance) =
+        samples
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(covariance, variance), (x, y)| {
+                let dx = x as f64 - mean_x;
+                (covariance + dx * (y - mean_y), variance + dx * dx)
+            });
+    let slope = if variance > 0.0 {
+        covariance / variance
+    } else {
+        0.0
+    };
+    samples
+        .iter()
+        .enumerate()
+        .map(|(x, y)| y - mean_y - slope * (x as f64 - mean_x))
+        .collect()
+}
+
+/// entropies are O(n²), longer series are not analysed
+const MAX_ENTROPY_LENGTH: usize = 10_000;
+
+/// Nonlinear HRV measures, SD1 and SD2 in ms
+#[derive(Clone, Copy, Debug)]
+pub struct NonlinearHrv {
+    /// standard deviation perpendicular to the identity line of the Poincaré plot
+    pub sd1: f64,
+    /// standard deviation along the identity line of the Poincaré plot
+    pub sd2: f64,
+    pub sample_entropy: Option<f64>,
+    pub approximate_entropy: Option<f64>,
+    /// short-term scaling exponent (4 - 16 beats)
+    pub dfa_alpha1: Option<f64>,
+    /// long-term scaling exponent (16 - 64 beats)
+    pub dfa_alpha2: Option<f64>,
+}
+
+impl NonlinearHrv {
+    /// (name, value, unit) of all measures, e.g. for a results table or an export
+    pub fn rows(&self) -> Vec<(&'static str, String, &'static str)> {
+        let optional = |v: Option<f64>| v.map(|v| format!("{:.3}", v)).unwrap_or_default();
+        vec![
+            ("SD1", format!("{:.1}", self.sd1), "ms"),
+            ("SD2", format!("{:.1}", self.sd2), "ms"),
+            ("SD2/SD1", format!("{:.2}", self.sd2 / self.sd1), ""),
+            ("Sample entropy", optional(self.sample_entropy), ""),
+            (
+                "Approximate entropy",
+                optional(self.approximate_entropy),
+                "",
+            ),
+            ("DFA α1", optional(self.dfa_alpha1), ""),
+            ("DFA α2", optional(self.dfa_alpha2), ""),
+        ]
+    }
+}
+
+pub fn nonlinear(series: &RrSeries) -> Option<NonlinearHrv> {
+    let rr = &series.intervals;
+    if rr.len() < 3 {
+        return None;
+    }
+    // coordinates rotated by 45° onto the axes of the Poincaré ellipse
+    let successive = series.successive();
+    if successive.len() < 2 {
+        return None;
+    }
+    let across: Vec<f64> = successive
+        .iter()
+        .map(|idx| (rr[*idx] - rr[idx - 1]) / std::f64::consts::SQRT_2)
+        .collect();
+    let along: Vec<f64> = successive
+        .iter()
+        .map(|idx| (rr[*idx] + rr[idx - 1]) / std::f64::consts::SQRT_2)
+        .collect();
+
+    // the tolerance is 0.2 times the standard deviation of the series
+    let tolerance = 0.2 * standard_deviation(rr);
+    let (sample_entropy, approximate_entropy) = if rr.len() <= MAX_ENTROPY_LENGTH {
+        (
+            sample_entropy(rr, 2, tolerance),
+            approximate_entropy(rr, 2, tolerance),
+        )
+    } else {
+        (None, None)
+    };
+
+    Some(NonlinearHrv {
+        sd1: standard_deviation(&across),
+        sd2: standard_deviation(&along),
+        sample_entropy,
+        approximate_entropy,
+        dfa_alpha1: dfa_exponent(rr, 4, 16),
+        dfa_alpha2: dfa_exponent(rr, 16, 64),
+    })
+}
+
+/// For each of the first `n_templates` templates of length `m` the number of templates
+/// within `tolerance` (Chebyshev distance), including the template itself
+fn count_matches(values: &[f64], m: usize, tolerance: f64, n_templates: usize) -> Vec<usize> {
+    (0..n_templates)
+        .map(|i| {
+            (0..n_templates)
+                .filter(|j| (0..m).all(|k| (values[i + k] - values[j + k]).abs() <= tolerance))
+                .count()
+        })
+        .collect()
+}
+
+/// Sample entropy after Richman JS, Moorman JR, Am J Physiol 278, 2000
+pub fn sample_entropy(values: &[f64], m: usize, tolerance: f64) -> Option<f64> {
+    if values.len() <= m + 1 {
+        return None;
+    }
+    // the same number of templates for both lengths, without self matches
+    let n_templates = values.len() - m;
+    let b: usize = count_matches(values, m, tolerance, n_templates)
+        .iter()
+        .map(|c| c - 1)
+        .sum();
+    let a: usize = count_matches(values, m + 1, tolerance, n_templates)
+        .iter()
+        .map(|c| c - 1)
+        .sum();
+    (a > 0 && b > 0).then(|| -(a as f64 / b as f64).ln())
+}
+
+/// Approximate entropy after Pincus SM, PNAS 88, 1991
+pub fn approximate_entropy(values: &[f64], m: usize, tolerance: f64) -> Option<f64> {
+    if values.len() <= m + 1 {
+        return None;
+    }
+    let phi = |m: usize| {
+        let n_templates = values.len() - m + 1;
+        count_matches(values, m, tolerance, n_templates)
+            .iter()
+            .map(|c| (*c as f64 / n_templates as f64).ln())
+            .sum::<f64>()
+            / n_templates as f64
+    };
+    Some(phi(m) - phi(m + 1))
+}
+
+/// Scaling exponent of the detrended fluctuation analysis (Peng CK et al., Chaos 5, 1995)
+/// for box sizes from `min_box` to `max_box` beats
+pub fn dfa_exponent(values: &[f64], min_box: usize, max_box: usize) -> Option<f64> {
+    if values.len() < 2 * max_box {
+        return None;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    let profile: Vec<f64> = values
+        .iter()
+        .scan(0.0, |sum, v| {
+            *sum += v - mean;
+            Some(*sum)
+        })
+        .collect();
+
+    let points: Vec<(f64, f64)> = (min_box..=max_box)
+        .filter_map(|box_size| {
+            let n_boxes = profile.len() / box_size;
+            let squared_residuals: f64 = profile
+                .chunks_exact(box_size)
+                .map(|chunk| {
+                    let detrended = remove_linear_trend(chunk);
+                    detrended.iter().map(|r| r * r).sum::<f64>()
+                })
+                .sum();
+            let fluctuation = (squared_residuals / (n_boxes * box_size) as f64).sqrt();
+            (fluctuation > 0.0).then(|| ((box_size as f64).ln(), fluctuation.ln()))
+        })
+        .collect();
+    slope(&points)
+}
+
+/// Slope of the least squares line through `points`
+fn slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    (variance > 0.0).then(|| covariance / variance)
+}
@@ -0,0 +1,484 @@
+use crate::analysis::filters::{filtfilt, Biquad};
+use crate::analysis::resample::UniformSignal;
+
+/// Longest plausible RR interval in seconds, longer ones span a gap or missed beats
+pub const MAX_RR: f64 = 2.0;
+
+/// The detected (and possibly manually corrected) R peaks of an ECG channel,
+/// stored as sorted positions on the x axis
+#[derive(Clone, Debug, Default)]
+pub struct BeatList {
+    pub positions: Vec<f64>,
+}
+
+impl BeatList {
+    pub fn new(mut positions: Vec<f64>) -> BeatList {
+        positions.sort_by(|a, b| a.total_cmp(b));
+        BeatList { positions }
+    }
+
+    pub fn add(&mut self, position: f64) {
+        let idx = self.positions.partition_point(|p| *p < position);
+        self.positions.insert(idx, position);
+    }
+
+    /// Index of the beat closest to `position`, if it is closer than `tolerance`
+    pub fn nearest(&self, position: f64, tolerance: f64) -> Option<usize> {
+        let idx = self.positions.partition_point(|p| *p < position);
+        [idx.checked_sub(1), Some(idx)]
+            .into_iter()
+            .flatten()
+            .filter(|i| *i < self.positions.len())
+            .map(|i| (i, (self.positions[i] - position).abs()))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        if idx < self.positions.len() {
+            self.positions.remove(idx);
+        }
+    }
+
+    /// Move the beat at `idx` to `position`, returns the new index of the beat
+    pub fn move_beat(&mut self, idx: usize, position: f64) -> usize {
+        self.remove(idx);
+        self.add(position);
+        self.positions.partition_point(|p| *p < position)
+    }
+
+    /// Intervals between consecutive beats in seconds, positioned at the second beat
+    pub fn rr_intervals(&self) -> Vec<(f64, f64)> {
+        self.positions
+            .windows(2)
+            .map(|w| (w[1], w[1] - w[0]))
+            .collect()
+    }
+
+    /// Heart rate in bpm of the beat interval containing `position`
+    pub fn heart_rate_at(&self, position: f64) -> Option<f64> {
+        let idx = self.positions.partition_point(|p| *p <= position);
+        let next = self.positions.get(idx)?;
+        let previous = self.positions.get(idx.checked_sub(1)?)?;
+        Some(60.0 / (next - previous))
+    }
+}
+
+/// R peak detection after Pan J, Tompkins WJ: "A Real-Time QRS Detection Algorithm",
+/// IEEE Trans Biomed Eng 32(3), 1985.
+///
+/// The filters are applied forward and backward, so no delay compensation is needed.
+/// Returns the positions of the R peaks on the x axis.
+pub fn detect_r_peaks(signal: &UniformSignal) -> Vec<f64> {
+    let fs = signal.samples_per_second;
+    let samples: Vec<f64> = signal
+        .samples
+        .iter()
+        .map(|v| if v.is_finite() { *v } else { 0.0 })
+        .collect();
+    if samples.len() < (2.0 * fs) as usize {
+        return vec![];
+    }
+
+    // band pass 5 - 15 Hz to emphasize the QRS complex
+    let band_passed = filtfilt(
+        &[Biquad::high_pass(5.0, fs), Biquad::low_pass(15.0, fs)],
+        &samples,
+    );
+
+    // five point derivative and squaring
+    let mut squared = vec![0.0; band_passed.len()];
+    for n in 2..band_passed.len().saturating_sub(2) {
+        let derivative = (-band_passed[n - 2] - 2.0 * band_passed[n - 1]
+            + 2.0 * band_passed[n + 1]
+            + band_passed[n + 2])
+            * fs
+            / 8.0;
+        squared[n] = derivative * derivative;
+    }
+
+    // moving window integration over 150 ms, centered
+    let integrated = moving_average(&squared, ((0.15 * fs).round() as usize).max(1));
+
+    let refractory = (0.2 * fs).round() as usize;
+    let t_wave_window = (0.36 * fs).round() as usize;
+    let peaks = find_peaks(&integrated, refractory);
+
+    // initialize the thresholds with the first two seconds
+    let learning_end = (2.0 * fs) as usize;
+    let learning_max = integrated[..learning_end]
+        .iter()
+        .fold(0.0_f64, |a, b| a.max(*b));
+    let learning_mean = integrated[..learning_end].iter().sum::<f64>() / learning_end as f64;
+    let mut signal_level = 0.25 * learning_max;
+    let mut noise_level = 0.5 * learning_mean;
+    let threshold =
+        |signal_level: f64, noise_level: f64| noise_level + 0.25 * (signal_level - noise_level);
+
+    let max_slope = |idx: usize| {
+        let start = idx.saturating_sub(t_wave_window / 2);
+        band_passed[start..=idx.min(band_passed.len() - 1)]
+            .windows(2)
+            .fold(0.0_f64, |a, w| a.max((w[1] - w[0]).abs()))
+    };
+
+    let mut qrs: Vec<usize> = vec![];
+    let mut qrs_slopes: Vec<f64> = vec![];
+    for (peak_nr, &peak) in peaks.iter().enumerate() {
+        let value = integrated[peak];
+
+        // search back for missed beats if the current interval is much longer than the average
+        if let Some(&last) = qrs.last() {
+            let recent: Vec<usize> = qrs.windows(2).rev().take(8).map(|w| w[1] - w[0]).collect();
+            if !recent.is_empty() {
+                let rr_average = recent.iter().sum::<usize>() as f64 / recent.len() as f64;
+                if (peak - last) as f64 > 1.66 * rr_average {
+                    let threshold2 = 0.5 * threshold(signal_level, noise_level);
+                    let first_after_last = peaks.partition_point(|p| *p <= last);
+                    let candidate = peaks[first_after_last..peak_nr]
+                        .iter()
+                        .filter(|p| **p > last + refractory && **p + refractory < peak)
+                        .filter(|p| integrated[**p] > threshold2)
+                        .max_by(|a, b| integrated[**a].total_cmp(&integrated[**b]));
+                    if let Some(&candidate) = candidate {
+                        signal_level = 0.25 * integrated[candidate] + 0.75 * signal_level;
+                        qrs.push(candidate);
+                        qrs_slopes.push(max_slope(candidate));
+                    }
+                }
+            }
+        }
+
+        if value > threshold(signal_level, noise_level) {
+            let slope = max_slope(peak);
+            let is_t_wave = match (qrs.last(), qrs_slopes.last()) {
+                (Some(&last), Some(&last_slope)) => {
+                    peak - last < t_wave_window && slope < 0.5 * last_slope
+                }
+                _ => false,
+            };
+            if is_t_wave {
+                noise_level = 0.125 * value + 0.875 * noise_level;
+            } else {
+                signal_level = 0.125 * value + 0.875 * signal_level;
+                qrs.push(peak);
+                qrs_slopes.push(slope);
+            }
+        } else {
+            noise_level = 0.125 * value + 0.875 * noise_level;
+        }
+    }
+
+    // locate the R peak in the band passed and then in the original signal
+    let search = (0.1 * fs).round() as usize;
+    let refine = (0.03 * fs).round() as usize;
+    let polarity = dominant_polarity(&band_passed, &qrs, search);
+    let mut r_peaks: Vec<usize> = qrs
+        .iter()
+        .map(|&idx| {
+            let coarse = extremum(&band_passed, idx, search, polarity);
+            extremum(&samples, coarse, refine, polarity)
+        })
+        .collect();
+    r_peaks.sort();
+    r_peaks.dedup_by(|a, b| *a - *b < refractory);
+    r_peaks.iter().map(|idx| signal.position(*idx)).collect()
+}
+
+/// R peak detection with two moving averages after Elgendi M: "Fast QRS Detection with an
+/// Optimized Knowledge-Based Method", PLoS ONE 8(9), 2013.
+///
+/// Works differently enough from [detect_r_peaks] that the agreement of both indicates the
+/// signal quality. Returns the positions of the R peaks on the x axis.
+pub fn detect_r_peaks_elgendi(signal: &UniformSignal) -> Vec<f64> {
+    let fs = signal.samples_per_second;
+    let samples: Vec<f64> = signal
+        .samples
+        .iter()
+        .map(|v| if v.is_finite() { *v } else { 0.0 })
+        .collect();
+    if samples.len() < (2.0 * fs) as usize {
+        return vec![];
+    }
+    let band_passed = filtfilt(
+        &[Biquad::high_pass(8.0, fs), Biquad::low_pass(20.0, fs)],
+        &samples,
+    );
+    let squared: Vec<f64> = band_passed.iter().map(|v| v * v).collect();
+    let qrs_window = ((0.097 * fs).round() as usize).max(1);
+    let ma_qrs = moving_average(&squared, qrs_window);
+    let ma_beat = moving_average(&squared, ((0.611 * fs).round() as usize).max(1));
+    let offset = 0.08 * squared.iter().sum::<f64>() / squared.len() as f64;
+
+    // blocks of interest where the QRS average exceeds the beat average, as wide as a QRS
+    let mut blocks: Vec<(usize, usize)> = vec![];
+    let mut block_start = None;
+    for n in 0..squared.len() {
+        let inside = ma_qrs[n] > ma_beat[n] + offset;
+        match (inside, block_start) {
+            (true, None) => block_start = Some(n),
+            (false, Some(start)) => {
+                if n - start >= qrs_window {
+                    blocks.push((start, n));
+                }
+                block_start = None;
+            }
+            _ => {}
+        }
+    }
+    let centers: Vec<usize> = blocks
+        .iter()
+        .map(|(start, end)| {
+            (*start..*end)
+                .max_by(|a, b| squared[*a].total_cmp(&squared[*b]))
+                .unwrap_or(*start)
+        })
+        .collect();
+
+    let refractory = (0.2 * fs).round() as usize;
+    let search = (0.1 * fs).round() as usize;
+    let refine = (0.03 * fs).round() as usize;
+    let polarity = dominant_polarity(&band_passed, &centers, search);
+    let mut r_peaks: Vec<usize> = centers
+        .iter()
+        .map(|&idx| extremum(&samples, idx, refine, polarity))
+        .collect();
+    r_peaks.sort();
+    r_peaks.dedup_by(|a, b| *a - *b < refractory);
+    r_peaks.iter().map(|idx| signal.position(*idx)).collect()
+}
+
+fn moving_average(signal: &[f64], window: usize) -> Vec<f64> {
+    let mut cumulative = Vec::with_capacity(signal.len() + 1);
+    cumulative.push(0.0);
+    signal.iter().fold(0.0, |sum, v| {
+        cumulative.push(sum + v);
+        sum + v
+    });
+    let half = window / 2;
+    (0..signal.len())
+        .map(|n| {
+            let start = n.saturating_sub(half);
+            let end = (n + half + 1).min(signal.len());
+            (cumulative[end] - cumulative[start]) / (end - start) as f64
+        })
+        .collect()
+}
+
+/// Local maxima which are at least `min_distance` samples apart, the larger one wins
+fn find_peaks(signal: &[f64], min_distance: usize) -> Vec<usize> {
+    let mut peaks: Vec<usize> = vec![];
+    for n in 1..signal.len().saturating_sub(1) {
+        if signal[n] > signal[n - 1] && signal[n] >= signal[n + 1] {
+            match peaks.last() {
+                Some(&last) if n - last < min_distance => {
+                    if signal[n] > signal[last] {
+                        *peaks.last_mut().unwrap() = n;
+                    }
+                }
+                _ => peaks.push(n),
+            }
+        }
+    }
+    peaks
+}
+
+/// +1.0 if the QRS complexes point upwards, -1.0 if they are inverted
+fn dominant_polarity(signal: &[f64], qrs: &[usize], search: usize) -> f64 {
+    let (positive, negative) = qrs.iter().fold((0.0, 0.0), |(positive, negative), &idx| {
+        let window = &signal[idx.saturating_sub(search)..(idx + search).min(signal.len())];
+        let max = window.iter().fold(0.0_f64, |a, b| a.max(*b));
+        let min = window.iter().fold(0.0_f64, |a, b| a.min(*b));
+        (positive + max, negative - min)
+    });
+    if negative > 1.5 * positive {
+        -1.0
+    } else {
+        1.0
+    }
+}
+
+/// Index of the largest value (times `polarity`) within `idx` ± `window`
+fn extremum(signal: &[f64], idx: usize, window: usize, polarity: f64) -> usize {
+    let start = idx.saturating_sub(window);
+    let end = (idx + window + 1).min(signal.len());
+    (start..end)
+        .max_by(|a, b| (signal[*a] * polarity).total_cmp(&(signal[*b] * polarity)))
+        .unwrap_or(idx)
+}
+
+/// Beat-by-beat comparison with reference annotations as in ANSI/AAMI EC57
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DetectionPerformance {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl DetectionPerformance {
+    /// Se = TP / (TP + FN), None without reference beats
+    pub fn sensitivity(&self) -> Option<f64> {
+        let reference = self.true_positives + self.false_negatives;
+        (reference > 0).then(|| self.true_positives as f64 / reference as f64)
+    }
+
+    /// +P = TP / (TP + FP), None without detected beats
+    pub fn positive_predictivity(&self) -> Option<f64> {
+        let detected = self.true_positives + self.false_positives;
+        (detected > 0).then(|| self.true_positives as f64 / detected as f64)
+    }
+}
+
+/// Match detected beats to reference beats, a detection within `tolerance` seconds
+/// (150 ms in EC57) of an unmatched reference beat is a true positive
+pub fn compare_with_reference(
+    detected: &[f64],
+    reference: &[f64],
+    tolerance: f64,
+) -> DetectionPerformance {
+    let mut performance = DetectionPerformance::default();
+    let mut ref_idx = 0;
+    for &beat in detected {
+        while ref_idx < reference.len() && reference[ref_idx] < beat - tolerance {
+            performance.false_negatives += 1;
+            ref_idx += 1;
+        }
+        if ref_idx < reference.len() && (reference[ref_idx] - beat).abs() <= tolerance {
+            performance.true_positives += 1;
+            ref_idx += 1;
+        } else {
+            performance.false_positives += 1;
+        }
+    }
+    performance.false_negatives += reference.len() - ref_idx;
+    performance
+}
+
+/// Beat annotation codes of the MIT-BIH databases (everything else is a rhythm or noise label)
+const BEAT_CODES: [&str; 19] = [
+    "N", "L", "R", "B", "A", "a", "J", "S", "V", "r", "F", "e", "j", "n", "E", "/", "f", "Q", "?",
+];
+
+/// Read beat annotations as printed by `rdann` from the WFDB tools, e.g.
+/// `    0:00.214       77     N    0    0    0`.
+///
+/// The sample number is converted to a position with `samples_per_second` and `start`.
+pub fn parse_reference_annotations(text: &str, samples_per_second: f64, start: f64) -> Vec<f64> {
+    let mut positions: Vec<f64> = text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let _time = fields.next()?;
+            let sample = fields.next()?.parse::<u64>().ok()?;
+            let code = fields.next()?;
+            BEAT_CODES
+                .contains(&code)
+                .then(|| start + sample as f64 / samples_per_second)
+        })
+        .collect();
+    positions.sort_by(|a, b| a.total_cmp(b));
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: f64 = 360.0;
+
+    /// Deterministic noise in -1..1 (linear congruential generator)
+    fn noise(state: &mut u64) -> f64 {
+        *state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((*state >> 33) as f64 / (1u64 << 31) as f64) * 2.0 - 1.0
+    }
+
+    /// Synthetic ECG at 360 Hz like the MIT-BIH records, with P, QRS and T waves, baseline
+    /// wander and noise. Returns the signal and the annotations in `rdann` format.
+    fn synthetic_record(seconds: f64) -> (UniformSignal, String) {
+        let wave = |t: f64, center: f64, width: f64, amplitude: f64| {
+            amplitude * (-((t - center) / width).powi(2) / 2.0).exp()
+        };
+        let mut state = 42;
+        let mut beats = vec![];
+        let mut position = 0.8;
+        while position < seconds - 1.0 {
+            beats.push(position);
+            // RR intervals between 0.6 and 1.1 s
+            position += 0.85 + 0.25 * noise(&mut state);
+        }
+        let samples: Vec<f64> = (0..(seconds * FS) as usize)
+            .map(|idx| {
+                let t = idx as f64 / FS;
+                let ecg: f64 = beats
+                    .iter()
+                    .filter(|beat| (t - **beat).abs() < 0.5)
+                    .map(|beat| {
+                        wave(t, beat - 0.18, 0.025, 0.15)
+                            + wave(t, beat - 0.03, 0.008, -0.15)
+                            + wave(t, *beat, 0.01, 1.2)
+                            + wave(t, beat + 0.03, 0.008, -0.25)
+                            + wave(t, beat + 0.28, 0.05, 0.3)
+                    })
+                    .sum();
+                ecg + 0.3 * (2.0 * std::f64::consts::PI * 0.2 * t).sin() + 0.03 * noise(&mut state)
+            })
+            .collect();
+        let mut annotations = "      Time   Sample #  Type  Sub Chan  Num\tAux\n".to_owned();
+        annotations += "    0:00.050       18     +    0    0    0\t(N\n";
+        for beat in beats {
+            let sample = (beat * FS).round() as u64;
+            annotations += &format!("    0:00.000 {:>8}     N    0    0    0\n", sample);
+        }
+        (
+            UniformSignal {
+                samples,
+                samples_per_second: FS,
+                start: 0.0,
+            },
+            annotations,
+        )
+    }
+
+    #[test]
+    fn detects_annotated_beats() {
+        let (signal, annotations) = synthetic_record(300.0);
+        let reference = parse_reference_annotations(&annotations, FS, signal.start);
+        assert!(reference.len() > 300);
+        let performance = compare_with_reference(&detect_r_peaks(&signal), &reference, 0.15);
+        assert!(
+            performance.sensitivity().unwrap() >= 0.99,
+            "{performance:?}"
+        );
+        assert!(
+            performance.positive_predictivity().unwrap() >= 0.99,
+            "{performance:?}"
+        );
+    }
+
+    #[test]
+    fn parses_only_beat_annotations() {
+        let annotations = "    0:00.050       18     +    0    0    0\t(N\n\
+                           \x20   0:00.214       77     N    0    0    0\n\
+                           \x20   0:01.000      360     ~    0    0    0\n\
+                           \x20   0:01.028      370     V    0    0    0\n";
+        let positions = parse_reference_annotations(annotations, 360.0, 10.0);
+        assert_eq!(positions, vec![10.0 + 77.0 / 360.0, 10.0 + 370.0 / 360.0]);
+    }
+
+    #[test]
+    fn compares_with_reference() {
+        let performance =
+            compare_with_reference(&[1.0, 2.1, 2.5, 4.0], &[1.05, 2.0, 3.0, 4.0], 0.15);
+        assert_eq!(performance.true_positives, 3);
+        assert_eq!(performance.false_positives, 1);
+        assert_eq!(performance.false_negatives, 1);
+        assert_eq!(performance.sensitivity(), Some(0.75));
+
+        let empty = compare_with_reference(&[], &[], 0.15);
+        assert_eq!(empty.sensitivity(), None);
+        assert_eq!(empty.positive_predictivity(), None);
+    }
+}
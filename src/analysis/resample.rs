@@ -0,0 +1,281 @@
+use std::ops::Range;
+
+/// Samples with a fixed sample rate, the first sample is at `start` on the x axis
+#[derive(Clone, Debug)]
+pub struct UniformSignal {
+    pub samples: Vec<f64>,
+    pub samples_per_second: f64,
+    pub start: f64,
+}
+
+impl UniformSignal {
+    /// Position on the x axis of the sample at `idx`
+    pub fn position(&self, idx: usize) -> f64 {
+        self.start + idx as f64 / self.samples_per_second
+    }
+
+    /// Index of the sample closest to `position`
+    pub fn index(&self, position: f64) -> usize {
+        (((position - self.start) * self.samples_per_second)
+            .round()
+            .max(0.0) as usize)
+            .min(self.samples.len().saturating_sub(1))
+    }
+}
+
+/// Estimate the sample rate of irregular `(x, y)` points from the median sample interval
+pub fn median_sample_rate(points: &[(f64, f64)]) -> Option<f64> {
+    let mut intervals: Vec<f64> = points
+        .windows(2)
+        .map(|w| w[1].0 - w[0].0)
+        .filter(|dt| *dt > 0.0)
+        .collect();
+    if intervals.is_empty() {
+        return None;
+    }
+    intervals.sort_by(|a, b| a.total_cmp(b));
+    Some(1.0 / intervals[intervals.len() / 2])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResamplingMethod {
+    Linear,
+    /// piecewise cubic Hermite (Catmull-Rom) interpolation
+    Cubic,
+    /// band-limited interpolation with a Lanczos windowed sinc, low-pass filtered when downsampling
+    Sinc,
+}
+
+impl ResamplingMethod {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ResamplingMethod::Linear => "Linear",
+            ResamplingMethod::Cubic => "Cubic",
+            ResamplingMethod::Sinc => "Sinc",
+        }
+    }
+}
+
+/// default multiple of the median sample interval above which steps are treated as dropouts,
+/// see `ChannelPlotter::gap_factor`
+pub const GAP_FACTOR: f64 = 3.0;
+
+/// half width of the sinc kernel in input samples
+const SINC_HALF_WIDTH: usize = 8;
+
+/// Index ranges of the ordered points without gaps, i.e. without steps longer than `max_gap`
+pub fn contiguous_segments(points: &[(f64, f64)], max_gap: f64) -> Vec<Range<usize>> {
+    let mut segments = vec![];
+    let mut start = 0;
+    for idx in 1..points.len() {
+        if points[idx].0 - points[idx - 1].0 > max_gap {
+            segments.push(start..idx);
+            start = idx;
+        }
+    }
+    if start < points.len() {
+        segments.push(start..points.len());
+    }
+    segments
+}
+
+/// Dropouts of a channel found by `find_gaps`
+#[derive(Clone, Debug, Default)]
+pub struct ChannelGaps {
+    /// samples per second from the median sample interval
+    pub samples_per_second: Option<f64>,
+    /// start and end of each gap, i.e. the last sample before and the first after it
+    pub gaps: Vec<(f64, f64)>,
+    /// first and last position of the channel
+    pub x_range: Option<(f64, f64)>,
+}
+
+impl ChannelGaps {
+    /// summed length of the gaps in seconds
+    pub fn missing(&self) -> f64 {
+        self.gaps.iter().map(|(start, end)| end - start).sum()
+    }
+}
+
+/// Gaps of the ordered points: steps longer than `gap_factor` times the median sample
+/// interval. Points without a finite value count as missing.
+pub fn find_gaps(points: &[(f64, f64)], gap_factor: f64) -> ChannelGaps {
+    let points: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|(_, y)| y.is_finite())
+        .collect();
+    let x_range = points.first().zip(points.last()).map(|(a, b)| (a.0, b.0));
+    let Some(samples_per_second) = median_sample_rate(&points) else {
+        return ChannelGaps {
+            x_range,
+            ..Default::default()
+        };
+    };
+    let gaps = contiguous_segments(&points, gap_factor / samples_per_second)
+        .windows(2)
+        .map(|w| (points[w[0].end - 1].0, points[w[1].start].0))
+        .collect();
+    ChannelGaps {
+        samples_per_second: Some(samples_per_second),
+        gaps,
+        x_range,
+    }
+}
+
+/// Interpolate the ordered `(x, y)` points onto a uniform grid with `method`.
+///
+/// Steps longer than `max_gap` are dropouts, the grid points within them are NaN.
+pub fn resample(
+    points: &[(f64, f64)],
+    samples_per_second: f64,
+    method: ResamplingMethod,
+    max_gap: f64,
+) -> UniformSignal {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return UniformSignal {
+            samples: vec![],
+            samples_per_second,
+            start: 0.0,
+        };
+    };
+    let n_samples = ((last.0 - first.0) * samples_per_second).floor() as usize + 1;
+    let mut samples = vec![f64::NAN; n_samples];
+    let input_rate = median_sample_rate(points).unwrap_or(samples_per_second);
+    // the cutoff of the sinc kernel is lowered to the new nyquist frequency when downsampling
+    let ratio = (samples_per_second / input_rate).min(1.0);
+
+    for segment in contiguous_segments(points, max_gap) {
+        let segment = &points[segment];
+        let first_sample = ((segment[0].0 - first.0) * samples_per_second).ceil() as usize;
+        let mut idx = 0;
+        for (n, sample) in samples.iter_mut().enumerate().skip(first_sample) {
+            let x = first.0 + n as f64 / samples_per_second;
+            if x > segment[segment.len() - 1].0 {
+                break;
+            }
+            while idx + 2 < segment.len() && segment[idx + 1].0 < x {
+                idx += 1;
+            }
+            *sample = match method {
+                ResamplingMethod::Linear => linear(segment, idx, x),
+                ResamplingMethod::Cubic => cubic(segment, idx, x),
+                ResamplingMethod::Sinc => sinc(segment, idx, x, ratio),
+            };
+        }
+    }
+    UniformSignal {
+        samples,
+        samples_per_second,
+        start: first.0,
+    }
+}
+
+/// fraction of the way from point `idx` to point `idx + 1` at `x`
+fn fraction(points: &[(f64, f64)], idx: usize, x: f64) -> f64 {
+    match points.get(idx + 1) {
+        Some((x1, _)) if *x1 > points[idx].0 => {
+            ((x - points[idx].0) / (x1 - points[idx].0)).clamp(0.0, 1.0)
+        }
+        _ => 0.0,
+    }
+}
+
+fn linear(points: &[(f64, f64)], idx: usize, x: f64) -> f64 {
+    let y0 = points[idx].1;
+    let y1 = points.get(idx + 1).map(|p| p.1).unwrap_or(y0);
+    y0 + (y1 - y0) * fraction(points, idx, x)
+}
+
+fn cubic(points: &[(f64, f64)], idx: usize, x: f64) -> f64 {
+    let Some(&(x1, y1)) = points.get(idx + 1) else {
+        return points[idx].1;
+    };
+    let (x0, y0) = points[idx];
+    let h = x1 - x0;
+    if h <= 0.0 {
+        return y0;
+    }
+    // slopes from the neighbours, one-sided at the ends of the segment
+    let slope = |k: usize| {
+        let previous = points[k.saturating_sub(1)];
+        let next = points[(k + 1).min(points.len() - 1)];
+        if next.0 > previous.0 {
+            (next.1 - previous.1) / (next.0 - previous.0)
+        } else {
+            0.0
+        }
+    };
+    let (m0, m1) = (slope(idx) * h, slope(idx + 1) * h);
+    let t = fraction(points, idx, x);
+    let (t2, t3) = (t * t, t * t * t);
+    (2.0 * t3 - 3.0 * t2 + 1.0) * y0
+        + (t3 - 2.0 * t2 + t) * m0
+        + (-2.0 * t3 + 3.0 * t2) * y1
+        + (t3 - t2) * m1
+}
+
+/// The points are treated as equidistant, the position between them is the fractional index
+fn sinc(points: &[(f64, f64)], idx: usize, x: f64, ratio: f64) -> f64 {
+    let u = idx as f64 + fraction(points, idx, x);
+    let half_width = (SINC_HALF_WIDTH as f64 / ratio).ceil() as i64;
+    let center = u.floor() as i64;
+    let (mut sum, mut weights) = (0.0, 0.0);
+    for k in center - half_width + 1..=center + half_width {
+        // repeat the edge samples instead of reaching over the ends of the segment
+        let y = points[k.clamp(0, points.len() as i64 - 1) as usize].1;
+        let d = (u - k as f64) * ratio;
+        let weight = lanczos(d, SINC_HALF_WIDTH as f64);
+        sum += weight * y;
+        weights += weight;
+    }
+    if weights != 0.0 {
+        sum / weights
+    } else {
+        points[idx].1
+    }
+}
+
+fn lanczos(x: f64, a: f64) -> f64 {
+    if x == 0.0 {
+        return 1.0;
+    }
+    if x.abs() >= a {
+        return 0.0;
+    }
+    let pi_x = std::f64::consts::PI * x;
+    a * pi_x.sin() * (pi_x / a).sin() / (pi_x * pi_x)
+}
+
+/// Linear interpolation of the ordered `(x, y)` points onto a uniform grid
+pub fn resample_linear(points: &[(f64, f64)], samples_per_second: f64) -> UniformSignal {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return UniformSignal {
+            samples: vec![],
+            samples_per_second,
+            start: 0.0,
+        };
+    };
+    let n_samples = ((last.0 - first.0) * samples_per_second).floor() as usize + 1;
+    let mut samples = Vec::with_capacity(n_samples);
+    let mut idx = 0;
+    for n in 0..n_samples {
+        let x = first.0 + n as f64 / samples_per_second;
+        while idx + 1 < points.len() && points[idx + 1].0 < x {
+            idx += 1;
+        }
+        let (x0, y0) = points[idx];
+        let (x1, y1) = points[(idx + 1).min(points.len() - 1)];
+        let y = if x1 > x0 {
+            y0 + (y1 - y0) * ((x - x0) / (x1 - x0)).clamp(0.0, 1.0)
+        } else {
+            y1
+        };
+        samples.push(y);
+    }
+    UniformSignal {
+        samples,
+        samples_per_second,
+        start: first.0,
+    }
+}
@@ -0,0 +1,212 @@
+use crate::analysis::filters::{filtfilt_missing, Biquad};
+use crate::analysis::qrs_detection::MAX_RR;
+use crate::analysis::resample::{resample_linear, UniformSignal};
+use crate::analysis::spectrum::welch;
+
+/// sample rate of the respiration surrogates
+const SURROGATE_RATE: f64 = 4.0;
+
+/// band of plausible breathing frequencies in Hz, 6 to 42 breaths per minute
+const BREATHING_BAND: (f64, f64) = (0.1, 0.7);
+
+/// estimates differing more (standard deviation in breaths per minute) aren't fused
+const MAX_SPREAD: f64 = 4.0;
+
+/// A signal modulated by breathing
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Surrogate {
+    /// amplitude of the R peaks above the baseline, changed by the heart axis and chest impedance
+    RAmplitude,
+    /// RR intervals, shortened during inspiration (respiratory sinus arrhythmia)
+    Rsa,
+    /// area of the QRS complexes
+    QrsArea,
+    /// movement of the chest measured by the accelerometer
+    Chest,
+}
+
+impl Surrogate {
+    pub const ALL: [Surrogate; 4] = [
+        Surrogate::RAmplitude,
+        Surrogate::Rsa,
+        Surrogate::QrsArea,
+        Surrogate::Chest,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Surrogate::RAmplitude => "R amplitude",
+            Surrogate::Rsa => "RSA",
+            Surrogate::QrsArea => "QRS area",
+            Surrogate::Chest => "Chest movement",
+        }
+    }
+}
+
+/// Respiration rates of one window in breaths per minute
+#[derive(Clone, Copy, Debug)]
+pub struct RespirationEstimate {
+    /// center of the window
+    pub position: f64,
+    /// rate of each surrogate, in the order of `Surrogate::ALL`
+    pub rates: [Option<f64>; 4],
+    /// mean of the rates if they agree
+    pub fused: Option<f64>,
+}
+
+/// Surrogates of the respiration from the R peaks `beats` of `ecg`, band passed to the
+/// breathing band at 4 Hz. The chest movement is added if an accelerometer axis is given.
+pub fn surrogates(
+    ecg: &UniformSignal,
+    beats: &[f64],
+    chest: Option<&UniformSignal>,
+) -> Vec<(Surrogate, UniformSignal)> {
+    let fs = ecg.samples_per_second;
+    let samples = |ms: f64| (ms * fs / 1E3).round() as usize;
+    let mut amplitudes = vec![];
+    let mut areas = vec![];
+    for position in beats {
+        let r = ecg.index(*position);
+        let (Some(baseline_start), Some(qrs_start)) =
+            (r.checked_sub(samples(120.0)), r.checked_sub(samples(40.0)))
+        else {
+            continue;
+        };
+        let qrs_end = r + samples(40.0);
+        if qrs_end >= ecg.samples.len() {
+            continue;
+        }
+        // the PR segment is the isoelectric baseline, so baseline wander doesn't count
+        let pr_segment = &ecg.samples[baseline_start..r - samples(80.0)];
+        let baseline = pr_segment.iter().sum::<f64>() / pr_segment.len().max(1) as f64;
+        let area = ecg.samples[qrs_start..=qrs_end]
+            .iter()
+            .map(|v| v - baseline)
+            .sum::<f64>()
+            / fs;
+        if baseline.is_finite() && area.is_finite() {
+            amplitudes.push((*position, ecg.samples[r] - baseline));
+            areas.push((*position, area));
+        }
+    }
+    let rr: Vec<(f64, f64)> = beats.windows(2).map(|w| (w[1], w[1] - w[0])).collect();
+
+    let band_pass = |signal: UniformSignal| -> UniformSignal {
+        UniformSignal {
+            samples: filtfilt_missing(
+                &[
+                    Biquad::high_pass(BREATHING_BAND.0, SURROGATE_RATE),
+                    Biquad::low_pass(BREATHING_BAND.1, SURROGATE_RATE),
+                ],
+                &signal.samples,
+            ),
+            ..signal
+        }
+    };
+    let mut result = vec![];
+    for (surrogate, points) in [
+        (Surrogate::RAmplitude, amplitudes),
+        (Surrogate::Rsa, rr),
+        (Surrogate::QrsArea, areas),
+    ] {
+        if points.len() > 2 {
+            result.push((
+                surrogate,
+                band_pass(resample_linear(&points, SURROGATE_RATE)),
+            ));
+        }
+    }
+    if let Some(chest) = chest {
+        // low pass before taking every nth sample, so faster movements don't alias
+        let low_passed = filtfilt_missing(
+            &[Biquad::low_pass(1.0, chest.samples_per_second)],
+            &chest.samples,
+        );
+        let duration = chest.samples.len() as f64 / chest.samples_per_second;
+        let decimated = UniformSignal {
+            samples: (0..(duration * SURROGATE_RATE) as usize)
+                .map(|n| low_passed[chest.index(chest.start + n as f64 / SURROGATE_RATE)])
+                .collect(),
+            samples_per_second: SURROGATE_RATE,
+            start: chest.start,
+        };
+        if decimated.samples.len() > 2 {
+            result.push((Surrogate::Chest, band_pass(decimated)));
+        }
+    }
+    result
+}
+
+/// Respiration rate in windows of `window` seconds every `step` seconds, from the dominant
+/// frequency of each surrogate in the breathing band. The rates are fused as in the smart
+/// fusion of Karlen W, Raman S, Ansermino JM, Dumont GA: "Multiparameter Respiratory Rate
+/// Estimation From the Photoplethysmogram", IEEE Trans Biomed Eng 60(7), 2013: their mean,
+/// if they deviate less than 4 breaths per minute.
+pub fn respiration_rate(
+    surrogates: &[(Surrogate, UniformSignal)],
+    beats: &[f64],
+    window: f64,
+    step: f64,
+) -> Vec<RespirationEstimate> {
+    let (Some(first), Some(last)) = (
+        surrogates.iter().map(|(_, s)| s.start).reduce(f64::min),
+        surrogates
+            .iter()
+            .map(|(_, s)| s.position(s.samples.len().saturating_sub(1)))
+            .reduce(f64::max),
+    ) else {
+        return vec![];
+    };
+    let window_samples = (window * SURROGATE_RATE).round() as usize;
+    let mut estimates = vec![];
+    let mut start = first;
+    while start + window <= last && window_samples > 2 {
+        let end = start + window;
+        // beats are missing within the window
+        let first_beat = beats.partition_point(|b| *b < start);
+        let last_beat = beats.partition_point(|b| *b <= end);
+        let beats_complete = last_beat > first_beat + 1
+            && beats[first_beat..last_beat]
+                .windows(2)
+                .all(|w| w[1] - w[0] < MAX_RR)
+            && beats[first_beat] - start < MAX_RR
+            && end - beats[last_beat - 1] < MAX_RR;
+
+        let mut rates = [None; 4];
+        for (surrogate, signal) in surrogates {
+            let from_beats = *surrogate != Surrogate::Chest;
+            let covered = signal.start <= start
+                && signal.position(signal.samples.len().saturating_sub(1)) >= end;
+            if !covered || (from_beats && !beats_complete) {
+                continue;
+            }
+            let first_sample = signal.index(start);
+            let samples = &signal.samples
+                [first_sample..(first_sample + window_samples).min(signal.samples.len())];
+            // the chest movement has dropouts
+            if samples.iter().any(|v| !v.is_finite()) {
+                continue;
+            }
+            let spectrum = welch(samples, SURROGATE_RATE, samples.len(), 0.0, 512);
+            let idx = Surrogate::ALL
+                .iter()
+                .position(|s| s == surrogate)
+                .unwrap_or(0);
+            rates[idx] = spectrum
+                .peak_frequency(BREATHING_BAND.0, BREATHING_BAND.1)
+                .map(|f| f * 60.0);
+        }
+        let available: Vec<f64> = rates.iter().flatten().copied().collect();
+        let mean = available.iter().sum::<f64>() / available.len().max(1) as f64;
+        let spread = (available.iter().map(|r| (r - mean).powi(2)).sum::<f64>()
+            / available.len().max(1) as f64)
+            .sqrt();
+        estimates.push(RespirationEstimate {
+            position: start + window / 2.0,
+            rates,
+            fused: (!available.is_empty() && spread < MAX_SPREAD).then_some(mean),
+        });
+        start += step;
+    }
+    estimates
+}
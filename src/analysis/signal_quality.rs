@@ -0,0 +1,196 @@
+use crate::analysis::qrs_detection::{
+    compare_with_reference, detect_r_peaks, detect_r_peaks_elgendi,
+};
+use crate::analysis::resample::UniformSignal;
+
+/// length of the windows the quality is rated for, in seconds
+pub const QUALITY_WINDOW: f64 = 10.0;
+
+/// beats of both detectors within this many seconds are the same beat
+const MATCH_TOLERANCE: f64 = 0.15;
+
+/// flat stretches shorter than this (in seconds) don't count as flat line
+const MIN_FLATLINE: f64 = 0.2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Quality {
+    Unusable,
+    Acceptable,
+    Good,
+}
+
+impl Quality {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Quality::Unusable => "Unusable",
+            Quality::Acceptable => "Acceptable",
+            Quality::Good => "Good",
+        }
+    }
+}
+
+/// Quality measures of one window of an ECG
+#[derive(Clone, Copy, Debug)]
+pub struct QualityWindow {
+    pub start: f64,
+    pub end: f64,
+    /// kurtosis of the samples, about 3 for noise and above 5 for a clean ECG
+    pub kurtosis: f64,
+    /// beats found by both detectors divided by the beats found by either, None without beats
+    pub bsqi: Option<f64>,
+    /// share of the samples in flat stretches
+    pub flatline: f64,
+    /// share of the samples in flat stretches at the minimum or maximum, e.g. a clipped signal
+    pub saturation: f64,
+    /// share of the window in gaps
+    pub missing: f64,
+    pub quality: Quality,
+}
+
+/// Rating of the quality of an ECG in consecutive windows
+#[derive(Clone, Debug, Default)]
+pub struct SignalQuality {
+    pub windows: Vec<QualityWindow>,
+}
+
+impl SignalQuality {
+    /// Merged ranges of the unusable windows
+    pub fn unusable_ranges(&self) -> Vec<(f64, f64)> {
+        let mut ranges: Vec<(f64, f64)> = vec![];
+        for window in self.windows.iter() {
+            if window.quality != Quality::Unusable {
+                continue;
+            }
+            match ranges.last_mut() {
+                Some(last) if window.start <= last.1 => last.1 = window.end,
+                _ => ranges.push((window.start, window.end)),
+            }
+        }
+        ranges
+    }
+
+    /// Share of the windows of each quality, from good to unusable
+    pub fn shares(&self) -> Vec<(Quality, f64)> {
+        let n = self.windows.len().max(1) as f64;
+        [Quality::Good, Quality::Acceptable, Quality::Unusable]
+            .into_iter()
+            .map(|quality| {
+                let count = self.windows.iter().filter(|w| w.quality == quality).count();
+                (quality, count as f64 / n)
+            })
+            .collect()
+    }
+}
+
+/// Rate the quality of an ECG in windows of `window_length` seconds, after Li Q, Mark RG,
+/// Clifford GD: "Robust heart rate estimation from multiple asynchronous noisy sources using
+/// signal quality indices and a Kalman filter", Physiol Meas 29(1), 2008 (bSQI and kurtosis).
+///
+/// Windows with flat lines, clipping, mostly gaps, a noise-like amplitude distribution or
+/// little agreement of two beat detectors are unusable, windows with good agreement and a peaked amplitude distribution are good.
+pub fn signal_quality(signal: &UniformSignal, window_length: f64) -> SignalQuality {
+    let fs = signal.samples_per_second;
+    let window = (window_length * fs).round() as usize;
+    if window < 2 || signal.samples.len() < window {
+        return SignalQuality::default();
+    }
+    let pan_tompkins = detect_r_peaks(signal);
+    let elgendi = detect_r_peaks_elgendi(signal);
+    let flat = flat_samples(&signal.samples, (MIN_FLATLINE * fs).round() as usize);
+
+    let windows = (0..signal.samples.len() / window)
+        .map(|nr| {
+            let range = nr * window..(nr + 1) * window;
+            let samples = &signal.samples[range.clone()];
+            let (start, end) = (signal.position(range.start), signal.position(range.end));
+            let values: Vec<f64> = samples.iter().copied().filter(|v| v.is_finite()).collect();
+            let missing = 1.0 - values.len() as f64 / window as f64;
+            let flatline = flat[range].iter().filter(|f| **f).count() as f64 / window as f64;
+            let saturation = clipped_samples(&values) as f64 / window as f64;
+            let kurtosis = kurtosis(&values);
+            let bsqi = agreement(&pan_tompkins, &elgendi, start, end);
+
+            let quality = if missing > 0.5
+                || flatline > 0.1
+                || saturation > 0.01
+                || kurtosis < 3.0
+                || bsqi.is_none_or(|b| b < 0.7)
+            {
+                Quality::Unusable
+            } else if bsqi.is_some_and(|b| b >= 0.9) && kurtosis > 5.0 {
+                Quality::Good
+            } else {
+                Quality::Acceptable
+            };
+            QualityWindow {
+                start,
+                end,
+                kurtosis,
+                bsqi,
+                flatline,
+                saturation,
+                missing,
+                quality,
+            }
+        })
+        .collect();
+    SignalQuality { windows }
+}
+
+/// Beats found by both detectors divided by the beats found by either, between `start` and `end`
+fn agreement(a: &[f64], b: &[f64], start: f64, end: f64) -> Option<f64> {
+    let within = |beats: &[f64]| -> Vec<f64> {
+        beats
+            .iter()
+            .copied()
+            .filter(|p| *p >= start && *p < end)
+            .collect()
+    };
+    let (a, b) = (within(a), within(b));
+    if a.is_empty() && b.is_empty() {
+        return None;
+    }
+    let matched = compare_with_reference(&a, &b, MATCH_TOLERANCE).true_positives as f64;
+    Some(matched / (a.len() as f64 + b.len() as f64 - matched))
+}
+
+/// Kurtosis of the values (not the excess kurtosis), 0 if they are constant
+fn kurtosis(values: &[f64]) -> f64 {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    if variance > 0.0 {
+        values.iter().map(|v| (v - mean).powi(4)).sum::<f64>() / n / variance.powi(2)
+    } else {
+        0.0
+    }
+}
+
+/// Number of samples in runs of at least three samples at the minimum or maximum
+fn clipped_samples(values: &[f64]) -> usize {
+    let min = values.iter().fold(f64::INFINITY, |a, b| a.min(*b));
+    let max = values.iter().fold(f64::NEG_INFINITY, |a, b| a.max(*b));
+    if max <= min {
+        return 0;
+    }
+    values
+        .chunk_by(|a, b| a == b)
+        .filter(|run| run.len() >= 3 && (run[0] == min || run[0] == max))
+        .map(|run| run.len())
+        .sum()
+}
+
+/// For each sample whether it's part of a run of at least `min_length` equal samples
+fn flat_samples(samples: &[f64], min_length: usize) -> Vec<bool> {
+    let mut flat = vec![false; samples.len()];
+    let mut run_start = 0;
+    for n in 1..=samples.len() {
+        if n == samples.len() || samples[n] != samples[run_start] {
+            if n - run_start >= min_length.max(2) && samples[run_start].is_finite() {
+                flat[run_start..n].iter_mut().for_each(|f| *f = true);
+            }
+            run_start = n;
+        }
+    }
+    flat
+}
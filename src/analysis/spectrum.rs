@@ -0,0 +1,304 @@
+use std::f64::consts::PI;
+
+/// Power spectral density, `power[i]` is the density at `frequencies[i]` (in unit² / Hz)
+#[derive(Clone, Debug, Default)]
+pub struct Spectrum {
+    pub frequencies: Vec<f64>,
+    pub power: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Power between `low` (inclusive) and `high` (exclusive) in unit²
+    pub fn band_power(&self, low: f64, high: f64) -> f64 {
+        self.frequencies
+            .windows(2)
+            .zip(self.power.windows(2))
+            .filter(|(f, _)| f[0] >= low && f[0] < high)
+            .map(|(f, p)| (f[1] - f[0]) * (p[0] + p[1]) / 2.0)
+            .sum()
+    }
+
+    /// Frequency with the highest density between `low` and `high`
+    pub fn peak_frequency(&self, low: f64, high: f64) -> Option<f64> {
+        self.frequencies
+            .iter()
+            .zip(self.power.iter())
+            .filter(|(f, _)| **f >= low && **f < high)
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(f, _)| *f)
+    }
+}
+
+/// In-place radix-2 FFT, the length of `re` and `im` must be a power of two
+pub fn fft(re: &mut [f64], im: &mut [f64]) {
+    let n = re.len();
+    assert!(n.is_power_of_two() && im.len() == n);
+
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut length = 2;
+    while length <= n {
+        let angle = -2.0 * PI / length as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(length) {
+            let (mut u_re, mut u_im) = (1.0, 0.0);
+            for k in 0..length / 2 {
+                let a = start + k;
+                let b = a + length / 2;
+                let t_re = re[b] * u_re - im[b] * u_im;
+                let t_im = re[b] * u_im + im[b] * u_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+                (u_re, u_im) = (u_re * w_re - u_im * w_im, u_re * w_im + u_im * w_re);
+            }
+        }
+        length <<= 1;
+    }
+}
+
+/// Short-time power spectra, `power[i][k]` is the density at `frequencies[k]` in the segment
+/// centered `times[i]` seconds after the first sample, empty for segments with missing samples
+#[derive(Clone, Debug, Default)]
+pub struct Spectrogram {
+    pub frequencies: Vec<f64>,
+    pub times: Vec<f64>,
+    pub power: Vec<Vec<f64>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowFunction {
+    Rectangular,
+    Hann,
+    Hamming,
+    Blackman,
+}
+
+impl WindowFunction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            WindowFunction::Rectangular => "Rectangular",
+            WindowFunction::Hann => "Hann",
+            WindowFunction::Hamming => "Hamming",
+            WindowFunction::Blackman => "Blackman",
+        }
+    }
+
+    pub fn coefficients(&self, length: usize) -> Vec<f64> {
+        if length < 2 {
+            return vec![1.0; length];
+        }
+        let cos = |n: usize, k: f64| (k * 2.0 * PI * n as f64 / (length - 1) as f64).cos();
+        (0..length)
+            .map(|n| match self {
+                WindowFunction::Rectangular => 1.0,
+                WindowFunction::Hann => 0.5 - 0.5 * cos(n, 1.0),
+                WindowFunction::Hamming => 0.54 - 0.46 * cos(n, 1.0),
+                WindowFunction::Blackman => 0.42 - 0.5 * cos(n, 1.0) + 0.08 * cos(n, 2.0),
+            })
+            .collect()
+    }
+}
+
+/// Width in Hz of the rectangular filter passing as much noise as a frequency bin of `window`
+pub fn equivalent_noise_bandwidth(window: &[f64], samples_per_second: f64) -> f64 {
+    let sum: f64 = window.iter().sum();
+    let squares: f64 = window.iter().map(|w| w * w).sum();
+    samples_per_second * squares / (sum * sum)
+}
+
+pub fn hann_window(length: usize) -> Vec<f64> {
+    WindowFunction::Hann.coefficients(length)
+}
+
+/// One-sided periodogram of a windowed segment, zero padded to `n_fft` (a power of two)
+fn periodogram(segment: &[f64], window: &[f64], n_fft: usize, samples_per_second: f64) -> Vec<f64> {
+    let mut re = vec![0.0; n_fft];
+    let mut im = vec![0.0; n_fft];
+    let mean = segment.iter().sum::<f64>() / segment.len() as f64;
+    segment
+        .iter()
+        .zip(window.iter())
+        .enumerate()
+        .for_each(|(idx, (value, w))| re[idx] = (value - mean) * w);
+    fft(&mut re, &mut im);
+
+    let window_power: f64 = window.iter().map(|w| w * w).sum();
+    let scale = 1.0 / (samples_per_second * window_power);
+    (0..=n_fft / 2)
+        .map(|k| {
+            let power = (re[k] * re[k] + im[k] * im[k]) * scale;
+            // the negative frequencies are folded onto the positive ones
+            if k == 0 || k == n_fft / 2 {
+                power
+            } else {
+                2.0 * power
+            }
+        })
+        .collect()
+}
+
+/// Welch's averaged periodogram with a Hann window, segments of `segment_length` samples
+/// overlapping by `overlap` (0 to < 1), each zero padded to at least `min_n_fft` samples
+pub fn welch(
+    samples: &[f64],
+    samples_per_second: f64,
+    segment_length: usize,
+    overlap: f64,
+    min_n_fft: usize,
+) -> Spectrum {
+    welch_with_window(
+        samples,
+        samples_per_second,
+        segment_length,
+        overlap,
+        min_n_fft,
+        WindowFunction::Hann,
+    )
+}
+
+/// Welch's averaged periodogram with the given window function,
+/// segments with missing (NaN) samples are left out
+pub fn welch_with_window(
+    samples: &[f64],
+    samples_per_second: f64,
+    segment_length: usize,
+    overlap: f64,
+    min_n_fft: usize,
+    window: WindowFunction,
+) -> Spectrum {
+    let segment_length = segment_length.min(samples.len());
+    if segment_length < 2 {
+        return Spectrum::default();
+    }
+    let step = ((segment_length as f64 * (1.0 - overlap)).round() as usize).max(1);
+    let n_fft = segment_length.max(min_n_fft).next_power_of_two();
+    let window = window.coefficients(segment_length);
+
+    let mut power = vec![0.0; n_fft / 2 + 1];
+    let mut n_segments = 0;
+    let mut start = 0;
+    while start + segment_length <= samples.len() {
+        let segment = &samples[start..start + segment_length];
+        start += step;
+        if segment.iter().any(|v| !v.is_finite()) {
+            continue;
+        }
+        periodogram(segment, &window, n_fft, samples_per_second)
+            .iter()
+            .zip(power.iter_mut())
+            .for_each(|(p, sum)| *sum += p);
+        n_segments += 1;
+    }
+    if n_segments == 0 {
+        return Spectrum::default();
+    }
+    power.iter_mut().for_each(|p| *p /= n_segments as f64);
+    Spectrum {
+        frequencies: (0..power.len())
+            .map(|k| k as f64 * samples_per_second / n_fft as f64)
+            .collect(),
+        power,
+    }
+}
+
+/// Periodograms of segments of `segment_length` samples starting every `step` samples
+pub fn spectrogram(
+    samples: &[f64],
+    samples_per_second: f64,
+    segment_length: usize,
+    step: usize,
+    window: WindowFunction,
+) -> Spectrogram {
+    if segment_length < 2 || samples.len() < segment_length {
+        return Spectrogram::default();
+    }
+    let n_fft = segment_length.next_power_of_two();
+    let window = window.coefficients(segment_length);
+    let mut spectrogram = Spectrogram {
+        frequencies: (0..=n_fft / 2)
+            .map(|k| k as f64 * samples_per_second / n_fft as f64)
+            .collect(),
+        ..Default::default()
+    };
+    let mut start = 0;
+    while start + segment_length <= samples.len() {
+        let segment = &samples[start..start + segment_length];
+        spectrogram
+            .times
+            .push((start as f64 + segment_length as f64 / 2.0) / samples_per_second);
+        spectrogram
+            .power
+            .push(if segment.iter().all(|v| v.is_finite()) {
+                periodogram(segment, &window, n_fft, samples_per_second)
+            } else {
+                vec![]
+            });
+        start += step.max(1);
+    }
+    spectrogram
+}
+
+/// Lomb-Scargle periodogram of unevenly sampled values `(t, y)` at `frequencies`.
+///
+/// The periodogram is scaled so its integral equals the variance of `y`,
+/// which makes the band powers comparable with those of `welch`.
+pub fn lomb_scargle(points: &[(f64, f64)], frequencies: &[f64]) -> Spectrum {
+    let n = points.len() as f64;
+    if points.len() < 3 || frequencies.len() < 2 {
+        return Spectrum::default();
+    }
+    let mean = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let variance = points.iter().map(|(_, y)| (y - mean).powi(2)).sum::<f64>() / (n - 1.0);
+
+    let power: Vec<f64> = frequencies
+        .iter()
+        .map(|f| {
+            let omega = 2.0 * PI * f;
+            if omega == 0.0 {
+                return 0.0;
+            }
+            let (sin_sum, cos_sum) = points.iter().fold((0.0, 0.0), |(s, c), (t, _)| {
+                let (sin, cos) = (2.0 * omega * t).sin_cos();
+                (s + sin, c + cos)
+            });
+            let tau = sin_sum.atan2(cos_sum) / (2.0 * omega);
+            let (mut yc, mut ys, mut cc, mut ss) = (0.0, 0.0, 0.0, 0.0);
+            points.iter().for_each(|(t, y)| {
+                let (sin, cos) = (omega * (t - tau)).sin_cos();
+                yc += (y - mean) * cos;
+                ys += (y - mean) * sin;
+                cc += cos * cos;
+                ss += sin * sin;
+            });
+            0.5 * (yc * yc / cc + ys * ys / ss)
+        })
+        .collect();
+
+    let mut spectrum = Spectrum {
+        frequencies: frequencies.to_vec(),
+        power,
+    };
+    let total = spectrum.band_power(frequencies[0], f64::INFINITY);
+    if total > 0.0 {
+        spectrum
+            .power
+            .iter_mut()
+            .for_each(|p| *p *= variance / total);
+    }
+    spectrum
+}
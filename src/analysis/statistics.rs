@@ -0,0 +1,101 @@
+use crate::analysis::resample::{contiguous_segments, median_sample_rate};
+
+/// percentiles reported in `ChannelStatistics::percentiles`
+pub const PERCENTILES: [f64; 5] = [5.0, 25.0, 50.0, 75.0, 95.0];
+
+/// Descriptive statistics of the values of a channel within a range
+#[derive(Clone, Copy, Debug)]
+pub struct ChannelStatistics {
+    pub count: usize,
+    /// time from the first to the last sample in seconds
+    pub duration: f64,
+    /// samples per second from the count and the duration, lower than the nominal rate with gaps
+    pub effective_sample_rate: f64,
+    /// samples per second from the median sample interval
+    pub nominal_sample_rate: Option<f64>,
+    /// standard deviation of the sample intervals without the gaps, in seconds
+    pub jitter: Option<f64>,
+    /// number of intervals longer than the gap factor times the median interval
+    pub gaps: usize,
+    /// summed length of the gaps in seconds
+    pub gap_duration: f64,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// standard deviation
+    pub std: f64,
+    /// root mean square
+    pub rms: f64,
+    /// the values at `PERCENTILES`
+    pub percentiles: [f64; 5],
+}
+
+/// Statistics of the `(position, value)` pairs sorted by position, values which aren't
+/// finite are left out. Intervals longer than `gap_factor` times the median interval are gaps.
+/// None if there are no finite values.
+pub fn statistics(points: &[(f64, f64)], gap_factor: f64) -> Option<ChannelStatistics> {
+    let points: Vec<(f64, f64)> = points
+        .iter()
+        .copied()
+        .filter(|(_, y)| y.is_finite())
+        .collect();
+    let (first, last) = (points.first()?, points.last()?);
+    let mut values: Vec<f64> = points.iter().map(|(_, y)| *y).collect();
+    values.sort_by(f64::total_cmp);
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let duration = last.0 - first.0;
+
+    let nominal_sample_rate = median_sample_rate(&points);
+    let (mut gaps, mut gap_duration, mut jitter) = (0, 0.0, None);
+    if let Some(rate) = nominal_sample_rate {
+        let segments = contiguous_segments(&points, gap_factor / rate);
+        gaps = segments.len().saturating_sub(1);
+        gap_duration = segments
+            .windows(2)
+            .map(|w| points[w[1].start].0 - points[w[0].end - 1].0)
+            .sum();
+        let intervals: Vec<f64> = segments
+            .iter()
+            .flat_map(|segment| points[segment.clone()].windows(2).map(|w| w[1].0 - w[0].0))
+            .collect();
+        if !intervals.is_empty() {
+            let mean_interval = intervals.iter().sum::<f64>() / intervals.len() as f64;
+            jitter = Some(
+                (intervals
+                    .iter()
+                    .map(|i| (i - mean_interval).powi(2))
+                    .sum::<f64>()
+                    / intervals.len() as f64)
+                    .sqrt(),
+            );
+        }
+    }
+    Some(ChannelStatistics {
+        count: values.len(),
+        duration,
+        effective_sample_rate: if duration > 0.0 {
+            (n - 1.0) / duration
+        } else {
+            0.0
+        },
+        nominal_sample_rate,
+        jitter,
+        gaps,
+        gap_duration,
+        min: values[0],
+        max: values[values.len() - 1],
+        mean,
+        std: (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n).sqrt(),
+        rms: (values.iter().map(|v| v * v).sum::<f64>() / n).sqrt(),
+        percentiles: PERCENTILES.map(|p| percentile(&values, p)),
+    })
+}
+
+/// Percentile `p` (0 to 100) of the sorted `values`, linearly interpolated between the ranks
+fn percentile(values: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (values.len() - 1) as f64;
+    let below = rank.floor() as usize;
+    let above = rank.ceil() as usize;
+    values[below] + (values[above] - values[below]) * (rank - below as f64)
+}
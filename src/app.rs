@@ -2,6 +2,24 @@ use egui::{global_dark_light_mode_buttons, Context, Modifiers};
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 use crate::data_structures::{DrawableChannel, SampleBasedChannel, TimeBasedChannel};
+use crate::keyboard_navigation::{KeyBindings, KeyboardNavigation};
+use crate::readout::show_readout;
+use crate::time_format::TimeZoneSetting;
+use crate::views::activity::ActivityView;
+use crate::views::af_screening::AfScreeningView;
+use crate::views::beat_detection::BeatDetectionView;
+use crate::views::beat_overlay::BeatOverlayView;
+use crate::views::data_quality::DataQualityView;
+use crate::views::delineation::DelineationView;
+use crate::views::derived_channels::DerivedChannelsView;
+use crate::views::heart_rate::HeartRateView;
+use crate::views::heart_rate_comparison::HeartRateComparisonView;
+use crate::views::hrv::HrvView;
+use crate::views::resampling::ResamplingView;
+use crate::views::respiration::RespirationView;
+use crate::views::selections::SelectionsView;
+use crate::views::spectrum::SpectrumView;
+use crate::views::statistics::StatisticsView;
 use crate::{parse_content, ChannelPlotter};
 
 use std::future::Future;
@@ -28,8 +46,31 @@ pub struct MonitorApp {
     take_screenshot: bool,
     app_state: AppState,
     plotter: ChannelPlotter,
+    keyboard_navigation: KeyboardNavigation,
+    activity: ActivityView,
+    af_screening: AfScreeningView,
+    beat_detection: BeatDetectionView,
+    beat_overlay: BeatOverlayView,
+    data_quality: DataQualityView,
+    delineation: DelineationView,
+    derived_channels: DerivedChannelsView,
+    heart_rate: HeartRateView,
+    heart_rate_comparison: HeartRateComparisonView,
+    hrv: HrvView,
+    resampling: ResamplingView,
+    respiration: RespirationView,
+    selections: SelectionsView,
+    spectrum: SpectrumView,
+    statistics: StatisticsView,
 }
 
+const KEY_BINDINGS_KEY: &str = "key_bindings";
+const TIME_ZONE_KEY: &str = "time_zone";
+const RR_CORRECTIONS_KEY: &str = "rr_corrections";
+const FILTER_SETTINGS_KEY: &str = "filter_settings";
+const DERIVED_CHANNELS_KEY: &str = "derived_channels";
+const GAP_FACTOR_KEY: &str = "gap_factor";
+
 impl Default for MonitorApp {
     fn default() -> Self {
         let plotter = ChannelPlotter::new("ECG".to_owned(), vec![]);
@@ -42,18 +83,72 @@ impl Default for MonitorApp {
             take_screenshot: false,
             app_state: AppState::Startup,
             plotter,
+            keyboard_navigation: KeyboardNavigation::new(KeyBindings::default()),
+            activity: ActivityView::default(),
+            af_screening: AfScreeningView::default(),
+            beat_detection: BeatDetectionView::default(),
+            beat_overlay: BeatOverlayView::default(),
+            data_quality: DataQualityView::default(),
+            delineation: DelineationView::default(),
+            derived_channels: DerivedChannelsView::default(),
+            heart_rate: HeartRateView::default(),
+            heart_rate_comparison: HeartRateComparisonView::default(),
+            hrv: HrvView::default(),
+            resampling: ResamplingView::default(),
+            respiration: RespirationView::default(),
+            selections: SelectionsView::default(),
+            spectrum: SpectrumView::default(),
+            statistics: StatisticsView::default(),
         }
     }
 }
 
 impl MonitorApp {
     /// Called once before the first frame.
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        MonitorApp::default()
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app = MonitorApp::default();
+        if let Some(storage) = cc.storage {
+            if let Some(key_bindings) = eframe::get_value(storage, KEY_BINDINGS_KEY) {
+                app.keyboard_navigation = KeyboardNavigation::new(key_bindings);
+            }
+            if let Some(time_zone) = eframe::get_value(storage, TIME_ZONE_KEY) {
+                app.plotter.time_zone = time_zone;
+            }
+            if let Some(rr_corrections) = eframe::get_value(storage, RR_CORRECTIONS_KEY) {
+                app.plotter.rr_corrections = rr_corrections;
+            }
+            if let Some(filter_settings) = eframe::get_value(storage, FILTER_SETTINGS_KEY) {
+                app.plotter.filter_settings = filter_settings;
+            }
+            if let Some(derived_channels) = eframe::get_value(storage, DERIVED_CHANNELS_KEY) {
+                app.plotter.derived_channels = derived_channels;
+            }
+            if let Some(gap_factor) = eframe::get_value(storage, GAP_FACTOR_KEY) {
+                app.plotter.gap_factor = gap_factor;
+            }
+        }
+        app
     }
 }
 
 impl eframe::App for MonitorApp {
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(
+            storage,
+            KEY_BINDINGS_KEY,
+            &self.keyboard_navigation.key_bindings,
+        );
+        eframe::set_value(storage, TIME_ZONE_KEY, &self.plotter.time_zone);
+        eframe::set_value(storage, RR_CORRECTIONS_KEY, &self.plotter.rr_corrections);
+        eframe::set_value(storage, FILTER_SETTINGS_KEY, &self.plotter.filter_settings);
+        eframe::set_value(
+            storage,
+            DERIVED_CHANNELS_KEY,
+            &self.plotter.derived_channels,
+        );
+        eframe::set_value(storage, GAP_FACTOR_KEY, &self.plotter.gap_factor);
+    }
+
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.with_layout(egui::Layout::left_to_right(egui::Align::Center), |ui| {
@@ -113,7 +208,7 @@ impl eframe::App for MonitorApp {
                 }
                 ui.separator();
                 if ui.button("Clear loaded data").clicked() {
-                    self.plotter.channels.clear();
+                    self.plotter.clear();
                 }
                 ui.separator();
 
@@ -125,6 +220,45 @@ impl eframe::App for MonitorApp {
                     }
                     ui.separator();
                 }
+                egui::ComboBox::from_label("Time zone")
+                    .selected_text(self.plotter.time_zone.label())
+                    .show_ui(ui, |ui| {
+                        for time_zone in [TimeZoneSetting::Local, TimeZoneSetting::Utc] {
+                            ui.selectable_value(
+                                &mut self.plotter.time_zone,
+                                time_zone,
+                                time_zone.label(),
+                            );
+                        }
+                    });
+                ui.separator();
+                ui.menu_button("Analysis", |ui| {
+                    ui.checkbox(&mut self.beat_detection.open, "Beat detection");
+                    ui.checkbox(&mut self.beat_overlay.open, "Beat overlay");
+                    ui.checkbox(&mut self.delineation.open, "ECG intervals");
+                    ui.checkbox(
+                        &mut self.heart_rate_comparison.open,
+                        "Heart rate comparison",
+                    );
+                    ui.checkbox(&mut self.heart_rate.open, "Heart rate trend");
+                    ui.checkbox(&mut self.hrv.open, "Heart rate variability");
+                    ui.checkbox(&mut self.af_screening.open, "AF screening");
+                    ui.checkbox(&mut self.respiration.open, "Respiration");
+                    ui.separator();
+                    ui.checkbox(&mut self.activity.open, "Activity");
+                    ui.checkbox(&mut self.data_quality.open, "Data quality");
+                    ui.checkbox(&mut self.derived_channels.open, "Derived channels");
+                    ui.checkbox(&mut self.resampling.open, "Resampling");
+                    ui.checkbox(&mut self.spectrum.open, "Spectrum");
+                    ui.checkbox(&mut self.statistics.open, "Statistics");
+                    ui.separator();
+                    ui.checkbox(&mut self.selections.open, "Selections");
+                });
+                ui.separator();
+                if ui.button("Keyboard shortcuts").clicked() {
+                    self.keyboard_navigation.toggle_help();
+                }
+                ui.separator();
                 global_dark_light_mode_buttons(ui);
             });
         });
@@ -137,6 +271,39 @@ impl eframe::App for MonitorApp {
             }
             AppState::LoadingScreen => self.app_state = AppState::GraphView,
             AppState::GraphView => {
+                self.keyboard_navigation
+                    .handle_input(ctx, &mut self.plotter);
+                self.keyboard_navigation
+                    .show_windows(ctx, &mut self.plotter);
+                self.beat_detection.show(ctx, &mut self.plotter);
+                self.beat_overlay.show(ctx, &mut self.plotter);
+                self.delineation.show(ctx, &mut self.plotter);
+                self.heart_rate_comparison.show(ctx, &mut self.plotter);
+                self.heart_rate.show(ctx, &mut self.plotter);
+                self.hrv.show(ctx, &mut self.plotter);
+                self.af_screening.show(ctx, &mut self.plotter);
+                self.respiration.show(ctx, &mut self.plotter);
+                self.activity.show(ctx, &mut self.plotter);
+                self.data_quality.show(ctx, &mut self.plotter);
+                self.derived_channels.show(ctx, &mut self.plotter);
+                self.resampling.show(ctx, &mut self.plotter);
+                self.spectrum.show(ctx, &mut self.plotter);
+                self.statistics.show(ctx, &mut self.plotter);
+                self.selections.show(ctx, &mut self.plotter);
+                egui::TopBottomPanel::bottom("navigator_panel")
+                    .resizable(true)
+                    .default_height(80.0)
+                    .show(ctx, |ui| {
+                        self.plotter.show_navigator(ui);
+                    });
+                egui::SidePanel::right("readout_panel")
+                    .resizable(true)
+                    .default_width(220.0)
+                    .show(ctx, |ui| {
+                        egui::ScrollArea::vertical().show(ui, |ui| {
+                            show_readout(ui, &mut self.plotter);
+                        });
+                    });
                 egui::CentralPanel::default().show(ctx, |ui| {
                     ui.with_layout(
                         egui::Layout::top_down_justified(egui::Align::Center),
@@ -145,7 +312,7 @@ impl eframe::App for MonitorApp {
 
                             ui.with_layout(egui::Layout::left_to_right(egui::Align::LEFT), |ui| {
                                 ui.label(
-                                    "Double click graph to reset view.\nHold SHIFT to scroll horizontally.\nHold CTRL to zoom in/out.\nDrag with the right mouse button pressed to select a zoom area",
+                                    "Double click graph to reset view.\nHold SHIFT to scroll horizontally.\nHold CTRL to zoom in/out.\nDrag with the right mouse button pressed to select a zoom area.\nDrag with SHIFT pressed to select a range for the analyses, press Z to zoom to it.\nDrag the window in the overview strip below to navigate.\nRight click the graph to add an annotation.\nPress F1 for keyboard shortcuts.",
                                 );
                             });
 
@@ -182,13 +349,13 @@ impl eframe::App for MonitorApp {
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
+pub(crate) fn execute<F: Future<Output = ()> + Send + 'static>(f: F) {
     // this is stupid... use any executor of your choice instead
 
     std::thread::spawn(move || futures::executor::block_on(f));
 }
 
 #[cfg(target_arch = "wasm32")]
-fn execute<F: Future<Output = ()> + 'static>(f: F) {
+pub(crate) fn execute<F: Future<Output = ()> + 'static>(f: F) {
     wasm_bindgen_futures::spawn_local(f);
 }
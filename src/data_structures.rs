@@ -1,15 +1,36 @@
 use core::f64;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use chrono::{Duration, NaiveDate, NaiveDateTime};
+use chrono::{NaiveDate, NaiveDateTime};
 use csv::StringRecord;
 use snafu::prelude::*;
 
-use egui::{Color32, Ui};
-use egui_plot::{Legend, Line, Plot, PlotPoints, PlotUi, Points};
+use egui::{Color32, Ui, Vec2b};
+use egui_plot::{
+    Legend, Line, MarkerShape, Plot, PlotBounds, PlotPoints, PlotTransform, PlotUi, Points,
+    Polygon, VLine,
+};
 
+use crate::analysis::activity::{activity, Activity, Axis};
+use crate::analysis::artefacts::CorrectionLog;
+use crate::analysis::delineation::{Fiducials, Wave};
+use crate::analysis::expression::{DerivedChannel, Expression, ExpressionError};
+use crate::analysis::filters::{FilterDisplay, FilterSettings};
+use crate::analysis::hrv::RrSeries;
+use crate::analysis::qrs_detection::BeatList;
+use crate::analysis::resample::{
+    find_gaps, median_sample_rate, resample, ChannelGaps, ResamplingMethod, UniformSignal,
+    GAP_FACTOR,
+};
+use crate::analysis::respiration::{respiration_rate, surrogates, RespirationEstimate};
+use crate::analysis::signal_quality::{signal_quality, Quality, SignalQuality, QUALITY_WINDOW};
 use crate::grid_helper;
-use grid_helper::ecg_grid_spacer;
+use crate::keyboard_navigation::NavAction;
+use crate::navigator::Navigator;
+use crate::time_format::{TimeFormatter, TimeZoneSetting};
+use crate::views::filter_settings::show_filter_settings;
+use crate::views::spectrogram::SpectrogramPlot;
+use grid_helper::clock_grid_spacer;
 
 #[derive(Clone, Debug)]
 pub enum PlotType {
@@ -23,6 +44,18 @@ pub enum PlotType {
 //     TimeBasedChannel,
 // }
 
+/// What a channel measures, analyses use this to pick suitable channels
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChannelKind {
+    Ecg,
+    HeartRate,
+    RrInterval,
+    Acceleration,
+    /// movement and posture derived from the acceleration
+    Activity,
+    Other,
+}
+
 pub enum Filetype {
     PolarECG,
     PolarACC,
@@ -34,11 +67,65 @@ pub enum Filetype {
 pub trait DrawableChannel {
     fn points_to_draw(&mut self, start_pos: f64, end_pos: f64) -> PlotPoints;
     fn draw(&mut self, plot_ui: &mut PlotUi, start_pos: f64, end_pos: f64);
-    fn show_settings(&mut self);
+    /// edit the settings of the channel, returns true if they changed
+    fn show_settings(&mut self, ui: &mut Ui) -> bool;
+    /// the filter chain of the channel, None if the channel can't be filtered
+    fn filter_settings(&mut self) -> Option<FilterSettings> {
+        None
+    }
+    fn set_filter_settings(&mut self, _settings: FilterSettings) {}
     fn get_name(&mut self) -> String;
     fn get_unit(&mut self) -> String {
         "mV".to_owned()
     }
+    /// name and unit as shown in the legend and the tooltips
+    fn get_label(&mut self) -> String {
+        format!("{} [{}]", self.get_name(), self.get_unit())
+    }
+    /// first and last position on the x axis
+    fn x_range(&mut self) -> Option<(f64, f64)> {
+        let points = self.points_to_draw(f64::NEG_INFINITY, f64::INFINITY);
+        let points = points.points();
+        Some((points.first()?.x, points.last()?.x))
+    }
+    /// true if the x axis is a unix timestamp, false if it starts at 0 with the recording
+    fn has_wall_clock_time(&mut self) -> bool {
+        false
+    }
+    /// linearly interpolated value at `position`, None outside of the recording
+    fn value_at(&mut self, position: f64) -> Option<f64>;
+    fn get_kind(&mut self) -> ChannelKind {
+        ChannelKind::Other
+    }
+    /// the channel with a fixed sample rate, as needed for filtering and detection,
+    /// NaN within dropouts
+    fn uniform_signal(&mut self) -> Option<UniformSignal>;
+    /// steps between samples longer than `max_gap` seconds are drawn as breaks in the line
+    /// and are missing in `uniform_signal`
+    fn set_max_gap(&mut self, _max_gap: Option<f64>) {}
+    /// The channel as drawn resampled to `samples_per_second`, without interpolating over
+    /// gaps longer than `gap_factor` times the median sample interval
+    fn resample(
+        &mut self,
+        samples_per_second: f64,
+        method: ResamplingMethod,
+        gap_factor: f64,
+    ) -> Option<SampleBasedChannel> {
+        let points: Vec<(f64, f64)> = self
+            .points_to_draw(f64::NEG_INFINITY, f64::INFINITY)
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect();
+        let max_gap = gap_factor / median_sample_rate(&points)?;
+        Some(SampleBasedChannel::from_uniform_signal(
+            format!("{} ({} Hz)", self.get_name(), samples_per_second),
+            resample(&points, samples_per_second, method, max_gap),
+            self.get_unit(),
+            self.get_kind(),
+            self.has_wall_clock_time(),
+        ))
+    }
 }
 
 #[derive(Debug, Snafu)]
@@ -49,32 +136,956 @@ pub enum ParserError {
     DataConversionError { data_str: String },
 }
 
+/// The part of the recording an analysis is calculated for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnalysisRange {
+    Recording,
+    Visible,
+    Selection,
+}
+
+impl AnalysisRange {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AnalysisRange::Recording => "Whole recording",
+            AnalysisRange::Visible => "Visible range",
+            AnalysisRange::Selection => "Selection",
+        }
+    }
+}
+
+/// Where an annotation comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnnotationKind {
+    /// added by the user in the plot
+    Marker,
+    /// a selection saved under a name
+    NamedRange,
+    /// an episode found by an analysis, replaced when the analysis is repeated
+    Analysis,
+}
+
+/// What the signal quality of a channel depends on, it is rated again when this changes
+#[derive(Clone, Debug, PartialEq)]
+struct RatedState {
+    filter_settings: Option<FilterSettings>,
+    x_range: Option<(f64, f64)>,
+    /// dropouts are missing in the rated signal
+    gap_factor: f64,
+}
+
+/// A marker on the time axis, e.g. an event the user wants to find again
+#[derive(Clone, Debug)]
+pub struct Annotation {
+    pub position: f64,
+    /// end of an annotated episode, None for a single point in time
+    pub end: Option<f64>,
+    pub label: String,
+    pub kind: AnnotationKind,
+}
+
 // #[derive(Clone, Debug)]
 pub struct ChannelPlotter {
     pub name: String,
     pub channels: Vec<Box<dyn DrawableChannel>>,
+    pub annotations: Vec<Annotation>,
+    /// detected R peaks per ECG channel label
+    pub beats: HashMap<String, BeatList>,
+    /// corrected RR artefacts per ECG or RR interval channel of all recordings, kept across
+    /// sessions, see [rr_corrections_key]
+    pub rr_corrections: HashMap<String, CorrectionLog>,
+    /// filter chains per channel label, applied to channels with these labels when they are added
+    pub filter_settings: HashMap<String, FilterSettings>,
+    /// wave boundaries of the beats per ECG channel label, sorted by R peak
+    pub fiducials: HashMap<String, Vec<Fiducials>>,
+    /// label of the channel whose beats can be edited with the mouse
+    pub beat_editing: Option<String>,
+    /// label of the channel whose wave boundaries can be moved with the mouse
+    pub fiducial_editing: Option<String>,
+    /// labels of the channels which are not drawn
+    pub hidden_channels: HashSet<String>,
+    /// one plot per channel instead of all channels in one plot
+    pub stacked: bool,
+    /// time zone for data with wall-clock time
+    pub time_zone: TimeZoneSetting,
+    /// spectrograms drawn below the channels, by channel label
+    pub spectrograms: HashMap<String, SpectrogramPlot>,
+    /// signal quality of ECG channels by label, with the state of the channel it was rated for,
+    /// None to rate it in the next frame
+    signal_quality: HashMap<String, (Option<RatedState>, SignalQuality)>,
+    /// channels calculated from formulas, recalculated whenever their inputs change
+    pub derived_channels: Vec<DerivedChannel>,
+    /// generation and gap factor the derived channels were calculated for, None to recalculate them
+    derived_generation: Option<(u64, f64)>,
+    /// steps longer than this multiple of the median sample interval are gaps
+    pub gap_factor: f64,
+    /// shade the gaps of the channels in the plots
+    pub show_gaps: bool,
+    /// gaps per channel label
+    gaps: HashMap<String, ChannelGaps>,
+    /// generation and gap factor the gaps were found for
+    gaps_generation: Option<(u64, f64)>,
+    /// range selected with SHIFT + drag in the main plot
+    pub selection: Option<(f64, f64)>,
+    /// position where the current selection drag started
+    selection_start: Option<f64>,
+    /// x range the main plot should switch to in the next frame
+    requested_x_bounds: Option<(f64, f64)>,
+    /// x range shown by the main plot in the last frame
+    visible_x_bounds: Option<(f64, f64)>,
+    /// position of the last right click in the main plot, used by the context menu
+    context_menu_position: Option<f64>,
+    cursor_position: Option<f64>,
+    /// index of the beat which is moved with the mouse
+    dragged_beat: Option<usize>,
+    /// beat index and wave boundary which is moved with the mouse
+    dragged_fiducial: Option<(usize, Wave)>,
+    navigator: Navigator,
+    /// incremented whenever the set of channels changes, so cached overviews can be invalidated
+    generation: u64,
 }
 
 impl ChannelPlotter {
     pub fn new(name: String, channels: Vec<Box<dyn DrawableChannel>>) -> ChannelPlotter {
-        ChannelPlotter { name, channels }
+        ChannelPlotter {
+            name,
+            channels,
+            annotations: vec![],
+            beats: HashMap::new(),
+            rr_corrections: HashMap::new(),
+            filter_settings: HashMap::new(),
+            fiducials: HashMap::new(),
+            beat_editing: None,
+            fiducial_editing: None,
+            hidden_channels: HashSet::new(),
+            stacked: false,
+            time_zone: TimeZoneSetting::Local,
+            spectrograms: HashMap::new(),
+            signal_quality: HashMap::new(),
+            derived_channels: vec![],
+            derived_generation: None,
+            gap_factor: GAP_FACTOR,
+            show_gaps: true,
+            gaps: HashMap::new(),
+            gaps_generation: None,
+            selection: None,
+            selection_start: None,
+            requested_x_bounds: None,
+            visible_x_bounds: None,
+            context_menu_position: None,
+            cursor_position: None,
+            dragged_beat: None,
+            dragged_fiducial: None,
+            navigator: Navigator::default(),
+            generation: 0,
+        }
     }
 
-    pub fn add_channel(&mut self, channel: Box<dyn DrawableChannel>) {
+    pub fn add_channel(&mut self, mut channel: Box<dyn DrawableChannel>) {
+        if let Some(settings) = self.filter_settings.get(&channel.get_label()) {
+            channel.set_filter_settings(settings.clone());
+        }
         self.channels.push(channel);
+        self.generation += 1;
     }
 
-    pub fn plot(&mut self, ui: &mut Ui) {
-        let unit_label = "mV".to_owned();
-        let _unit_labels: HashMap<String, String> = self
+    /// Add a channel, replacing a channel with the same label (e.g. when recalculating derived channels)
+    pub fn add_or_replace_channel(&mut self, mut channel: Box<dyn DrawableChannel>) {
+        let label = channel.get_label();
+        match self
+            .channels
+            .iter_mut()
+            .position(|c| c.get_label() == label)
+        {
+            Some(idx) => {
+                if let Some(settings) = self.filter_settings.get(&label) {
+                    channel.set_filter_settings(settings.clone());
+                }
+                self.channels[idx] = channel;
+                // the data changed, even if the filters and the range are the same
+                if let Some((state, _)) = self.signal_quality.get_mut(&label) {
+                    *state = None;
+                }
+                self.generation += 1;
+            }
+            None => self.add_channel(channel),
+        }
+    }
+
+    /// Add the channel `label` resampled to `samples_per_second`, returns the new label
+    pub fn add_resampled_channel(
+        &mut self,
+        label: &str,
+        samples_per_second: f64,
+        method: ResamplingMethod,
+    ) -> Option<String> {
+        let gap_factor = self.gap_factor;
+        let mut channel =
+            self.channel_by_label(label)?
+                .resample(samples_per_second, method, gap_factor)?;
+        let label = channel.get_label();
+        self.add_or_replace_channel(Box::new(channel));
+        Some(label)
+    }
+
+    /// Add RR interval (ms) and heart rate (bpm) channels calculated from the beats of an ECG channel
+    pub fn add_beat_derived_channels(&mut self, label: &str) -> bool {
+        let Some(rr_intervals) = self.beats.get(label).map(|b| b.rr_intervals()) else {
+            return false;
+        };
+        let Some(channel) = self.channel_by_label(label) else {
+            return false;
+        };
+        let name = channel.get_name();
+        let wall_clock = channel.has_wall_clock_time();
+        let rr: Vec<(f64, f64)> = rr_intervals
+            .iter()
+            .map(|(position, rr)| (*position, rr * 1E3))
+            .collect();
+        let heart_rate: Vec<(f64, f64)> = rr_intervals
+            .iter()
+            .map(|(position, rr)| (*position, 60.0 / rr))
+            .collect();
+        self.add_or_replace_channel(Box::new(TimeBasedChannel::from_positions(
+            format!("RR interval ({})", name),
+            &rr,
+            "ms".to_owned(),
+            ChannelKind::RrInterval,
+            wall_clock,
+        )));
+        self.add_or_replace_channel(Box::new(TimeBasedChannel::from_positions(
+            format!("HR ({})", name),
+            &heart_rate,
+            "bpm".to_owned(),
+            ChannelKind::HeartRate,
+            wall_clock,
+        )));
+        true
+    }
+
+    /// Add the vector magnitude, ENMO, activity counts, cadence, steps and tilt derived from
+    /// the acceleration channels `axes` (X, Y, Z), replacing earlier ones
+    pub fn add_activity_channels(
+        &mut self,
+        axes: [&str; 3],
+        epoch: f64,
+        upright_axis: Axis,
+    ) -> Option<Activity> {
+        let mut signals = vec![];
+        for label in axes {
+            signals.push(self.channel_by_label(label)?.uniform_signal()?);
+        }
+        let channel = self.channel_by_label(axes[0])?;
+        let wall_clock = channel.has_wall_clock_time();
+        let units_per_g = match channel.get_unit().as_str() {
+            "mg" => 1E3,
+            "m/s²" | "m/s^2" => 9.80665,
+            _ => 1.0,
+        };
+        let activity = activity(
+            &signals[0],
+            &signals[1],
+            &signals[2],
+            units_per_g,
+            epoch,
+            upright_axis,
+        );
+        let mut cumulative_steps: Vec<(f64, f64)> = activity
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(n, position)| (*position, (n + 1) as f64))
+            .collect();
+        cumulative_steps.insert(0, (signals[0].start, 0.0));
+        for (name, signal, unit) in [
+            ("Vector magnitude", &activity.magnitude, "g"),
+            ("Tilt", &activity.tilt, "°"),
+        ] {
+            self.add_or_replace_channel(Box::new(SampleBasedChannel::from_uniform_signal(
+                name.to_owned(),
+                signal.clone(),
+                unit.to_owned(),
+                ChannelKind::Activity,
+                wall_clock,
+            )));
+        }
+        for (name, points, unit) in [
+            ("ENMO", &activity.enmo, "mg"),
+            ("Activity counts", &activity.counts, "mg·s"),
+            ("Cadence", &activity.cadence, "steps/min"),
+            ("Steps", &cumulative_steps, "steps"),
+        ] {
+            self.add_or_replace_channel(Box::new(TimeBasedChannel::from_positions(
+                name.to_owned(),
+                points,
+                unit.to_owned(),
+                ChannelKind::Activity,
+                wall_clock,
+            )));
+        }
+        Some(activity)
+    }
+
+    /// Add the respiration rate derived from the beats of the ECG channel `ecg_label`, fused
+    /// with the movement of the accelerometer channel `chest_label` if given. Beats in ranges
+    /// of unusable signal quality are left out. None without beats.
+    pub fn add_respiration_channel(
+        &mut self,
+        ecg_label: &str,
+        chest_label: Option<&str>,
+        window: f64,
+        step: f64,
+    ) -> Option<Vec<RespirationEstimate>> {
+        let low_quality = self.low_quality_ranges(ecg_label);
+        let beats: Vec<f64> = self
+            .beats
+            .get(ecg_label)?
+            .positions
+            .iter()
+            .copied()
+            .filter(|p| !low_quality.iter().any(|(s, e)| p >= s && p < e))
+            .collect();
+        let chest = match chest_label {
+            Some(label) => Some(self.channel_by_label(label)?.uniform_signal()?),
+            None => None,
+        };
+        let channel = self.channel_by_label(ecg_label)?;
+        let ecg = channel.uniform_signal()?;
+        let wall_clock = channel.has_wall_clock_time();
+        let name = channel.get_name();
+        let surrogates = surrogates(&ecg, &beats, chest.as_ref());
+        let estimates = respiration_rate(&surrogates, &beats, window, step);
+        let points: Vec<(f64, f64)> = estimates
+            .iter()
+            .filter_map(|e| Some((e.position, e.fused?)))
+            .collect();
+        self.add_or_replace_channel(Box::new(TimeBasedChannel::from_positions(
+            format!("Respiration rate ({name})"),
+            &points,
+            "breaths/min".to_owned(),
+            ChannelKind::Other,
+            wall_clock,
+        )));
+        Some(estimates)
+    }
+
+    /// Let the user edit the settings of a channel, e.g. its filters and spectrogram
+    pub fn show_channel_settings(&mut self, ui: &mut Ui, label: &str) {
+        let Some(channel) = self.channel_by_label(label) else {
+            return;
+        };
+        if channel.show_settings(ui) {
+            if let Some(settings) = channel.filter_settings() {
+                self.filter_settings.insert(label.to_owned(), settings);
+            }
+            // the overview shows the filtered signal
+            self.generation += 1;
+        }
+        ui.separator();
+        let mut show_spectrogram = self.spectrograms.contains_key(label);
+        if ui
+            .checkbox(&mut show_spectrogram, "Spectrogram below the channel")
+            .changed()
+        {
+            if show_spectrogram {
+                self.spectrograms
+                    .insert(label.to_owned(), SpectrogramPlot::default());
+            } else {
+                self.spectrograms.remove(label);
+            }
+        }
+        if let Some(spectrogram) = self.spectrograms.get_mut(label) {
+            spectrogram.show_settings(ui);
+        }
+        if self.channel_by_label(label).map(|c| c.get_kind()) != Some(ChannelKind::Ecg) {
+            return;
+        }
+        ui.separator();
+        let mut show_quality = self.signal_quality.contains_key(label);
+        if ui
+            .checkbox(&mut show_quality, "Signal quality band")
+            .on_hover_text(
+                "Rate the quality in 10 s windows, analyses leave out the unusable windows",
+            )
+            .changed()
+        {
+            if show_quality {
+                // rated in the next frame
+                self.signal_quality
+                    .insert(label.to_owned(), (None, SignalQuality::default()));
+            } else {
+                self.signal_quality.remove(label);
+            }
+        }
+        if let Some((_, quality)) = self.signal_quality.get(label) {
+            for (quality, share) in quality.shares() {
+                ui.colored_label(
+                    quality_color(quality),
+                    format!("{}: {:.0} %", quality.label(), share * 100.0),
+                );
+            }
+        }
+    }
+
+    /// The signal quality of an ECG channel, if its quality band is shown
+    pub fn signal_quality(&self, label: &str) -> Option<&SignalQuality> {
+        self.signal_quality.get(label).map(|(_, quality)| quality)
+    }
+
+    /// Ranges of a channel which analyses should leave out because of unusable signal quality
+    pub fn low_quality_ranges(&self, label: &str) -> Vec<(f64, f64)> {
+        self.signal_quality(label)
+            .map(|quality| quality.unusable_ranges())
+            .unwrap_or_default()
+    }
+
+    /// Rate the signal quality of the channels whose data changed since they were rated
+    fn update_signal_quality(&mut self) {
+        let labels: Vec<String> = self.signal_quality.keys().cloned().collect();
+        let gap_factor = self.gap_factor;
+        for label in labels {
+            let Some(channel) = self.channel_by_label(&label) else {
+                continue;
+            };
+            let state = RatedState {
+                filter_settings: channel.filter_settings(),
+                x_range: channel.x_range(),
+                gap_factor,
+            };
+            if self
+                .signal_quality
+                .get(&label)
+                .is_some_and(|(rated, _)| rated.as_ref() == Some(&state))
+            {
+                continue;
+            }
+            let quality = self
+                .channel_by_label(&label)
+                .and_then(|channel| channel.uniform_signal())
+                .map(|signal| signal_quality(&signal, QUALITY_WINDOW))
+                .unwrap_or_default();
+            self.signal_quality.insert(label, (Some(state), quality));
+        }
+    }
+
+    /// Add or change the derived channel with the name of `channel`, fails if its formula is invalid
+    pub fn set_derived_channel(&mut self, channel: DerivedChannel) -> Result<(), ExpressionError> {
+        Expression::parse(&channel.formula)?;
+        match self
+            .derived_channels
+            .iter_mut()
+            .find(|c| c.name == channel.name)
+        {
+            Some(existing) => {
+                // the channel with the old unit has a different label
+                let old_label = format!("{} [{}]", existing.name, existing.unit);
+                self.channels.retain_mut(|c| c.get_label() != old_label);
+                *existing = channel;
+            }
+            None => self.derived_channels.push(channel),
+        }
+        self.derived_generation = None;
+        Ok(())
+    }
+
+    /// Remove the derived channel `name` and its formula
+    pub fn remove_derived_channel(&mut self, name: &str) {
+        let Some(idx) = self.derived_channels.iter().position(|c| c.name == name) else {
+            return;
+        };
+        let channel = self.derived_channels.remove(idx);
+        let label = format!("{} [{}]", channel.name, channel.unit);
+        self.channels.retain_mut(|c| c.get_label() != label);
+        self.generation += 1;
+    }
+
+    /// Recalculate the derived channels if any channel changed since they were calculated,
+    /// in the order they were defined, so formulas can use the derived channels before them
+    fn update_derived_channels(&mut self) {
+        if self.derived_generation == Some((self.generation, self.gap_factor)) {
+            return;
+        }
+        let mut derived_channels = std::mem::take(&mut self.derived_channels);
+        for derived in derived_channels.iter_mut() {
+            derived.error = self.calculate_derived_channel(derived).err();
+        }
+        self.derived_channels = derived_channels;
+        self.derived_generation = Some((self.generation, self.gap_factor));
+    }
+
+    fn calculate_derived_channel(&mut self, derived: &DerivedChannel) -> Result<(), String> {
+        let expression = Expression::parse(&derived.formula).map_err(|e| e.to_string())?;
+        let own_label = format!("{} [{}]", derived.name, derived.unit);
+        let mut inputs = HashMap::new();
+        let mut wall_clock = false;
+        let gap_factor = self.gap_factor;
+        for name in expression.channels() {
+            let Some(label) = self.resolve_channel_name(&name) else {
+                return Err(ExpressionError::UnknownChannel { name }.to_string());
+            };
+            if label == own_label {
+                return Err(ExpressionError::SelfReference.to_string());
+            }
+            let Some(channel) = self.channel_by_label(&label) else {
+                continue;
+            };
+            wall_clock |= channel.has_wall_clock_time();
+            // dropouts stay NaN instead of being interpolated over
+            let points: Vec<(f64, f64)> = channel
+                .points_to_draw(f64::NEG_INFINITY, f64::INFINITY)
+                .points()
+                .iter()
+                .map(|p| (p.x, p.y))
+                .collect();
+            if let Some(samples_per_second) = median_sample_rate(&points) {
+                let max_gap = gap_factor / samples_per_second;
+                let signal = resample(
+                    &points,
+                    samples_per_second,
+                    ResamplingMethod::Linear,
+                    max_gap,
+                );
+                inputs.insert(name, signal);
+            }
+        }
+        let signal = expression.evaluate(&inputs).map_err(|e| e.to_string())?;
+        self.add_or_replace_channel(Box::new(SampleBasedChannel::from_uniform_signal(
+            derived.name.clone(),
+            signal,
+            derived.unit.clone(),
+            ChannelKind::Other,
+            wall_clock,
+        )));
+        Ok(())
+    }
+
+    /// Label of the channel a formula refers to by `name`: its label, its name,
+    /// or its name ignoring case
+    fn resolve_channel_name(&mut self, name: &str) -> Option<String> {
+        let mut names: Vec<(String, String)> = self
+            .channels
+            .iter_mut()
+            .map(|c| (c.get_label(), c.get_name()))
+            .collect();
+        names.sort_by_key(|(label, channel_name)| {
+            if label == name {
+                0
+            } else if channel_name == name {
+                1
+            } else {
+                2
+            }
+        });
+        names
+            .into_iter()
+            .find(|(label, channel_name)| {
+                label == name || channel_name.to_lowercase() == name.to_lowercase()
+            })
+            .map(|(label, _)| label)
+    }
+
+    /// The gaps of a channel, found again when the data or the gap factor changes
+    pub fn channel_gaps(&self, label: &str) -> Option<&ChannelGaps> {
+        self.gaps.get(label)
+    }
+
+    /// Find the gaps of all channels and let the channels break their lines there
+    fn update_gaps(&mut self) {
+        if self.gaps_generation == Some((self.generation, self.gap_factor)) {
+            return;
+        }
+        self.gaps.clear();
+        for channel in self.channels.iter_mut() {
+            let points: Vec<(f64, f64)> = channel
+                .points_to_draw(f64::NEG_INFINITY, f64::INFINITY)
+                .points()
+                .iter()
+                .map(|p| (p.x, p.y))
+                .collect();
+            let gaps = find_gaps(&points, self.gap_factor);
+            channel.set_max_gap(gaps.samples_per_second.map(|rate| self.gap_factor / rate));
+            self.gaps.insert(channel.get_label(), gaps);
+        }
+        self.gaps_generation = Some((self.generation, self.gap_factor));
+    }
+
+    /// Remove all channels and annotations
+    pub fn clear(&mut self) {
+        self.channels.clear();
+        self.annotations.clear();
+        self.beats.clear();
+        self.fiducials.clear();
+        self.beat_editing = None;
+        self.fiducial_editing = None;
+        self.hidden_channels.clear();
+        self.spectrograms.clear();
+        self.signal_quality.clear();
+        self.selection = None;
+        self.visible_x_bounds = None;
+        self.cursor_position = None;
+        self.generation += 1;
+    }
+
+    /// Let the main plot show the range from `start` to `end` in the next frame
+    pub fn set_x_bounds(&mut self, start: f64, end: f64) {
+        if end > start {
+            self.requested_x_bounds = Some((start, end));
+        }
+    }
+
+    /// The x range shown by the main plot in the last frame
+    pub fn visible_x_bounds(&self) -> Option<(f64, f64)> {
+        self.requested_x_bounds.or(self.visible_x_bounds)
+    }
+
+    pub fn add_annotation(&mut self, position: f64, label: String) {
+        self.insert_annotation(Annotation {
+            position,
+            end: None,
+            label,
+            kind: AnnotationKind::Marker,
+        });
+    }
+
+    /// Annotate the episode from `start` to `end`
+    pub fn add_episode(&mut self, start: f64, end: f64, label: String, kind: AnnotationKind) {
+        self.insert_annotation(Annotation {
+            position: start,
+            end: Some(end),
+            label,
+            kind,
+        });
+    }
+
+    fn insert_annotation(&mut self, annotation: Annotation) {
+        self.annotations.push(annotation);
+        self.annotations
+            .sort_by(|a, b| a.position.total_cmp(&b.position));
+    }
+
+    /// The channel with the given label
+    pub fn channel_by_label(&mut self, label: &str) -> Option<&mut dyn DrawableChannel> {
+        let idx = self
             .channels
             .iter_mut()
-            .map(|c| {
-                let channel = c.as_mut();
-                (channel.get_name(), channel.get_unit())
+            .position(|c| c.get_label() == label)?;
+        Some(self.channels[idx].as_mut())
+    }
+
+    /// Labels of all channels of the given kind
+    pub fn channel_labels(&mut self, kind: ChannelKind) -> Vec<String> {
+        self.channels
+            .iter_mut()
+            .filter_map(|c| (c.get_kind() == kind).then(|| c.get_label()))
+            .collect()
+    }
+
+    /// Labels of the channels an RR series can be taken from:
+    /// ECG channels with detected beats and RR interval channels
+    pub fn rr_sources(&mut self) -> Vec<String> {
+        let mut sources: Vec<String> = self
+            .channel_labels(ChannelKind::Ecg)
+            .into_iter()
+            .filter(|label| self.beats.contains_key(label))
+            .collect();
+        sources.extend(self.channel_labels(ChannelKind::RrInterval));
+        sources
+    }
+
+    /// RR intervals in ms from the beats of an ECG channel or from an RR interval channel
+    pub fn rr_series(&mut self, label: &str) -> Option<RrSeries> {
+        if let Some(beats) = self.beats.get(label) {
+            let gaps = self
+                .channel_gaps(label)
+                .map(|gaps| gaps.gaps.as_slice())
+                .unwrap_or_default();
+            return Some(RrSeries::from_beats(beats, gaps));
+        }
+        let channel = self.channel_by_label(label)?;
+        let scale = match channel.get_unit().as_str() {
+            "s" => 1E3,
+            _ => 1.0,
+        };
+        let points: Vec<(f64, f64)> = channel
+            .points_to_draw(f64::NEG_INFINITY, f64::INFINITY)
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect();
+        Some(RrSeries::from_points(&points, scale))
+    }
+
+    /// The RR series of a channel with its artefact corrections applied
+    pub fn corrected_rr_series(&mut self, label: &str) -> Option<RrSeries> {
+        let series = self.rr_series(label)?;
+        Some(match self.rr_correction_log(label) {
+            Some(log) => log.apply(&series),
+            None => series,
+        })
+    }
+
+    /// The artefact corrections of the RR series of the channel with `label`
+    pub fn rr_correction_log(&mut self, label: &str) -> Option<&CorrectionLog> {
+        let key = rr_corrections_key(self.channel_by_label(label)?)?;
+        self.rr_corrections.get(&key)
+    }
+
+    /// Replace the artefact corrections of the channel with `label`, None to undo them
+    pub fn set_rr_correction_log(&mut self, label: &str, log: Option<CorrectionLog>) {
+        let Some(key) = self.channel_by_label(label).and_then(rr_corrections_key) else {
+            return;
+        };
+        match log {
+            Some(log) => self.rr_corrections.insert(key, log),
+            None => self.rr_corrections.remove(&key),
+        };
+    }
+
+    /// Start and end of `range` on the x axis, None if there is no such range
+    pub fn analysis_range(&mut self, range: AnalysisRange) -> Option<(f64, f64)> {
+        match range {
+            AnalysisRange::Recording => self.recording_x_range(),
+            AnalysisRange::Visible => self.visible_x_bounds(),
+            AnalysisRange::Selection => self.selection,
+        }
+    }
+
+    /// Range on the x axis covered by all channels
+    pub fn recording_x_range(&mut self) -> Option<(f64, f64)> {
+        self.channels
+            .iter_mut()
+            .filter_map(|c| c.x_range())
+            .reduce(|(start_a, end_a), (start_b, end_b)| (start_a.min(start_b), end_a.max(end_b)))
+    }
+
+    pub fn has_wall_clock_time(&mut self) -> bool {
+        self.channels.iter_mut().any(|c| c.has_wall_clock_time())
+    }
+
+    /// Formatter for the x axis of the loaded data
+    pub fn time_formatter(&mut self) -> TimeFormatter {
+        TimeFormatter::new(self.has_wall_clock_time(), self.time_zone)
+    }
+
+    /// Move the visible window so it is centered on `position`, keeping its width
+    pub fn center_on(&mut self, position: f64) {
+        if let Some((start, end)) = self.visible_x_bounds() {
+            let half_width = (end - start) / 2.0;
+            self.set_x_bounds(position - half_width, position + half_width);
+        }
+    }
+
+    /// Let the main plot show the selection with a small margin
+    pub fn zoom_to_selection(&mut self) {
+        if let Some((start, end)) = self.selection {
+            let margin = 0.05 * (end - start);
+            self.set_x_bounds(start - margin, end + margin);
+        }
+    }
+
+    /// Keep the selection as a named range, false without a selection
+    pub fn save_selection(&mut self, name: String) -> bool {
+        let Some((start, end)) = self.selection else {
+            return false;
+        };
+        self.add_episode(start, end, name, AnnotationKind::NamedRange);
+        true
+    }
+
+    /// Name for the next saved selection
+    pub fn next_selection_name(&self) -> String {
+        let n = self
+            .annotations
+            .iter()
+            .filter(|a| a.kind == AnnotationKind::NamedRange)
+            .count();
+        format!("Range {}", n + 1)
+    }
+
+    /// Sorted positions of the beats of all channels
+    fn all_beats(&self) -> Vec<f64> {
+        let mut positions: Vec<f64> = self
+            .beats
+            .values()
+            .flat_map(|b| b.positions.iter().copied())
+            .collect();
+        positions.sort_by(|a, b| a.total_cmp(b));
+        positions
+    }
+
+    /// Apply a keyboard navigation action to the visible window
+    pub fn navigate(&mut self, action: NavAction) {
+        let (Some((start, end)), Some((first, last))) =
+            (self.visible_x_bounds(), self.recording_x_range())
+        else {
+            return;
+        };
+        let width = end - start;
+        let center = start + width / 2.0;
+        match action {
+            NavAction::PageLeft => self.set_x_bounds(start - width, start),
+            NavAction::PageRight => self.set_x_bounds(end, end + width),
+            NavAction::StepLeft => self.set_x_bounds(start - 1.0, end - 1.0),
+            NavAction::StepRight => self.set_x_bounds(start + 1.0, end + 1.0),
+            NavAction::BigStepLeft => self.set_x_bounds(start - 10.0, end - 10.0),
+            NavAction::BigStepRight => self.set_x_bounds(start + 10.0, end + 10.0),
+            NavAction::JumpToStart => self.set_x_bounds(first, first + width),
+            NavAction::JumpToEnd => self.set_x_bounds(last - width, last),
+            NavAction::ZoomTo(ms) => {
+                let half_width = ms as f64 / 2E3;
+                self.set_x_bounds(center - half_width, center + half_width);
+            }
+            NavAction::NextAnnotation => {
+                let positions: Vec<f64> = self.annotations.iter().map(|a| a.position).collect();
+                if let Some(position) = next_position(&positions, center) {
+                    self.center_on(position);
+                }
+            }
+            NavAction::PreviousAnnotation => {
+                let positions: Vec<f64> = self.annotations.iter().map(|a| a.position).collect();
+                if let Some(position) = previous_position(&positions, center) {
+                    self.center_on(position);
+                }
+            }
+            NavAction::NextBeat => {
+                if let Some(position) = next_position(&self.all_beats(), center) {
+                    self.center_on(position);
+                }
+            }
+            NavAction::PreviousBeat => {
+                if let Some(position) = previous_position(&self.all_beats(), center) {
+                    self.center_on(position);
+                }
+            }
+            NavAction::ZoomToSelection => self.zoom_to_selection(),
+            NavAction::GoToTime | NavAction::ToggleHelp => {}
+        }
+    }
+
+    /// Draw the overview strip for the whole recording
+    pub fn show_navigator(&mut self, ui: &mut Ui) {
+        let visible_x_bounds = self.visible_x_bounds();
+        let time_formatter = self.time_formatter();
+        if let Some((start, end)) = self.navigator.show(
+            ui,
+            time_formatter,
+            &mut self.channels,
+            &self.annotations,
+            visible_x_bounds,
+            self.generation,
+        ) {
+            self.set_x_bounds(start, end);
+        }
+    }
+
+    /// Draw all visible channels, either in one plot or stacked with one plot per channel
+    pub fn plot(&mut self, ui: &mut Ui) {
+        self.update_derived_channels();
+        self.update_gaps();
+        self.update_signal_quality();
+        let requested_x_bounds = self.requested_x_bounds.take();
+        let visible_channels: Vec<usize> = (0..self.channels.len())
+            .filter(|idx| {
+                let label = self.channels[*idx].get_label();
+                !self.hidden_channels.contains(&label)
+            })
+            .collect();
+        let spectrogram_channels: Vec<usize> = visible_channels
+            .iter()
+            .copied()
+            .filter(|idx| {
+                let label = self.channels[*idx].get_label();
+                self.spectrograms.contains_key(&label)
+            })
+            .collect();
+        let stacked = self.stacked && visible_channels.len() > 1;
+        let spacing = ui.spacing().item_spacing.y;
+        let n_plots = if stacked { visible_channels.len() } else { 1 } + spectrogram_channels.len();
+        let height =
+            ((ui.available_height() - spacing * (n_plots as f32 - 1.0)) / n_plots as f32).max(60.0);
+        if stacked {
+            for (n, idx) in visible_channels.iter().enumerate() {
+                let has_spectrogram = spectrogram_channels.contains(idx);
+                let is_last = n + 1 == visible_channels.len();
+                self.show_plot(
+                    ui,
+                    format!("{} {}", self.name, idx),
+                    &[*idx],
+                    Some(height),
+                    requested_x_bounds,
+                    is_last && !has_spectrogram,
+                );
+                if has_spectrogram {
+                    self.show_spectrogram(ui, *idx, height, is_last);
+                }
+            }
+        } else {
+            self.show_plot(
+                ui,
+                self.name.to_string(),
+                &visible_channels,
+                (n_plots > 1).then_some(height),
+                requested_x_bounds,
+                spectrogram_channels.is_empty(),
+            );
+            for (n, idx) in spectrogram_channels.iter().enumerate() {
+                self.show_spectrogram(ui, *idx, height, n + 1 == spectrogram_channels.len());
+            }
+        }
+    }
+
+    /// Draw the spectrogram of the channel at `idx` in a plot linked to the channel plots
+    fn show_spectrogram(&mut self, ui: &mut Ui, idx: usize, height: f32, show_x_axis: bool) {
+        let time_formatter = self.time_formatter();
+        let utc_offset = self
+            .visible_x_bounds
+            .map(|(start, end)| time_formatter.utc_offset((start + end) / 2.0))
+            .unwrap_or(0.0);
+        let channel = self.channels[idx].as_mut();
+        let Some(spectrogram) = self.spectrograms.get_mut(&channel.get_label()) else {
+            return;
+        };
+        let hovered = spectrogram.show(
+            ui,
+            format!("{} spectrogram {}", self.name, idx),
+            channel,
+            self.generation,
+            height,
+            time_formatter,
+            utc_offset,
+            show_x_axis,
+        );
+        if hovered.is_some() {
+            self.cursor_position = hovered;
+        }
+    }
+
+    fn show_plot(
+        &mut self,
+        ui: &mut Ui,
+        id: String,
+        channel_indices: &[usize],
+        height: Option<f32>,
+        requested_x_bounds: Option<(f64, f64)>,
+        show_x_axis: bool,
+    ) {
+        let time_formatter = self.time_formatter();
+        let utc_offset = self
+            .visible_x_bounds
+            .map(|(start, end)| time_formatter.utc_offset((start + end) / 2.0))
+            .unwrap_or(0.0);
+        let unit_labels: HashMap<String, String> = channel_indices
+            .iter()
+            .map(|idx| {
+                let channel = self.channels[*idx].as_mut();
+                (channel.get_label(), channel.get_unit())
             })
             .collect();
-        Plot::new(self.name.to_string())
+        // all units of the channels sharing the y axis
+        let mut y_units: Vec<String> = unit_labels.values().cloned().collect();
+        y_units.sort();
+        y_units.dedup();
+        let mut plot = Plot::new(id)
             // .view_aspect(5.0)
             // .data_aspect(1.0)
             // .auto_bounds_x()
@@ -89,21 +1100,16 @@ impl ChannelPlotter {
             // )
             // .center_y_axis(true)
             .label_formatter(move |name, value| {
-                let time_pos = Duration::nanoseconds((value.x * 1E9) as i64);
-                let hours = (time_pos.num_seconds() / 60) / 60;
-                let minutes = (time_pos.num_seconds() / 60) % 60;
-                let seconds = time_pos.num_seconds() % 60;
                 if !name.is_empty() {
                     format!(
-                        "{}\n({:.2}:{:.2}) {}\n{:02}:{:02}:{:02}.{:.3}",
+                        "{}\n{:.3} {}\n{}",
                         name,
-                        value.x,
                         value.y,
-                        unit_label,
-                        hours,
-                        minutes,
-                        seconds,
-                        time_pos.num_milliseconds() % 1000
+                        unit_labels
+                            .get(name)
+                            .map(|u| u.as_str())
+                            .unwrap_or_default(),
+                        time_formatter.format_position(value.x)
                     )
                 } else {
                     "".to_owned()
@@ -112,20 +1118,506 @@ impl ChannelPlotter {
             })
             .legend(Legend::default().position(egui_plot::Corner::LeftBottom))
             .link_axis("ecg", true, false)
-            .x_grid_spacer(ecg_grid_spacer)
-            .x_axis_label("Time [s]".to_owned())
-            .y_axis_label("mV".to_owned()) // TODO: respect the individual's channels units
-            // .clamp_grid(true)
-            .show(ui, |plot_ui| {
-                self.channels.iter_mut().for_each(|channel| {
-                    channel
-                        .as_mut()
-                        .draw(plot_ui, f64::NEG_INFINITY, f64::INFINITY);
-                });
+            .link_cursor("ecg", true, false)
+            .x_grid_spacer(clock_grid_spacer(utc_offset))
+            .x_axis_formatter(move |value, _max_chars, range| {
+                time_formatter.format_tick(value, range)
+            })
+            .show_axes([show_x_axis, true])
+            .y_axis_label(y_units.join(" | "));
+        if show_x_axis {
+            plot = plot.x_axis_label(time_formatter.axis_label());
+        }
+        if let Some(height) = height {
+            plot = plot.height(height);
+        }
+        let edited_channel = channel_indices.iter().copied().find(|idx| {
+            let label = self.channels[*idx].get_label();
+            self.beat_editing.as_ref() == Some(&label)
+        });
+        let fiducial_channel = channel_indices.iter().copied().find(|idx| {
+            let label = self.channels[*idx].get_label();
+            self.fiducial_editing.as_ref() == Some(&label)
+        });
+        let selecting = ui.input(|i| i.modifiers.shift);
+        if edited_channel.is_some() || fiducial_channel.is_some() || selecting {
+            // dragging moves beats or wave boundaries or selects a range instead of moving the plot
+            plot = plot.allow_drag(false);
+        }
+        // .clamp_grid(true)
+        let response = plot.show(ui, |plot_ui| {
+            let (start_pos, end_pos) = if let Some((start, end)) = requested_x_bounds {
+                let bounds = plot_ui.plot_bounds();
+                plot_ui.set_plot_bounds(PlotBounds::from_min_max(
+                    [start, bounds.min()[1]],
+                    [end, bounds.max()[1]],
+                ));
+                // keep fitting the y axis to the data
+                plot_ui.set_auto_bounds(Vec2b::new(false, true));
+                (start, end)
+            } else if plot_ui.auto_bounds().x {
+                // the plot needs all points to calculate the automatic bounds
+                (f64::NEG_INFINITY, f64::INFINITY)
+            } else {
+                let bounds = plot_ui.plot_bounds();
+                (bounds.min()[0], bounds.max()[0])
+            };
+            // draw one screen width to each side, so panning doesn't show empty areas
+            let width = end_pos - start_pos;
+            channel_indices.iter().for_each(|idx| {
+                self.channels[*idx]
+                    .as_mut()
+                    .draw(plot_ui, start_pos - width, end_pos + width);
+            });
+            channel_indices.iter().for_each(|idx| {
+                let channel = self.channels[*idx].as_mut();
+                let label = channel.get_label();
+                if let Some(beats) = self.beats.get(&label) {
+                    draw_beats(plot_ui, channel, beats, start_pos - width, end_pos + width);
+                }
+                let log = rr_corrections_key(channel).and_then(|key| self.rr_corrections.get(&key));
+                if let Some(log) = log {
+                    draw_artefacts(plot_ui, channel, log, start_pos - width, end_pos + width);
+                }
+                if let Some(fiducials) = self.fiducials.get(&label) {
+                    draw_fiducials(
+                        plot_ui,
+                        channel,
+                        fiducials,
+                        start_pos - width,
+                        end_pos + width,
+                    );
+                }
             });
+            if selecting || self.selection_start.is_some() {
+                self.select_range(plot_ui);
+            } else if let Some(idx) = edited_channel {
+                self.edit_beats(plot_ui, idx);
+            } else if let Some(idx) = fiducial_channel {
+                self.edit_fiducials(plot_ui, idx);
+            }
+            if let Some((start, end)) = self.selection {
+                let bounds = plot_ui.plot_bounds();
+                let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+                plot_ui.polygon(
+                    Polygon::new(PlotPoints::new(vec![
+                        [start, bottom],
+                        [end, bottom],
+                        [end, top],
+                        [start, top],
+                    ]))
+                    .fill_color(Color32::from_rgba_unmultiplied(100, 150, 250, 30))
+                    .stroke(egui::Stroke::NONE),
+                );
+            }
+            self.annotations.iter().for_each(|annotation| {
+                if let Some(end) = annotation.end {
+                    let bounds = plot_ui.plot_bounds();
+                    let (bottom, top) = (bounds.min()[1], bounds.max()[1]);
+                    plot_ui.polygon(
+                        Polygon::new(PlotPoints::new(vec![
+                            [annotation.position, bottom],
+                            [end, bottom],
+                            [end, top],
+                            [annotation.position, top],
+                        ]))
+                        .fill_color(Color32::from_rgba_unmultiplied(255, 200, 0, 25))
+                        .stroke(egui::Stroke::NONE)
+                        .name(annotation.label.to_owned()),
+                    );
+                }
+                plot_ui.vline(
+                    VLine::new(annotation.position)
+                        .color(Color32::GOLD)
+                        .style(egui_plot::LineStyle::dashed_loose())
+                        .name(annotation.label.to_owned()),
+                );
+            });
+            if plot_ui.response().hovered() {
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    self.cursor_position = Some(pointer.x);
+                }
+            }
+            if plot_ui.response().secondary_clicked() {
+                self.context_menu_position = plot_ui.pointer_coordinate().map(|p| p.x);
+            }
+        });
+        let bounds = response.transform.bounds();
+        self.visible_x_bounds = Some((bounds.min()[0], bounds.max()[0]));
+        let quality_channels: Vec<String> = channel_indices
+            .iter()
+            .map(|idx| self.channels[*idx].get_label())
+            .filter(|label| self.signal_quality.contains_key(label))
+            .collect();
+        for (n, label) in quality_channels.iter().enumerate() {
+            if let Some((_, quality)) = self.signal_quality.get(label) {
+                draw_quality_band(ui, &response.transform, quality, n);
+            }
+        }
+        if self.show_gaps {
+            for idx in channel_indices {
+                let label = self.channels[*idx].get_label();
+                if let Some(gaps) = self.gaps.get(&label) {
+                    draw_gaps(ui, &response.transform, &gaps.gaps);
+                }
+            }
+        }
+
+        response.response.context_menu(|ui| {
+            if let Some(position) = self.context_menu_position {
+                if ui.button("Add annotation here").clicked() {
+                    let label = format!("Marker {}", self.annotations.len() + 1);
+                    self.add_annotation(position, label);
+                    ui.close_menu();
+                }
+            }
+            if let Some(position) = self.context_menu_position {
+                let episode = self
+                    .annotations
+                    .iter()
+                    .find(|a| {
+                        a.end
+                            .is_some_and(|end| a.position <= position && position <= end)
+                    })
+                    .cloned();
+                if let Some(Annotation {
+                    position: start,
+                    end: Some(end),
+                    label,
+                    ..
+                }) = episode
+                {
+                    if ui.button(format!("Select \"{label}\"")).clicked() {
+                        self.selection = Some((start, end));
+                        ui.close_menu();
+                    }
+                }
+            }
+            if self.selection.is_some() {
+                if ui.button("Zoom to selection").clicked() {
+                    self.zoom_to_selection();
+                    ui.close_menu();
+                }
+                if ui.button("Save selection as named range").clicked() {
+                    let name = self.next_selection_name();
+                    self.save_selection(name);
+                    ui.close_menu();
+                }
+                if ui.button("Clear selection").clicked() {
+                    self.selection = None;
+                    ui.close_menu();
+                }
+            }
+        });
+    }
+
+    /// Select a range on the x axis by dragging with SHIFT pressed
+    fn select_range(&mut self, plot_ui: &mut PlotUi) {
+        let response = plot_ui.response().clone();
+        let Some(pointer) = plot_ui
+            .ctx()
+            .input(|i| i.pointer.interact_pos())
+            .map(|pos| plot_ui.plot_from_screen(pos).x)
+        else {
+            return;
+        };
+        if response.drag_started() {
+            self.selection_start = Some(pointer);
+        }
+        if response.dragged() {
+            if let Some(start) = self.selection_start {
+                self.selection =
+                    (pointer != start).then(|| (start.min(pointer), start.max(pointer)));
+            }
+        }
+        if response.drag_released() {
+            self.selection_start = None;
+        }
+    }
+
+    /// Add (click), delete (CTRL + click) and move (drag) beats of a channel with the mouse
+    fn edit_beats(&mut self, plot_ui: &mut PlotUi, channel_idx: usize) {
+        let response = plot_ui.response().clone();
+        let Some(pointer) = plot_ui
+            .ctx()
+            .input(|i| i.pointer.interact_pos())
+            .map(|pos| plot_ui.plot_from_screen(pos).x)
+        else {
+            return;
+        };
+        let tolerance = 8.0 * plot_ui.transform().dvalue_dpos()[0];
+        let channel = self.channels[channel_idx].as_mut();
+        let beats = self.beats.entry(channel.get_label()).or_default();
+
+        if response.clicked() {
+            if plot_ui.ctx().input(|i| i.modifiers.command) {
+                if let Some(idx) = beats.nearest(pointer, tolerance) {
+                    beats.remove(idx);
+                }
+            } else {
+                beats.add(snap_to_peak(channel, pointer));
+            }
+        }
+        if response.drag_started() {
+            self.dragged_beat = beats.nearest(pointer, tolerance);
+        }
+        if response.dragged() {
+            if let Some(idx) = self.dragged_beat {
+                self.dragged_beat = Some(beats.move_beat(idx, pointer));
+            }
+        }
+        if response.drag_released() {
+            if let Some(idx) = self.dragged_beat.take() {
+                beats.move_beat(idx, snap_to_peak(channel, pointer));
+            }
+        }
+    }
+
+    /// Move the wave boundaries of a channel by dragging them
+    fn edit_fiducials(&mut self, plot_ui: &mut PlotUi, channel_idx: usize) {
+        let response = plot_ui.response().clone();
+        let Some(pointer) = plot_ui
+            .ctx()
+            .input(|i| i.pointer.interact_pos())
+            .map(|pos| plot_ui.plot_from_screen(pos).x)
+        else {
+            return;
+        };
+        let tolerance = 8.0 * plot_ui.transform().dvalue_dpos()[0];
+        let label = self.channels[channel_idx].get_label();
+        let Some(fiducials) = self.fiducials.get_mut(&label) else {
+            return;
+        };
+
+        if response.drag_started() {
+            // the wave boundary closest to the pointer, of the beats around it
+            let idx = fiducials.partition_point(|f| f.r_peak < pointer);
+            self.dragged_fiducial = (idx.saturating_sub(1)..(idx + 1).min(fiducials.len()))
+                .flat_map(|beat| Wave::ALL.map(|wave| (beat, wave)))
+                .filter_map(|(beat, wave)| {
+                    let position = fiducials[beat].get(wave)?;
+                    Some(((beat, wave), (position - pointer).abs()))
+                })
+                .filter(|(_, distance)| *distance < tolerance)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(dragged, _)| dragged);
+        }
+        if response.dragged() {
+            if let Some((beat, wave)) = self.dragged_fiducial {
+                fiducials[beat].set(wave, pointer);
+            }
+        }
+        if response.drag_released() {
+            self.dragged_fiducial = None;
+        }
+    }
+
+    /// Position of the crosshair, i.e. where the pointer was last seen over a plot
+    pub fn cursor_position(&self) -> Option<f64> {
+        self.cursor_position
+    }
+}
+
+/// Markers for the beats between `start_pos` and `end_pos` on the trace of `channel`
+fn draw_beats(
+    plot_ui: &mut PlotUi,
+    channel: &mut dyn DrawableChannel,
+    beats: &BeatList,
+    start_pos: f64,
+    end_pos: f64,
+) {
+    let start_idx = beats.positions.partition_point(|p| *p < start_pos);
+    let end_idx = beats.positions.partition_point(|p| *p <= end_pos);
+    let markers: PlotPoints = beats.positions[start_idx..end_idx]
+        .iter()
+        .filter_map(|p| channel.value_at(*p).map(|v| [*p, v]))
+        .collect();
+    plot_ui.points(
+        Points::new(markers)
+            .shape(MarkerShape::Down)
+            .radius(5.0)
+            .filled(true)
+            .color(Color32::RED),
+    );
+}
+
+/// Circles around the beats ending corrected RR intervals
+fn draw_artefacts(
+    plot_ui: &mut PlotUi,
+    channel: &mut dyn DrawableChannel,
+    log: &CorrectionLog,
+    start_pos: f64,
+    end_pos: f64,
+) {
+    let markers: PlotPoints = log
+        .corrections
+        .iter()
+        .filter(|c| c.position >= start_pos && c.position <= end_pos)
+        .filter_map(|c| channel.value_at(c.position).map(|v| [c.position, v]))
+        .collect();
+    plot_ui.points(
+        Points::new(markers)
+            .shape(MarkerShape::Circle)
+            .radius(8.0)
+            .filled(false)
+            .color(Color32::from_rgb(255, 140, 0))
+            .name("RR artefacts"),
+    );
+}
+
+/// Markers for the wave boundaries of the beats between `start_pos` and `end_pos`
+fn draw_fiducials(
+    plot_ui: &mut PlotUi,
+    channel: &mut dyn DrawableChannel,
+    fiducials: &[Fiducials],
+    start_pos: f64,
+    end_pos: f64,
+) {
+    let start_idx = fiducials.partition_point(|f| f.r_peak < start_pos);
+    let end_idx = fiducials.partition_point(|f| f.r_peak <= end_pos);
+    let visible = &fiducials[start_idx..end_idx];
+    let groups: [(&[Wave], Color32, &str); 3] = [
+        (
+            &[Wave::POnset, Wave::POffset],
+            Color32::from_rgb(50, 180, 80),
+            "P wave",
+        ),
+        (
+            &[Wave::QrsOnset, Wave::QrsOffset],
+            Color32::from_rgb(60, 120, 230),
+            "QRS",
+        ),
+        (&[Wave::TEnd], Color32::from_rgb(170, 80, 200), "T end"),
+    ];
+    for (waves, color, name) in groups {
+        let markers: PlotPoints = visible
+            .iter()
+            .flat_map(|f| waves.iter().filter_map(|wave| f.get(*wave)))
+            .filter_map(|p| channel.value_at(p).map(|v| [p, v]))
+            .collect();
+        plot_ui.points(
+            Points::new(markers)
+                .shape(MarkerShape::Diamond)
+                .radius(4.0)
+                .filled(true)
+                .color(color)
+                .name(name),
+        );
     }
 }
 
+/// Color of a channel without an explicit color. A line broken at gaps consists of several
+/// plot items, which would each get another automatic color, so it's derived from the label.
+fn label_color(label: &str) -> Color32 {
+    let hash = label.bytes().fold(2_166_136_261_u32, |h, b| {
+        (h ^ b as u32).wrapping_mul(16_777_619)
+    });
+    egui::ecolor::Hsva::new((hash % 360) as f32 / 360.0, 0.85, 0.5, 1.0).into()
+}
+
+/// Split the ordered points into the runs without steps longer than `max_gap`
+fn split_at_gaps(points: Vec<[f64; 2]>, max_gap: Option<f64>) -> Vec<Vec<[f64; 2]>> {
+    let Some(max_gap) = max_gap else {
+        return vec![points];
+    };
+    let mut segments: Vec<Vec<[f64; 2]>> = vec![];
+    for point in points {
+        match segments.last_mut() {
+            Some(segment)
+                if segment
+                    .last()
+                    .is_some_and(|last| point[0] - last[0] <= max_gap) =>
+            {
+                segment.push(point)
+            }
+            _ => segments.push(vec![point]),
+        }
+    }
+    segments
+}
+
+/// Shade the gaps of a channel over the whole height of the plot. Like the quality band it's
+/// painted on the screen, so it doesn't change the automatic bounds.
+fn draw_gaps(ui: &Ui, transform: &PlotTransform, gaps: &[(f64, f64)]) {
+    let frame = transform.frame();
+    let bounds = transform.bounds();
+    let painter = ui.painter().with_clip_rect(*frame);
+    gaps.iter()
+        .filter(|(start, end)| *end >= bounds.min()[0] && *start <= bounds.max()[0])
+        .for_each(|(start, end)| {
+            let left = transform.position_from_point_x(*start);
+            let right = transform.position_from_point_x(*end);
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, frame.top()),
+                    egui::pos2(right.max(left + 1.0), frame.bottom()),
+                ),
+                0.0,
+                Color32::from_rgba_unmultiplied(220, 60, 60, 30),
+            );
+        });
+}
+
+fn quality_color(quality: Quality) -> Color32 {
+    match quality {
+        Quality::Good => Color32::from_rgb(60, 180, 75),
+        Quality::Acceptable => Color32::from_rgb(240, 200, 40),
+        Quality::Unusable => Color32::from_rgb(220, 50, 50),
+    }
+}
+
+/// Band along the bottom edge of the plot colored by the signal quality of the windows,
+/// the `n`th band is drawn above the previous ones. It's painted on the screen rather than
+/// added to the plot, so it doesn't change the automatic bounds.
+fn draw_quality_band(ui: &Ui, transform: &PlotTransform, quality: &SignalQuality, n: usize) {
+    const BAND_HEIGHT: f32 = 6.0;
+    let frame = transform.frame();
+    let bottom = frame.bottom() - n as f32 * BAND_HEIGHT;
+    let bounds = transform.bounds();
+    let painter = ui.painter().with_clip_rect(*frame);
+    quality
+        .windows
+        .iter()
+        .filter(|w| w.end >= bounds.min()[0] && w.start <= bounds.max()[0])
+        .for_each(|w| {
+            let left = transform.position_from_point_x(w.start);
+            let right = transform.position_from_point_x(w.end);
+            painter.rect_filled(
+                egui::Rect::from_min_max(
+                    egui::pos2(left, bottom - BAND_HEIGHT),
+                    egui::pos2(right, bottom),
+                ),
+                0.0,
+                quality_color(w.quality).gamma_multiply(0.8),
+            );
+        });
+}
+
+/// Move `position` to the most prominent sample within 50 ms, i.e. the R peak
+fn snap_to_peak(channel: &mut dyn DrawableChannel, position: f64) -> f64 {
+    let points = channel.points_to_draw(position - 0.05, position + 0.05);
+    let points = points.points();
+    if points.is_empty() {
+        return position;
+    }
+    let mean = points.iter().map(|p| p.y).sum::<f64>() / points.len() as f64;
+    points
+        .iter()
+        .max_by(|a, b| (a.y - mean).abs().total_cmp(&(b.y - mean).abs()))
+        .map(|p| p.x)
+        .unwrap_or(position)
+}
+
+/// First of the sorted `positions` right of `center`
+fn next_position(positions: &[f64], center: f64) -> Option<f64> {
+    positions.iter().copied().find(|p| *p > center + 1E-6)
+}
+
+/// Last of the sorted `positions` left of `center`
+fn previous_position(positions: &[f64], center: f64) -> Option<f64> {
+    positions.iter().rev().copied().find(|p| *p < center - 1E-6)
+}
+
 #[derive(Clone, Debug)]
 pub struct TimeBasedChannel {
     name: String,
@@ -134,6 +1626,14 @@ pub struct TimeBasedChannel {
     plot_type: PlotType,
     unit: String,
     color: Color32,
+    kind: ChannelKind,
+    /// false for channels derived from sample-based data, their timestamps start at the unix epoch
+    wall_clock: bool,
+    filter: FilterSettings,
+    /// the filtered values, parallel to `data`, if the filter is active
+    filtered: Option<Vec<f64>>,
+    /// the line is broken at steps longer than this many seconds
+    max_gap: Option<f64>,
 }
 
 impl TimeBasedChannel {
@@ -144,6 +1644,7 @@ impl TimeBasedChannel {
         plot_type: PlotType,
         unit: String,
         color: Option<Color32>,
+        kind: ChannelKind,
     ) -> TimeBasedChannel {
         let color = color.unwrap_or(Color32::TRANSPARENT);
         TimeBasedChannel {
@@ -153,9 +1654,53 @@ impl TimeBasedChannel {
             plot_type,
             color,
             unit,
+            kind,
+            wall_clock: true,
+            filter: FilterSettings::default(),
+            filtered: None,
+            max_gap: None,
+        }
+    }
+
+    /// indices of the first sample at or after `start_pos` and after the last one before `end_pos`
+    fn index_range(&self, start_pos: f64, end_pos: f64) -> (usize, usize) {
+        // the data is ordered by time, so we can look up the visible range
+        let start_idx = self
+            .data
+            .partition_point(|(x, _)| timestamp_to_position(x) < start_pos);
+        let end_idx = self
+            .data
+            .partition_point(|(x, _)| timestamp_to_position(x) <= end_pos);
+        (start_idx, end_idx.max(start_idx))
+    }
+
+    /// the value at `idx` as it is drawn, i.e. filtered if the filter is active
+    fn value(&self, idx: usize) -> f64 {
+        match &self.filtered {
+            Some(filtered) => filtered[idx],
+            None => self.data[idx].1,
         }
     }
 
+    /// Create a channel from `(position, value)` pairs, e.g. for values derived from other channels
+    pub fn from_positions(
+        name: String,
+        points: &[(f64, f64)],
+        unit: String,
+        kind: ChannelKind,
+        wall_clock: bool,
+    ) -> TimeBasedChannel {
+        let data = points
+            .iter()
+            .filter_map(|(x, y)| {
+                NaiveDateTime::from_timestamp_millis((x * 1E3).round() as i64).map(|t| (t, *y))
+            })
+            .collect();
+        let mut channel = TimeBasedChannel::new(name, data, 1.0, PlotType::Line, unit, None, kind);
+        channel.wall_clock = wall_clock;
+        channel
+    }
+
     pub fn parse_polar_data(
         data: String,
         file_type: Filetype,
@@ -216,7 +1761,7 @@ impl TimeBasedChannel {
             }
             match file_type {
                 Filetype::PolarRR => {
-                    let y = fields[1].parse::<f64>().unwrap() * 1E-3_f64;
+                    let y = fields[1].parse::<f64>().unwrap();
                     channels[0].push((x, y));
                 }
                 Filetype::PolarHR => {
@@ -250,29 +1795,62 @@ impl TimeBasedChannel {
                     | Filetype::PolarRR => PlotType::Line,
                     Filetype::Unknown => PlotType::Points,
                 };
-                let unit_start = h.find('[').unwrap_or(0);
-                let unit_end = h.find(']').unwrap_or(h.len());
-                let unit = h[unit_start..unit_end].to_string();
-                TimeBasedChannel::new(h.to_string(), c, 1.0, plot_type, unit, None)
+                let (name, unit) = split_header(&h);
+                // the values have been converted while parsing
+                let unit = match unit.as_str() {
+                    "uV" => "mV".to_owned(),
+                    "mg" => "g".to_owned(),
+                    _ => unit,
+                };
+                let kind = match file_type {
+                    Filetype::PolarECG => ChannelKind::Ecg,
+                    Filetype::PolarACC => ChannelKind::Acceleration,
+                    Filetype::PolarHR => ChannelKind::HeartRate,
+                    Filetype::PolarRR => ChannelKind::RrInterval,
+                    Filetype::Unknown => ChannelKind::Other,
+                };
+                TimeBasedChannel::new(name, c, 1.0, plot_type, unit, None, kind)
             })
             .collect())
     }
 }
 
+/// Key of the RR corrections of a channel: its label and x range, so the persisted corrections
+/// aren't applied to another recording with a channel of the same label
+fn rr_corrections_key(channel: &mut dyn DrawableChannel) -> Option<String> {
+    let (first, last) = channel.x_range()?;
+    Some(format!("{} {}–{}", channel.get_label(), first, last))
+}
+
+/// Position on the x axis for a timestamp: the seconds since 1970-01-01 00:00 of the timestamp
+/// as written in the file, without a time zone
+pub fn timestamp_to_position(timestamp: &NaiveDateTime) -> f64 {
+    timestamp.timestamp_millis() as f64 / 1E3_f64
+}
+
+/// Split a header like `ecg [uV]` into the name and the unit
+fn split_header(header: &str) -> (String, String) {
+    match (header.find('['), header.rfind(']')) {
+        (Some(unit_start), Some(unit_end)) if unit_start < unit_end => (
+            header[..unit_start].trim().to_string(),
+            header[unit_start + 1..unit_end].trim().to_string(),
+        ),
+        _ => (header.trim().to_string(), String::new()),
+    }
+}
+
 impl DrawableChannel for TimeBasedChannel {
     fn get_name(&mut self) -> String {
         self.name.to_string()
     }
 
-    fn points_to_draw(&mut self, start_pos: f64, _end_pos: f64) -> PlotPoints {
-        let _ = start_pos;
-        // TODO use start_pos and end_pos
-        self.data
-            .iter()
-            .map(|(x, y)| {
+    fn points_to_draw(&mut self, start_pos: f64, end_pos: f64) -> PlotPoints {
+        let (start_idx, end_idx) = self.index_range(start_pos, end_pos);
+        (start_idx..end_idx)
+            .map(|idx| {
                 [
-                    x.timestamp_millis() as f64 / 1E3_f64,
-                    *y * self.scaling_factor,
+                    timestamp_to_position(&self.data[idx].0),
+                    self.value(idx) * self.scaling_factor,
                 ]
             })
             .collect()
@@ -282,42 +1860,144 @@ impl DrawableChannel for TimeBasedChannel {
         match self.plot_type {
             // TODO get displayed bounds - is this a performance optimization?
             PlotType::Line => {
-                let plot_points: PlotPoints = self.points_to_draw(start_pos, end_pos);
-                let line = Line::new(plot_points)
-                    .width(2.0)
-                    .color(self.color)
-                    .name(self.name.to_owned());
-                plot_ui.line(line);
+                if self.filtered.is_some() && self.filter.display == FilterDisplay::Overlay {
+                    let (start_idx, end_idx) = self.index_range(start_pos, end_pos);
+                    let raw_points: Vec<[f64; 2]> = self.data[start_idx..end_idx]
+                        .iter()
+                        .map(|(x, y)| [timestamp_to_position(x), *y * self.scaling_factor])
+                        .collect();
+                    for segment in split_at_gaps(raw_points, self.max_gap) {
+                        plot_ui.line(
+                            Line::new(PlotPoints::new(segment))
+                                .width(1.0)
+                                .color(Color32::GRAY.gamma_multiply(0.5))
+                                .name(format!("{} raw", self.get_label())),
+                        );
+                    }
+                }
+                let points: Vec<[f64; 2]> = self
+                    .points_to_draw(start_pos, end_pos)
+                    .points()
+                    .iter()
+                    .map(|p| [p.x, p.y])
+                    .collect();
+                // the segments share the name, so they are one entry of the legend
+                let label = self.get_label();
+                let color = match self.color {
+                    Color32::TRANSPARENT => label_color(&label),
+                    color => color,
+                };
+                for segment in split_at_gaps(points, self.max_gap) {
+                    let line = Line::new(PlotPoints::new(segment))
+                        .width(2.0)
+                        .color(color)
+                        .name(&label);
+                    plot_ui.line(line);
+                }
             }
             PlotType::Points => {
                 let plot_points: PlotPoints = self
                     .data
                     .iter()
                     .filter(|(_idx, v)| *v == 0.0f64)
-                    .map(|(x, y)| {
-                        [
-                            x.timestamp_millis() as f64 / 1E6_f64,
-                            *y * self.scaling_factor,
-                        ]
-                    })
+                    .map(|(x, y)| [timestamp_to_position(x), *y * self.scaling_factor])
                     .collect();
                 let points = Points::new(plot_points)
                     .color(self.color)
                     .radius(5.0)
                     .stems(0.0) // draw a line to 0
-                    .name(self.name.to_owned());
+                    .name(self.get_label());
                 plot_ui.points(points);
             }
         }
     }
 
-    fn show_settings(&mut self) {
-        todo!();
+    fn show_settings(&mut self, ui: &mut Ui) -> bool {
+        let mut settings = self.filter.clone();
+        let changed = show_filter_settings(ui, &mut settings);
+        if changed {
+            self.set_filter_settings(settings);
+        }
+        changed
+    }
+
+    fn filter_settings(&mut self) -> Option<FilterSettings> {
+        Some(self.filter.clone())
+    }
+
+    fn set_filter_settings(&mut self, settings: FilterSettings) {
+        let values: Vec<f64> = self.data.iter().map(|(_, y)| *y).collect();
+        let points: Vec<(f64, f64)> = self
+            .data
+            .iter()
+            .map(|(x, y)| (timestamp_to_position(x), *y))
+            .collect();
+        // the samples are treated as equidistant, which holds for the ECG and ACC streams
+        self.filtered = match median_sample_rate(&points) {
+            Some(samples_per_second) if settings.is_active() => {
+                Some(settings.apply(&values, samples_per_second))
+            }
+            _ => None,
+        };
+        self.filter = settings;
     }
 
     fn get_unit(&mut self) -> String {
         self.unit.to_owned()
     }
+
+    fn x_range(&mut self) -> Option<(f64, f64)> {
+        let first = timestamp_to_position(&self.data.first()?.0);
+        let last = timestamp_to_position(&self.data.last()?.0);
+        Some((first, last))
+    }
+
+    fn has_wall_clock_time(&mut self) -> bool {
+        self.wall_clock
+    }
+
+    fn value_at(&mut self, position: f64) -> Option<f64> {
+        let idx = self
+            .data
+            .partition_point(|(x, _)| timestamp_to_position(x) < position);
+        let x1 = timestamp_to_position(&self.data.get(idx)?.0);
+        let y1 = self.value(idx);
+        if x1 == position {
+            return Some(y1 * self.scaling_factor);
+        }
+        let x0 = timestamp_to_position(&self.data.get(idx.checked_sub(1)?)?.0);
+        let y0 = self.value(idx - 1);
+        let t = (position - x0) / (x1 - x0);
+        Some((y0 + (y1 - y0) * t) * self.scaling_factor)
+    }
+
+    fn get_kind(&mut self) -> ChannelKind {
+        self.kind
+    }
+
+    fn set_max_gap(&mut self, max_gap: Option<f64>) {
+        self.max_gap = max_gap;
+    }
+
+    fn uniform_signal(&mut self) -> Option<UniformSignal> {
+        let points: Vec<(f64, f64)> = (0..self.data.len())
+            .map(|idx| {
+                (
+                    timestamp_to_position(&self.data[idx].0),
+                    self.value(idx) * self.scaling_factor,
+                )
+            })
+            .collect();
+        let samples_per_second = median_sample_rate(&points)?;
+        // dropouts stay NaN instead of being interpolated over
+        let max_gap = self.max_gap.unwrap_or(GAP_FACTOR / samples_per_second);
+        Some(resample(
+            &points,
+            samples_per_second,
+            ResamplingMethod::Linear,
+            max_gap,
+        ))
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -329,9 +2009,18 @@ pub struct SampleBasedChannel {
     plot_type: PlotType,
     color: Color32,
     unit: String,
+    kind: ChannelKind,
+    /// position of the first sample on the x axis
+    start: f64,
+    /// true if `start` is a unix timestamp
+    wall_clock: bool,
+    filter: FilterSettings,
+    /// the filtered samples if the filter is active
+    filtered: Option<Vec<f64>>,
 }
 
 impl SampleBasedChannel {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         name: String,
         data: Vec<f64>,
@@ -340,6 +2029,7 @@ impl SampleBasedChannel {
         plot_type: PlotType,
         color: Option<Color32>,
         unit: String,
+        kind: ChannelKind,
     ) -> SampleBasedChannel {
         SampleBasedChannel {
             name,
@@ -349,9 +2039,69 @@ impl SampleBasedChannel {
             plot_type,
             color: color.unwrap_or(Color32::TRANSPARENT),
             unit,
+            kind,
+            start: 0.0,
+            wall_clock: false,
+            filter: FilterSettings::default(),
+            filtered: None,
         }
     }
 
+    /// Place the first sample at `start`, a unix timestamp if `wall_clock` is set
+    pub fn with_start(mut self, start: f64, wall_clock: bool) -> SampleBasedChannel {
+        self.start = start;
+        self.wall_clock = wall_clock;
+        self
+    }
+
+    /// Create a channel from a signal with a fixed sample rate, e.g. a resampled channel
+    pub fn from_uniform_signal(
+        name: String,
+        signal: UniformSignal,
+        unit: String,
+        kind: ChannelKind,
+        wall_clock: bool,
+    ) -> SampleBasedChannel {
+        SampleBasedChannel::new(
+            name,
+            signal.samples,
+            signal.samples_per_second,
+            1.0,
+            PlotType::Line,
+            None,
+            unit,
+            kind,
+        )
+        .with_start(signal.start, wall_clock)
+    }
+
+    /// the samples as they are drawn, i.e. filtered if the filter is active
+    fn values(&self) -> &[f64] {
+        self.filtered.as_deref().unwrap_or(&self.data)
+    }
+
+    /// (x, y) of the samples from `start_pos` to `end_pos`
+    fn points_from(&self, values: &[f64], start_pos: f64, end_pos: f64) -> PlotPoints {
+        // make sure our slice is within bounds
+        let start_idx: usize = (((start_pos - self.start).max(0.0f64) * self.samples_per_second)
+            .floor() as usize)
+            .min(values.len());
+        let end_idx: usize = (((end_pos - self.start).max(0.0f64) * self.samples_per_second).ceil()
+            as usize)
+            .min(values.len());
+        let start_idx = start_idx.min(end_idx);
+        values[start_idx..end_idx]
+            .iter()
+            .enumerate()
+            .map(|(idx, y)| {
+                [
+                    self.start + (start_idx + idx) as f64 / self.samples_per_second,
+                    y * self.scaling_factor,
+                ]
+            })
+            .collect()
+    }
+
     pub fn get_slice(&mut self, start: Option<usize>, end: Option<usize>) -> &[f64] {
         let start = start.unwrap_or(0);
         let end = end.unwrap_or(self.data.len());
@@ -400,9 +2150,11 @@ impl SampleBasedChannel {
                         line_it.next();
 
                         let mut data = Vec::with_capacity(n_records);
-                        data = line_it
-                            .map(|line| line.replace(',', ".").parse::<f64>().unwrap_or(f64::NAN))
-                            .collect();
+                        data.extend(
+                            line_it.map(|line| {
+                                line.replace(',', ".").parse::<f64>().unwrap_or(f64::NAN)
+                            }),
+                        );
 
                         let scaling_factor = 1.0;
                         let color = None;
@@ -419,6 +2171,7 @@ impl SampleBasedChannel {
                             plot_type,
                             color,
                             unit,
+                            ChannelKind::Ecg,
                         )]);
                     }
                 }
@@ -430,33 +2183,26 @@ impl SampleBasedChannel {
 
 impl DrawableChannel for SampleBasedChannel {
     fn points_to_draw(&mut self, start_pos: f64, end_pos: f64) -> PlotPoints {
-        // make sure our slice is within bounds
-        let start_idx: usize = ((start_pos.max(0.0f64) * self.samples_per_second).floor() as usize)
-            .min(self.data.len());
-        let end_idx: usize =
-            ((end_pos.max(0.0f64) * self.samples_per_second).ceil() as usize).min(self.data.len());
-        assert!(start_idx <= end_idx);
-        self.data[start_idx..end_idx]
-            .iter()
-            .enumerate()
-            .map(|(idx, y)| {
-                [
-                    idx as f64 / self.samples_per_second,
-                    y * self.scaling_factor,
-                ]
-            })
-            .collect()
+        self.points_from(self.values(), start_pos, end_pos)
     }
 
     fn draw(&mut self, plot_ui: &mut PlotUi, start_pos: f64, end_pos: f64) {
         match self.plot_type {
             // TODO get displayed bounds - is this a performance optimization?
             PlotType::Line => {
+                if self.filtered.is_some() && self.filter.display == FilterDisplay::Overlay {
+                    plot_ui.line(
+                        Line::new(self.points_from(&self.data, start_pos, end_pos))
+                            .width(1.0)
+                            .color(Color32::GRAY.gamma_multiply(0.5))
+                            .name(format!("{} raw", self.get_label())),
+                    );
+                }
                 let plot_points: PlotPoints = self.points_to_draw(start_pos, end_pos);
                 let line = Line::new(plot_points)
                     .width(2.0)
                     .color(self.color)
-                    .name(self.name.to_owned());
+                    .name(self.get_label());
                 plot_ui.line(line);
             }
             PlotType::Points => {
@@ -467,7 +2213,7 @@ impl DrawableChannel for SampleBasedChannel {
                     .filter(|(_idx, v)| **v == 0.0f64)
                     .map(|(idx, _y)| {
                         [
-                            idx as f64 / self.samples_per_second,
+                            self.start + idx as f64 / self.samples_per_second,
                             1.0 * self.scaling_factor,
                         ]
                     })
@@ -476,7 +2222,7 @@ impl DrawableChannel for SampleBasedChannel {
                     .color(self.color)
                     .radius(5.0)
                     .stems(0.0) // draw a line to 0
-                    .name(self.name.to_owned());
+                    .name(self.get_label());
                 plot_ui.points(points);
             }
         }
@@ -490,7 +2236,65 @@ impl DrawableChannel for SampleBasedChannel {
         self.name.to_owned()
     }
 
-    fn show_settings(&mut self) {
-        todo!();
+    fn show_settings(&mut self, ui: &mut Ui) -> bool {
+        let mut settings = self.filter.clone();
+        let changed = show_filter_settings(ui, &mut settings);
+        if changed {
+            self.set_filter_settings(settings);
+        }
+        changed
+    }
+
+    fn filter_settings(&mut self) -> Option<FilterSettings> {
+        Some(self.filter.clone())
+    }
+
+    fn set_filter_settings(&mut self, settings: FilterSettings) {
+        self.filtered = settings
+            .is_active()
+            .then(|| settings.apply(&self.data, self.samples_per_second));
+        self.filter = settings;
+    }
+
+    fn x_range(&mut self) -> Option<(f64, f64)> {
+        if self.data.is_empty() {
+            return None;
+        }
+        Some((
+            self.start,
+            self.start + (self.data.len() - 1) as f64 / self.samples_per_second,
+        ))
+    }
+
+    fn has_wall_clock_time(&mut self) -> bool {
+        self.wall_clock
+    }
+
+    fn value_at(&mut self, position: f64) -> Option<f64> {
+        let idx = (position - self.start) * self.samples_per_second;
+        if idx < 0.0 {
+            return None;
+        }
+        let idx0 = idx.floor() as usize;
+        let values = self.values();
+        let y0 = *values.get(idx0)?;
+        let y1 = *values.get(idx0 + 1).unwrap_or(&y0);
+        Some((y0 + (y1 - y0) * idx.fract()) * self.scaling_factor)
+    }
+
+    fn get_kind(&mut self) -> ChannelKind {
+        self.kind
+    }
+
+    fn uniform_signal(&mut self) -> Option<UniformSignal> {
+        Some(UniformSignal {
+            samples: self
+                .values()
+                .iter()
+                .map(|y| y * self.scaling_factor)
+                .collect(),
+            samples_per_second: self.samples_per_second,
+            start: self.start,
+        })
     }
 }
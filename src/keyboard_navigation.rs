@@ -0,0 +1,354 @@
+use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+use egui::{Context, Event, Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::ChannelPlotter;
+
+/// Everything the plot can be told to do from the keyboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NavAction {
+    PageLeft,
+    PageRight,
+    StepLeft,
+    StepRight,
+    BigStepLeft,
+    BigStepRight,
+    JumpToStart,
+    JumpToEnd,
+    /// show a window of the given number of milliseconds
+    ZoomTo(u32),
+    NextAnnotation,
+    PreviousAnnotation,
+    NextBeat,
+    PreviousBeat,
+    GoToTime,
+    ToggleHelp,
+    ZoomToSelection,
+}
+
+impl NavAction {
+    pub fn description(&self) -> String {
+        match self {
+            NavAction::PageLeft => "Page left by one screen".to_owned(),
+            NavAction::PageRight => "Page right by one screen".to_owned(),
+            NavAction::StepLeft => "Step left by 1 s".to_owned(),
+            NavAction::StepRight => "Step right by 1 s".to_owned(),
+            NavAction::BigStepLeft => "Step left by 10 s".to_owned(),
+            NavAction::BigStepRight => "Step right by 10 s".to_owned(),
+            NavAction::JumpToStart => "Jump to the start of the recording".to_owned(),
+            NavAction::JumpToEnd => "Jump to the end of the recording".to_owned(),
+            NavAction::ZoomTo(ms) if ms % 60_000 == 0 => format!("Show {} min", ms / 60_000),
+            NavAction::ZoomTo(ms) => format!("Show {} s", *ms as f64 / 1000.0),
+            NavAction::NextAnnotation => "Jump to the next annotation".to_owned(),
+            NavAction::PreviousAnnotation => "Jump to the previous annotation".to_owned(),
+            NavAction::NextBeat => "Jump to the next detected beat".to_owned(),
+            NavAction::PreviousBeat => "Jump to the previous detected beat".to_owned(),
+            NavAction::GoToTime => "Go to time...".to_owned(),
+            NavAction::ToggleHelp => "Show/hide this help".to_owned(),
+            NavAction::ZoomToSelection => "Zoom to the selected range".to_owned(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBinding {
+    pub action: NavAction,
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl KeyBinding {
+    fn new(action: NavAction, modifiers: Modifiers, key: Key) -> KeyBinding {
+        KeyBinding {
+            action,
+            modifiers,
+            key,
+        }
+    }
+
+    fn shortcut(&self) -> KeyboardShortcut {
+        KeyboardShortcut::new(self.modifiers, self.key)
+    }
+}
+
+/// The configurable key bindings, they are stored with the app state
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeyBindings {
+    pub bindings: Vec<KeyBinding>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use NavAction::*;
+        let none = Modifiers::NONE;
+        let shift = Modifiers::SHIFT;
+        KeyBindings {
+            bindings: vec![
+                KeyBinding::new(PageLeft, none, Key::PageUp),
+                KeyBinding::new(PageRight, none, Key::PageDown),
+                KeyBinding::new(StepLeft, none, Key::ArrowLeft),
+                KeyBinding::new(StepRight, none, Key::ArrowRight),
+                KeyBinding::new(BigStepLeft, shift, Key::ArrowLeft),
+                KeyBinding::new(BigStepRight, shift, Key::ArrowRight),
+                KeyBinding::new(JumpToStart, none, Key::Home),
+                KeyBinding::new(JumpToEnd, none, Key::End),
+                KeyBinding::new(ZoomTo(2_500), none, Key::Num1),
+                KeyBinding::new(ZoomTo(10_000), none, Key::Num2),
+                KeyBinding::new(ZoomTo(30_000), none, Key::Num3),
+                KeyBinding::new(ZoomTo(60_000), none, Key::Num4),
+                KeyBinding::new(ZoomTo(300_000), none, Key::Num5),
+                KeyBinding::new(NextAnnotation, none, Key::N),
+                KeyBinding::new(PreviousAnnotation, shift, Key::N),
+                KeyBinding::new(NextBeat, none, Key::B),
+                KeyBinding::new(PreviousBeat, shift, Key::B),
+                KeyBinding::new(GoToTime, none, Key::G),
+                KeyBinding::new(ToggleHelp, none, Key::F1),
+                KeyBinding::new(ZoomToSelection, none, Key::Z),
+            ],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TimeInputMode {
+    Relative,
+    WallClock,
+}
+
+/// Handles the key bindings, the shortcut help overlay and the "go to time" dialog
+pub struct KeyboardNavigation {
+    pub key_bindings: KeyBindings,
+    show_help: bool,
+    /// index of the binding waiting for a new key
+    rebinding: Option<usize>,
+    show_go_to_time: bool,
+    focus_time_input: bool,
+    time_input: String,
+    time_input_mode: TimeInputMode,
+    time_input_error: Option<String>,
+}
+
+impl KeyboardNavigation {
+    pub fn new(key_bindings: KeyBindings) -> KeyboardNavigation {
+        KeyboardNavigation {
+            key_bindings,
+            show_help: false,
+            rebinding: None,
+            show_go_to_time: false,
+            focus_time_input: false,
+            time_input: String::new(),
+            time_input_mode: TimeInputMode::Relative,
+            time_input_error: None,
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.show_help = !self.show_help;
+    }
+
+    /// Consume the bound keys and apply their actions to the plotter
+    pub fn handle_input(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        if let Some(idx) = self.rebinding {
+            ctx.input_mut(|i| {
+                let pressed = i.events.iter().find_map(|e| match e {
+                    Event::Key {
+                        key,
+                        pressed: true,
+                        modifiers,
+                        ..
+                    } => Some((*key, *modifiers)),
+                    _ => None,
+                });
+                if let Some((key, modifiers)) = pressed {
+                    if key != Key::Escape {
+                        self.key_bindings.bindings[idx].key = key;
+                        self.key_bindings.bindings[idx].modifiers = modifiers;
+                    }
+                    self.rebinding = None;
+                    i.events.clear();
+                }
+            });
+            return;
+        }
+        // don't steal keys from text fields
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        // check the bindings with more modifiers first, so SHIFT+→ doesn't trigger →
+        let mut bindings = self.key_bindings.bindings.clone();
+        bindings.sort_by_key(|b| {
+            std::cmp::Reverse(
+                b.modifiers.shift as u8 + b.modifiers.alt as u8 + b.modifiers.command as u8,
+            )
+        });
+        let actions: Vec<NavAction> = ctx.input_mut(|i| {
+            bindings
+                .iter()
+                .filter(|b| i.consume_shortcut(&b.shortcut()))
+                .map(|b| b.action)
+                .collect()
+        });
+        actions.iter().for_each(|action| match action {
+            NavAction::GoToTime => {
+                self.show_go_to_time = true;
+                self.focus_time_input = true;
+                self.time_input_error = None;
+            }
+            NavAction::ToggleHelp => self.toggle_help(),
+            action => plotter.navigate(*action),
+        });
+    }
+
+    /// Show the help overlay and the "go to time" dialog if they are open
+    pub fn show_windows(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut show_help = self.show_help;
+        egui::Window::new("Keyboard shortcuts")
+            .open(&mut show_help)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label("Click a shortcut and press a new key combination to change it.");
+                ui.separator();
+                egui::Grid::new("key_bindings_grid")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (idx, binding) in self.key_bindings.bindings.iter().enumerate() {
+                            ui.label(binding.action.description());
+                            let text = if self.rebinding == Some(idx) {
+                                "press a key...".to_owned()
+                            } else {
+                                ctx.format_shortcut(&binding.shortcut())
+                            };
+                            if ui.button(text).clicked() {
+                                self.rebinding = Some(idx);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                if ui.button("Restore defaults").clicked() {
+                    self.key_bindings = KeyBindings::default();
+                    self.rebinding = None;
+                }
+            });
+        self.show_help = show_help;
+
+        let mut show_go_to_time = self.show_go_to_time;
+        let mut close = false;
+        egui::Window::new("Go to time")
+            .open(&mut show_go_to_time)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.radio_value(
+                        &mut self.time_input_mode,
+                        TimeInputMode::Relative,
+                        "Relative to the start",
+                    );
+                    ui.radio_value(
+                        &mut self.time_input_mode,
+                        TimeInputMode::WallClock,
+                        "Wall-clock time",
+                    );
+                });
+                ui.label(match self.time_input_mode {
+                    TimeInputMode::Relative => "e.g. 1:23:45.5, 12:30 or 90",
+                    TimeInputMode::WallClock => "e.g. 14:03:22 or 2023-11-05 14:03:22.250",
+                });
+                let response = ui.text_edit_singleline(&mut self.time_input);
+                if self.focus_time_input {
+                    response.request_focus();
+                    self.focus_time_input = false;
+                }
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(Key::Enter));
+                if ui.button("Go").clicked() || submitted {
+                    match self.target_position(plotter) {
+                        Ok(position) => {
+                            plotter.center_on(position);
+                            close = true;
+                        }
+                        Err(e) => self.time_input_error = Some(e),
+                    }
+                }
+                if let Some(error) = &self.time_input_error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+            });
+        self.show_go_to_time = show_go_to_time && !close;
+    }
+
+    /// Convert the text in the "go to time" dialog to a position on the x axis
+    fn target_position(&self, plotter: &mut ChannelPlotter) -> Result<f64, String> {
+        let (start, _end) = plotter
+            .recording_x_range()
+            .ok_or_else(|| "No data loaded".to_owned())?;
+        let input = self.time_input.trim();
+        match self.time_input_mode {
+            TimeInputMode::Relative => parse_duration(input)
+                .map(|offset| start + offset)
+                .ok_or_else(|| format!("Can't read \"{}\" as a duration", input)),
+            TimeInputMode::WallClock => {
+                let time_formatter = plotter.time_formatter();
+                if !time_formatter.wall_clock {
+                    return Err("The loaded data has no wall-clock time".to_owned());
+                }
+                let start_datetime = time_formatter
+                    .displayed_datetime(start)
+                    .ok_or_else(|| "Invalid recording start".to_owned())?;
+                parse_wall_clock(input, start_datetime)
+                    .and_then(|datetime| time_formatter.timestamp_from_displayed(datetime))
+                    .ok_or_else(|| format!("Can't read \"{}\" as a date or time", input))
+            }
+        }
+    }
+}
+
+/// Parse durations like `1:23:45.5`, `12:30` or `90.5` to seconds
+pub fn parse_duration(input: &str) -> Option<f64> {
+    let parts: Vec<&str> = input.split(':').collect();
+    if parts.is_empty() || parts.len() > 3 {
+        return None;
+    }
+    let (seconds, minutes_and_hours) = parts.split_last()?;
+    let seconds = seconds.trim().parse::<f64>().ok()?;
+    let mut total = seconds;
+    let mut factor = 60.0;
+    for part in minutes_and_hours.iter().rev() {
+        total += part.trim().parse::<u32>().ok()? as f64 * factor;
+        factor *= 60.0;
+    }
+    Some(total)
+}
+
+/// Parse a date and time or only a time of day.
+///
+/// A time of day is placed on the day of `reference`, or on the next day
+/// if it would be before `reference` (recordings spanning midnight).
+pub fn parse_wall_clock(input: &str, reference: NaiveDateTime) -> Option<NaiveDateTime> {
+    const DATETIME_FORMATS: [&str; 4] = [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M",
+        "%d.%m.%Y %H:%M:%S%.f",
+    ];
+    const TIME_FORMATS: [&str; 2] = ["%H:%M:%S%.f", "%H:%M"];
+
+    if let Some(datetime) = DATETIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveDateTime::parse_from_str(input, f).ok())
+    {
+        return Some(datetime);
+    }
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveTime::parse_from_str(input, f).ok())?;
+    let date: NaiveDate = reference.date();
+    let datetime = date.and_time(time);
+    if datetime < reference {
+        date.succ_opt().map(|d| d.and_time(time))
+    } else {
+        Some(datetime)
+    }
+}
@@ -6,7 +6,19 @@ mod sample_data;
 pub use sample_data::SampleData;
 mod tools;
 pub use tools::grid_helper;
+pub use tools::time_format;
 mod data_structures;
-pub use data_structures::ChannelPlotter;
+pub use data_structures::{
+    ChannelKind, ChannelPlotter, DrawableChannel, SampleBasedChannel, TimeBasedChannel,
+};
 mod data_import;
 pub use data_import::parse_content;
+mod analysis;
+mod keyboard_navigation;
+mod navigator;
+mod readout;
+mod views;
+pub use analysis::{
+    activity, af_screening, artefacts, beat_template, delineation, expression, filters, heart_rate,
+    hrv, qrs_detection, resample, respiration, signal_quality, spectrum, statistics,
+};
@@ -0,0 +1,234 @@
+use egui::{Color32, CursorIcon, Stroke, Ui};
+use egui_plot::{Line, Plot, PlotPoints, Polygon};
+
+use crate::data_structures::{Annotation, DrawableChannel};
+use crate::grid_helper::clock_grid_spacer;
+use crate::time_format::TimeFormatter;
+
+/// number of buckets the whole recording is reduced to
+const N_BINS: usize = 1000;
+/// distance in pixels in which the edges of the window rectangle can be grabbed
+const EDGE_GRAB_DISTANCE: f64 = 6.0;
+
+/// min/max envelope of a channel, normalized to the range [0, 1]
+struct ChannelOverview {
+    name: String,
+    upper: Vec<[f64; 2]>,
+    lower: Vec<[f64; 2]>,
+}
+
+enum DragMode {
+    Move { grab_offset: f64 },
+    ResizeStart,
+    ResizeEnd,
+}
+
+/// The navigator strip shows a decimated overview of all channels
+/// and a window rectangle for the range shown in the main plot.
+#[derive(Default)]
+pub struct Navigator {
+    overviews: Vec<ChannelOverview>,
+    /// the plotter generation the overviews have been calculated for
+    generation: Option<u64>,
+    drag_mode: Option<DragMode>,
+}
+
+impl Navigator {
+    /// Show the navigator, returns the new x range for the main plot if the user moved the window
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        time_formatter: TimeFormatter,
+        channels: &mut [Box<dyn DrawableChannel>],
+        annotations: &[Annotation],
+        visible_x_bounds: Option<(f64, f64)>,
+        generation: u64,
+    ) -> Option<(f64, f64)> {
+        if self.generation != Some(generation) {
+            self.overviews = channels
+                .iter_mut()
+                .filter_map(|c| calculate_overview(c.as_mut()))
+                .collect();
+            self.generation = Some(generation);
+        }
+
+        let mut new_bounds = None;
+        let utc_offset = visible_x_bounds
+            .map(|(start, end)| time_formatter.utc_offset((start + end) / 2.0))
+            .unwrap_or(0.0);
+        Plot::new("navigator")
+            .height(ui.available_height())
+            .allow_drag(false)
+            .allow_zoom(false)
+            .allow_scroll(false)
+            .allow_boxed_zoom(false)
+            .allow_double_click_reset(false)
+            .show_y(false)
+            .show_axes([true, false])
+            .include_y(0.0)
+            .include_y(1.0)
+            .set_margin_fraction(egui::Vec2 { x: 0.0, y: 0.05 })
+            .x_grid_spacer(clock_grid_spacer(utc_offset))
+            .x_axis_formatter(move |value, _max_chars, range| {
+                time_formatter.format_tick(value, range)
+            })
+            .show(ui, |plot_ui| {
+                self.overviews.iter().for_each(|overview| {
+                    let color = plot_ui.ctx().style().visuals.text_color();
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(overview.upper.clone()))
+                            .color(color.gamma_multiply(0.6))
+                            .name(overview.name.to_owned()),
+                    );
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(overview.lower.clone()))
+                            .color(color.gamma_multiply(0.6))
+                            .name(overview.name.to_owned()),
+                    );
+                });
+
+                // annotation ticks at the upper edge, episodes as bars along it
+                annotations.iter().for_each(|annotation| {
+                    plot_ui.line(
+                        Line::new(PlotPoints::new(vec![
+                            [annotation.position, 0.85],
+                            [annotation.position, 1.05],
+                        ]))
+                        .color(Color32::GOLD)
+                        .width(2.0),
+                    );
+                    if let Some(end) = annotation.end {
+                        plot_ui.line(
+                            Line::new(PlotPoints::new(vec![
+                                [annotation.position, 1.0],
+                                [end, 1.0],
+                            ]))
+                            .color(Color32::GOLD)
+                            .width(4.0),
+                        );
+                    }
+                });
+
+                let Some((start, end)) = visible_x_bounds else {
+                    return;
+                };
+                plot_ui.polygon(
+                    Polygon::new(PlotPoints::new(vec![
+                        [start, -0.05],
+                        [end, -0.05],
+                        [end, 1.05],
+                        [start, 1.05],
+                    ]))
+                    .fill_color(Color32::from_rgba_unmultiplied(100, 150, 250, 40))
+                    .stroke(Stroke::new(2.0, Color32::from_rgb(100, 150, 250))),
+                );
+
+                let response = plot_ui.response().clone();
+                let Some(pointer) = plot_ui
+                    .ctx()
+                    .input(|i| i.pointer.interact_pos())
+                    .map(|pos| plot_ui.plot_from_screen(pos).x)
+                else {
+                    return;
+                };
+                let grab_distance = EDGE_GRAB_DISTANCE * plot_ui.transform().dvalue_dpos()[0];
+                let width = end - start;
+
+                if response.hovered() && self.drag_mode.is_none() {
+                    if (pointer - start).abs() < grab_distance
+                        || (pointer - end).abs() < grab_distance
+                    {
+                        plot_ui.ctx().set_cursor_icon(CursorIcon::ResizeHorizontal);
+                    } else if pointer > start && pointer < end {
+                        plot_ui.ctx().set_cursor_icon(CursorIcon::Grab);
+                    }
+                }
+
+                if response.drag_started() {
+                    self.drag_mode = Some(if (pointer - start).abs() < grab_distance {
+                        DragMode::ResizeStart
+                    } else if (pointer - end).abs() < grab_distance {
+                        DragMode::ResizeEnd
+                    } else if pointer > start && pointer < end {
+                        DragMode::Move {
+                            grab_offset: pointer - start,
+                        }
+                    } else {
+                        // jump to the pointer and keep dragging from the center of the window
+                        DragMode::Move {
+                            grab_offset: width / 2.0,
+                        }
+                    });
+                }
+
+                if response.dragged() {
+                    new_bounds = match self.drag_mode {
+                        Some(DragMode::Move { grab_offset }) => {
+                            plot_ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+                            Some((pointer - grab_offset, pointer - grab_offset + width))
+                        }
+                        Some(DragMode::ResizeStart) => {
+                            Some((pointer.min(end - grab_distance), end))
+                        }
+                        Some(DragMode::ResizeEnd) => {
+                            Some((start, pointer.max(start + grab_distance)))
+                        }
+                        None => None,
+                    };
+                } else if response.clicked() {
+                    // center the window on the clicked position
+                    new_bounds = Some((pointer - width / 2.0, pointer + width / 2.0));
+                }
+
+                if response.drag_released() {
+                    self.drag_mode = None;
+                }
+            });
+        new_bounds
+    }
+}
+
+/// Reduce a channel to its min/max envelope in `N_BINS` buckets
+fn calculate_overview(channel: &mut dyn DrawableChannel) -> Option<ChannelOverview> {
+    let points = channel.points_to_draw(f64::NEG_INFINITY, f64::INFINITY);
+    let points = points.points();
+    let first = points.first()?.x;
+    let last = points.last()?.x;
+    if last <= first {
+        return None;
+    }
+
+    let (y_min, y_max) = points
+        .iter()
+        .filter(|p| p.y.is_finite())
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+            (min.min(p.y), max.max(p.y))
+        });
+    let y_range = if y_max > y_min { y_max - y_min } else { 1.0 };
+
+    let bin_width = (last - first) / N_BINS as f64;
+    let mut bins = vec![(f64::INFINITY, f64::NEG_INFINITY); N_BINS];
+    points.iter().filter(|p| p.y.is_finite()).for_each(|p| {
+        let idx = (((p.x - first) / bin_width) as usize).min(N_BINS - 1);
+        let (min, max) = &mut bins[idx];
+        *min = min.min(p.y);
+        *max = max.max(p.y);
+    });
+
+    let mut upper = Vec::with_capacity(N_BINS);
+    let mut lower = Vec::with_capacity(N_BINS);
+    bins.iter()
+        .enumerate()
+        .filter(|(_, (min, max))| min <= max)
+        .for_each(|(idx, (min, max))| {
+            let x = first + (idx as f64 + 0.5) * bin_width;
+            upper.push([x, (max - y_min) / y_range]);
+            lower.push([x, (min - y_min) / y_range]);
+        });
+
+    Some(ChannelOverview {
+        name: channel.get_name(),
+        upper,
+        lower,
+    })
+}
@@ -0,0 +1,88 @@
+use egui::Ui;
+
+use crate::analysis::qrs_detection::{detect_r_peaks, BeatList};
+use crate::analysis::resample::{median_sample_rate, resample_linear};
+use crate::data_structures::{ChannelKind, DrawableChannel};
+use crate::ChannelPlotter;
+
+/// seconds of the ECG to each side of the cursor in which beats are detected for the heart rate
+const HEART_RATE_SPAN: f64 = 5.0;
+
+/// Shows the values of all channels at the crosshair position
+/// and lets the user choose which channels are drawn.
+pub fn show_readout(ui: &mut Ui, plotter: &mut ChannelPlotter) {
+    ui.heading("Channels");
+    ui.checkbox(&mut plotter.stacked, "One plot per channel");
+    ui.separator();
+
+    let cursor_position = plotter.cursor_position();
+    match cursor_position {
+        Some(position) => {
+            let time_formatter = plotter.time_formatter();
+            ui.label(time_formatter.format_position(position));
+        }
+        None => {
+            ui.label("Hover a plot to see the values at the cursor.");
+        }
+    }
+    ui.separator();
+
+    egui::Grid::new("readout_grid")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            for idx in 0..plotter.channels.len() {
+                let label = plotter.channels[idx].get_label();
+                let mut visible = !plotter.hidden_channels.contains(&label);
+                if ui.checkbox(&mut visible, &label).changed() {
+                    if visible {
+                        plotter.hidden_channels.remove(&label);
+                    } else {
+                        plotter.hidden_channels.insert(label.to_owned());
+                    }
+                }
+                let channel = plotter.channels[idx].as_mut();
+                let value = cursor_position.and_then(|p| channel.value_at(p));
+                match value {
+                    Some(value) if visible => {
+                        ui.monospace(format!("{:.3} {}", value, channel.get_unit()));
+                    }
+                    _ => {
+                        ui.label("–");
+                    }
+                }
+                ui.menu_button("⚙", |ui| plotter.show_channel_settings(ui, &label))
+                    .response
+                    .on_hover_text("Channel settings");
+                ui.end_row();
+
+                let channel = plotter.channels[idx].as_mut();
+                if visible && channel.get_kind() == ChannelKind::Ecg {
+                    ui.label("    heart rate");
+                    // the same beats as in the beat list, detected around the cursor if there are none
+                    let heart_rate =
+                        cursor_position.and_then(|p| match plotter.beats.get(&label) {
+                            Some(beats) if !beats.positions.is_empty() => beats.heart_rate_at(p),
+                            _ => estimate_heart_rate(channel, p),
+                        });
+                    match heart_rate {
+                        Some(heart_rate) => ui.monospace(format!("{:.0} bpm", heart_rate)),
+                        None => ui.label("–"),
+                    };
+                    ui.end_row();
+                }
+            }
+        });
+}
+
+/// Heart rate at `position` from the R peaks detected around it with the detector of the beat list
+fn estimate_heart_rate(channel: &mut dyn DrawableChannel, position: f64) -> Option<f64> {
+    let points: Vec<(f64, f64)> = channel
+        .points_to_draw(position - HEART_RATE_SPAN, position + HEART_RATE_SPAN)
+        .points()
+        .iter()
+        .map(|p| (p.x, p.y))
+        .collect();
+    let signal = resample_linear(&points, median_sample_rate(&points)?);
+    BeatList::new(detect_r_peaks(&signal)).heart_rate_at(position)
+}
@@ -1 +1,2 @@
 pub mod grid_helper;
+pub mod time_format;
@@ -25,22 +25,41 @@ fn fill_marks_between(out: &mut Vec<GridMark>, step_size: f64, (min, max): (f64,
     out.extend(marks_iter);
 }
 pub fn ecg_grid_spacer(grid_input: GridInput) -> Vec<GridMark> {
+    clock_grid_marks(grid_input, 0.0)
+}
+
+/// Like `ecg_grid_spacer`, but the marks are shifted by `utc_offset` seconds,
+/// so minute and hour marks are on whole minutes and hours of the displayed time zone
+pub fn clock_grid_spacer(utc_offset: f64) -> impl Fn(GridInput) -> Vec<GridMark> {
+    move |grid_input| clock_grid_marks(grid_input, utc_offset)
+}
+
+fn clock_grid_marks(grid_input: GridInput, utc_offset: f64) -> Vec<GridMark> {
     /*
     We want the classic ecg grid with
     0.04s between the smallest marks, 0.2 s between the medium marks and 1.0 s between the large marks
     but if we zoom out 10 s steps, minute, 5 minute and hour marks - so we go a little crazy
+    for recordings spanning many hours we continue with 10 minute, hour, 6 hour and day marks
     */
-
-    // now let's generate the grid marks based on the base_step_size
-    if grid_input.base_step_size >= 60.0 {
-        generate_marks([60.0, 300.0, 3600.0], grid_input.bounds)
+    let step_sizes = if grid_input.base_step_size >= 3600.0 {
+        [3600.0, 21600.0, 86400.0]
+    } else if grid_input.base_step_size >= 600.0 {
+        [600.0, 3600.0, 21600.0]
+    } else if grid_input.base_step_size >= 60.0 {
+        [60.0, 300.0, 3600.0]
     } else if grid_input.base_step_size >= 10.0 {
-        generate_marks([10.0, 60.0, 300.0], grid_input.bounds)
+        [10.0, 60.0, 300.0]
     } else if grid_input.base_step_size >= 1.0 {
-        generate_marks([1.0, 10.0, 60.0], grid_input.bounds)
+        [1.0, 10.0, 60.0]
     } else if grid_input.base_step_size >= 0.2 {
-        generate_marks([0.2, 1.0, 10.0], grid_input.bounds)
+        [0.2, 1.0, 10.0]
     } else {
-        generate_marks([0.04, 0.2, 1.0], grid_input.bounds)
-    }
+        [0.04, 0.2, 1.0]
+    };
+
+    // now let's generate the grid marks in the shifted time and move them back
+    let (min, max) = grid_input.bounds;
+    let mut marks = generate_marks(step_sizes, (min + utc_offset, max + utc_offset));
+    marks.iter_mut().for_each(|m| m.value -= utc_offset);
+    marks
 }
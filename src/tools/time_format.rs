@@ -0,0 +1,144 @@
+use std::ops::RangeInclusive;
+
+use chrono::{Local, NaiveDateTime, Offset, TimeZone};
+use serde::{Deserialize, Serialize};
+
+/// Time zone used to show absolute timestamps.
+///
+/// The timestamps in the files carry no time zone. They are the local wall-clock time of the
+/// phone which recorded them, assumed to be in the time zone of this computer: local time shows
+/// them unchanged, UTC converts them with the offset of this computer's time zone.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TimeZoneSetting {
+    Utc,
+    Local,
+}
+
+impl TimeZoneSetting {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TimeZoneSetting::Utc => "UTC",
+            TimeZoneSetting::Local => "Local time",
+        }
+    }
+}
+
+/// Formats positions on the x axis either as wall-clock time (for unix timestamps)
+/// or as time relative to the start of the recording.
+#[derive(Clone, Copy, Debug)]
+pub struct TimeFormatter {
+    pub wall_clock: bool,
+    pub time_zone: TimeZoneSetting,
+}
+
+impl TimeFormatter {
+    pub fn new(wall_clock: bool, time_zone: TimeZoneSetting) -> TimeFormatter {
+        TimeFormatter {
+            wall_clock,
+            time_zone,
+        }
+    }
+
+    /// Seconds to add to a position to get the displayed time, used to align the grid
+    pub fn utc_offset(&self, position: f64) -> f64 {
+        match (self.wall_clock, self.time_zone) {
+            (true, TimeZoneSetting::Utc) => timestamp_to_datetime(position)
+                .and_then(|dt| Local.offset_from_local_datetime(&dt).earliest())
+                .map(|offset| -offset.fix().local_minus_utc() as f64)
+                .unwrap_or(0.0),
+            _ => 0.0,
+        }
+    }
+
+    /// Label for a tick on the x axis, the date is added if `range` spans midnight
+    pub fn format_tick(&self, position: f64, range: &RangeInclusive<f64>) -> String {
+        let with_millis = position.fract().abs() > 1E-6;
+        if !self.wall_clock {
+            return format_relative(position, with_millis);
+        }
+        let spans_midnight = match (
+            self.displayed_datetime(*range.start()),
+            self.displayed_datetime(*range.end()),
+        ) {
+            (Some(start), Some(end)) => start.date() != end.date(),
+            _ => false,
+        };
+        match self.displayed_datetime(position) {
+            Some(dt) => {
+                let time_format = if with_millis {
+                    "%H:%M:%S%.3f"
+                } else {
+                    "%H:%M:%S"
+                };
+                if spans_midnight {
+                    dt.format(&format!("%Y-%m-%d {}", time_format)).to_string()
+                } else {
+                    dt.format(time_format).to_string()
+                }
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Full precision label for a position, e.g. for tooltips
+    pub fn format_position(&self, position: f64) -> String {
+        if !self.wall_clock {
+            return format_relative(position, true);
+        }
+        self.displayed_datetime(position)
+            .map(|dt| dt.format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn axis_label(&self) -> String {
+        if self.wall_clock {
+            format!("Time ({})", self.time_zone.label())
+        } else {
+            "Time [hh:mm:ss]".to_owned()
+        }
+    }
+
+    /// the displayed date and time for a position, which holds the local time of the file
+    pub fn displayed_datetime(&self, position: f64) -> Option<NaiveDateTime> {
+        let dt = timestamp_to_datetime(position)?;
+        match self.time_zone {
+            TimeZoneSetting::Local => Some(dt),
+            TimeZoneSetting::Utc => Some(Local.from_local_datetime(&dt).earliest()?.naive_utc()),
+        }
+    }
+
+    /// inverse of `displayed_datetime`
+    pub fn timestamp_from_displayed(&self, dt: NaiveDateTime) -> Option<f64> {
+        let dt = match self.time_zone {
+            TimeZoneSetting::Local => dt,
+            TimeZoneSetting::Utc => Local.from_utc_datetime(&dt).naive_local(),
+        };
+        Some(dt.timestamp_millis() as f64 / 1E3_f64)
+    }
+}
+
+/// Convert a position on the x axis (unix timestamp in seconds) to a date and time
+pub fn timestamp_to_datetime(position: f64) -> Option<NaiveDateTime> {
+    NaiveDateTime::from_timestamp_millis((position * 1E3).round() as i64)
+}
+
+/// Format seconds as `hh:mm:ss` or `hh:mm:ss.mmm`
+pub fn format_relative(position: f64, with_millis: bool) -> String {
+    let sign = if position < 0.0 { "-" } else { "" };
+    let total_millis = (position.abs() * 1E3).round() as i64;
+    let hours = total_millis / 3_600_000;
+    let minutes = (total_millis / 60_000) % 60;
+    let seconds = (total_millis / 1000) % 60;
+    if with_millis {
+        format!(
+            "{}{:02}:{:02}:{:02}.{:03}",
+            sign,
+            hours,
+            minutes,
+            seconds,
+            total_millis % 1000
+        )
+    } else {
+        format!("{}{:02}:{:02}:{:02}", sign, hours, minutes, seconds)
+    }
+}
@@ -0,0 +1,18 @@
+pub mod activity;
+pub mod af_screening;
+pub mod beat_detection;
+pub mod beat_overlay;
+mod common;
+pub mod data_quality;
+pub mod delineation;
+pub mod derived_channels;
+pub mod filter_settings;
+pub mod heart_rate;
+pub mod heart_rate_comparison;
+pub mod hrv;
+pub mod resampling;
+pub mod respiration;
+pub mod selections;
+pub mod spectrogram;
+pub mod spectrum;
+pub mod statistics;
@@ -0,0 +1,144 @@
+use egui::{Context, DragValue};
+
+use crate::analysis::activity::{Activity, Axis};
+use crate::data_structures::ChannelKind;
+use crate::views::common::{export_csv, select_channel};
+use crate::ChannelPlotter;
+
+/// Window deriving movement and posture channels from the three accelerometer axes
+pub struct ActivityView {
+    pub open: bool,
+    axes: [Option<String>; 3],
+    upright_axis: Axis,
+    /// length of the epochs of ENMO, activity counts and cadence in seconds
+    epoch: f64,
+    /// the activity measures with the epoch length they were calculated for
+    result: Option<(Activity, f64)>,
+    message: Option<String>,
+}
+
+impl Default for ActivityView {
+    fn default() -> Self {
+        ActivityView {
+            open: false,
+            axes: [None, None, None],
+            upright_axis: Axis::X,
+            epoch: 10.0,
+            result: None,
+            message: None,
+        }
+    }
+}
+
+impl ActivityView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Activity")
+            .open(&mut open)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                let labels = plotter.channel_labels(ChannelKind::Acceleration);
+                if labels.len() < 3 {
+                    ui.label("Load an accelerometer file with three axes first.");
+                    return;
+                }
+                for (idx, (axis, name)) in self.axes.iter_mut().zip(["X", "Y", "Z"]).enumerate() {
+                    // the axes are loaded in the order X, Y, Z
+                    if !axis.as_ref().is_some_and(|a| labels.contains(a)) {
+                        *axis = labels.get(idx).cloned();
+                    }
+                    select_channel(ui, name, axis, &labels);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Axis along the body when upright");
+                    for axis in [Axis::X, Axis::Y, Axis::Z] {
+                        ui.radio_value(&mut self.upright_axis, axis, axis.label());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Epoch");
+                    ui.add(
+                        DragValue::new(&mut self.epoch)
+                            .speed(0.5)
+                            .clamp_range(1.0..=600.0)
+                            .suffix(" s"),
+                    );
+                });
+                if ui
+                    .button("Add activity channels")
+                    .on_hover_text(
+                        "Vector magnitude, ENMO, activity counts, cadence, steps and tilt",
+                    )
+                    .clicked()
+                {
+                    if let [Some(x), Some(y), Some(z)] = &self.axes {
+                        self.result = plotter
+                            .add_activity_channels([x, y, z], self.epoch, self.upright_axis)
+                            .map(|activity| (activity, self.epoch));
+                        self.message = self
+                            .result
+                            .is_none()
+                            .then(|| "The axes have too few samples.".to_owned());
+                    }
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let Some((result, epoch)) = &self.result else {
+                    return;
+                };
+                ui.separator();
+                show_summary(ui, result, *epoch);
+                let time_formatter = plotter.time_formatter();
+                export_csv(ui, "activity.csv", || {
+                    let mut csv =
+                        "epoch center,ENMO [mg],activity counts [mg·s],cadence [steps/min]\n"
+                            .to_owned();
+                    for idx in 0..result.enmo.len() {
+                        csv += &format!(
+                            "{},{},{},{}\n",
+                            time_formatter.format_position(result.enmo[idx].0),
+                            result.enmo[idx].1,
+                            result.counts[idx].1,
+                            result.cadence[idx].1
+                        );
+                    }
+                    csv
+                });
+            });
+        self.open = open;
+    }
+}
+
+fn show_summary(ui: &mut egui::Ui, result: &Activity, epoch: f64) {
+    let walking: Vec<f64> = result
+        .cadence
+        .iter()
+        .map(|(_, cadence)| *cadence)
+        .filter(|cadence| *cadence > 0.0)
+        .collect();
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len().max(1) as f64;
+    let enmo: Vec<f64> = result.enmo.iter().map(|(_, enmo)| *enmo).collect();
+    egui::Grid::new("activity_summary")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Steps");
+            ui.monospace(result.steps.len().to_string());
+            ui.end_row();
+            ui.label("Time walking");
+            ui.monospace(format!("{:.1} min", walking.len() as f64 * epoch / 60.0));
+            ui.end_row();
+            ui.label("Mean cadence walking");
+            ui.monospace(format!("{:.0} steps/min", mean(&walking)));
+            ui.end_row();
+            ui.label("Mean ENMO");
+            ui.monospace(format!("{:.1} mg", mean(&enmo)));
+            ui.end_row();
+            for (posture, share) in result.posture_shares() {
+                ui.label(posture.label());
+                ui.monospace(format!("{:.0} %", share * 100.0));
+                ui.end_row();
+            }
+        });
+}
@@ -0,0 +1,253 @@
+use egui::{Color32, Context, DragValue};
+use egui_plot::{HLine, Legend, Line, LineStyle, Plot, PlotPoints, Points};
+
+use crate::analysis::af_screening::{screen_af, AfScreening, AfScreeningSettings};
+use crate::data_structures::{AnalysisRange, AnnotationKind};
+use crate::views::common::{export_csv, low_quality_note, select_channel, select_range};
+use crate::ChannelPlotter;
+
+/// label of the annotations of the episodes, earlier ones are replaced by a new screening
+const EPISODE_LABEL: &str = "Irregular rhythm (AF screening)";
+
+/// Window screening an RR series for irregular episodes as in atrial fibrillation
+pub struct AfScreeningView {
+    pub open: bool,
+    /// label of the ECG or RR interval channel the intervals are taken from
+    source: Option<String>,
+    range: AnalysisRange,
+    settings: AfScreeningSettings,
+    result: Option<AfScreening>,
+    message: Option<String>,
+}
+
+impl Default for AfScreeningView {
+    fn default() -> Self {
+        AfScreeningView {
+            open: false,
+            source: None,
+            range: AnalysisRange::Recording,
+            settings: AfScreeningSettings::default(),
+            result: None,
+            message: None,
+        }
+    }
+}
+
+impl AfScreeningView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("AF screening")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                ui.colored_label(
+                    Color32::from_rgb(230, 140, 0),
+                    "Screening only, not a diagnosis. Irregular RR intervals also result from \
+                     ectopic beats, other arrhythmias or detection errors; suspected atrial \
+                     fibrillation has to be confirmed from the ECG by a physician.",
+                );
+                let sources = plotter.rr_sources();
+                if sources.is_empty() {
+                    ui.label(
+                        "Detect the beats of an ECG channel or load an RR interval file first.",
+                    );
+                    return;
+                }
+                select_channel(ui, "RR intervals", &mut self.source, &sources);
+                let range = select_range(ui, "af_screening_range", &mut self.range, plotter);
+                self.show_settings(ui);
+                let (Some(source), Some(range)) = (self.source.clone(), range) else {
+                    return;
+                };
+                low_quality_note(ui, plotter, &source);
+                if ui
+                    .button("Screen")
+                    .on_hover_text("Annotate the irregular episodes, replacing earlier ones")
+                    .clicked()
+                {
+                    self.screen(plotter, &source, range);
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let Some(result) = &self.result else {
+                    return;
+                };
+                ui.separator();
+
+                let time_formatter = plotter.time_formatter();
+                ui.label(format!(
+                    "{} irregular episodes, {:.1} % of {:.0} min analysed ({:.1} min)",
+                    result.episodes.len(),
+                    result.burden(),
+                    result.analysed_duration / 60.0,
+                    result.episode_duration / 60.0
+                ));
+                let mut jump_to = None;
+                egui::ScrollArea::vertical()
+                    .max_height(120.0)
+                    .show(ui, |ui| {
+                        for (start, end) in result.episodes.iter() {
+                            let text = format!(
+                                "{} – {} ({:.0} s)",
+                                time_formatter.format_position(*start),
+                                time_formatter.format_position(*end),
+                                end - start
+                            );
+                            if ui.link(text).clicked() {
+                                jump_to = Some((*start, *end));
+                            }
+                        }
+                    });
+                if let Some((start, end)) = jump_to {
+                    let margin = 0.1 * (end - start);
+                    plotter.set_x_bounds(start - margin, end + margin);
+                }
+                let settings = self.settings;
+                let nrmssd: Vec<[f64; 2]> =
+                    result.windows.iter().map(|w| [w.end, w.nrmssd]).collect();
+                let entropy: Vec<[f64; 2]> =
+                    result.windows.iter().map(|w| [w.end, w.entropy]).collect();
+                let irregular: Vec<[f64; 2]> = result
+                    .windows
+                    .iter()
+                    .filter(|w| w.irregular)
+                    .map(|w| [w.end, w.nrmssd])
+                    .collect();
+                let response = Plot::new("af_screening_plot")
+                    .height(200.0)
+                    .legend(Legend::default())
+                    .x_axis_formatter(move |value, _max_chars, range| {
+                        time_formatter.format_tick(value, range)
+                    })
+                    .label_formatter(move |name, value| {
+                        format!(
+                            "{}\n{}\n{:.3}",
+                            name,
+                            time_formatter.format_position(value.x),
+                            value.y
+                        )
+                    })
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(PlotPoints::new(nrmssd)).name("normalised RMSSD"));
+                        plot_ui.line(Line::new(PlotPoints::new(entropy)).name("Shannon entropy"));
+                        plot_ui.points(
+                            Points::new(PlotPoints::new(irregular))
+                                .radius(2.5)
+                                .color(Color32::RED)
+                                .name("irregular windows"),
+                        );
+                        plot_ui.hline(
+                            HLine::new(settings.nrmssd_threshold)
+                                .style(LineStyle::dashed_loose())
+                                .name("normalised RMSSD threshold"),
+                        );
+                        plot_ui.hline(
+                            HLine::new(settings.entropy_threshold)
+                                .style(LineStyle::dashed_loose())
+                                .name("Shannon entropy threshold"),
+                        );
+                        plot_ui
+                            .response()
+                            .clicked()
+                            .then(|| plot_ui.pointer_coordinate().map(|p| p.x))
+                            .flatten()
+                    });
+                if let Some(position) = response.inner {
+                    plotter.center_on(position);
+                }
+                export_csv(ui, "af_screening.csv", || {
+                    let mut csv =
+                        "window start,window end,CoV,normalised RMSSD,Shannon entropy,random turning points,irregular\n"
+                            .to_owned();
+                    result.windows.iter().for_each(|w| {
+                        csv += &format!(
+                            "{},{},{},{},{},{},{}\n",
+                            time_formatter.format_position(w.start),
+                            time_formatter.format_position(w.end),
+                            w.cov,
+                            w.nrmssd,
+                            w.entropy,
+                            w.random,
+                            w.irregular
+                        );
+                    });
+                    csv
+                });
+            });
+        self.open = open;
+    }
+
+    fn show_settings(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+            egui::Grid::new("af_screening_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Window");
+                    ui.add(
+                        DragValue::new(&mut self.settings.window)
+                            .clamp_range(16..=512)
+                            .suffix(" RR intervals"),
+                    );
+                    ui.end_row();
+                    ui.label("Step");
+                    ui.add(
+                        DragValue::new(&mut self.settings.step)
+                            .clamp_range(1..=512)
+                            .suffix(" RR intervals"),
+                    );
+                    ui.end_row();
+                    ui.label("Normalised RMSSD above");
+                    ui.add(
+                        DragValue::new(&mut self.settings.nrmssd_threshold)
+                            .speed(0.005)
+                            .clamp_range(0.0..=1.0),
+                    );
+                    ui.end_row();
+                    ui.label("Shannon entropy above");
+                    ui.add(
+                        DragValue::new(&mut self.settings.entropy_threshold)
+                            .speed(0.005)
+                            .clamp_range(0.0..=1.0),
+                    );
+                    ui.end_row();
+                    ui.label("Shortest episode");
+                    ui.add(
+                        DragValue::new(&mut self.settings.min_episode)
+                            .clamp_range(0.0..=3600.0)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+                });
+        });
+    }
+
+    fn screen(&mut self, plotter: &mut ChannelPlotter, source: &str, (start, end): (f64, f64)) {
+        let Some(series) = plotter.corrected_rr_series(source) else {
+            return;
+        };
+        // windows with intervals left out are skipped like gaps
+        let series = series
+            .range(start, end)
+            .without(&plotter.low_quality_ranges(source));
+        let result = screen_af(&series, &self.settings);
+        self.message = result.windows.is_empty().then(|| {
+            format!(
+                "The range has less than {} RR intervals without gaps.",
+                self.settings.window
+            )
+        });
+        plotter
+            .annotations
+            .retain(|a| !(a.kind == AnnotationKind::Analysis && a.label == EPISODE_LABEL));
+        result.episodes.iter().for_each(|(start, end)| {
+            plotter.add_episode(
+                *start,
+                *end,
+                EPISODE_LABEL.to_owned(),
+                AnnotationKind::Analysis,
+            );
+        });
+        self.result = Some(result);
+    }
+}
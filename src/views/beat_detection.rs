@@ -0,0 +1,171 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use egui::Context;
+
+use crate::analysis::qrs_detection::{
+    compare_with_reference, detect_r_peaks, parse_reference_annotations, BeatList,
+    DetectionPerformance,
+};
+use crate::app::execute;
+use crate::data_structures::ChannelKind;
+use crate::views::common::select_channel;
+use crate::ChannelPlotter;
+
+/// Window to run the R peak detection on an ECG channel and to correct the beats
+pub struct BeatDetectionView {
+    pub open: bool,
+    /// label of the selected ECG channel
+    channel: Option<String>,
+    reference_channel: (Sender<String>, Receiver<String>),
+    performance: Option<DetectionPerformance>,
+    message: Option<String>,
+}
+
+impl Default for BeatDetectionView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            channel: None,
+            reference_channel: channel(),
+            performance: None,
+            message: None,
+        }
+    }
+}
+
+impl BeatDetectionView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        if let Ok(text) = self.reference_channel.1.try_recv() {
+            self.compare_with_reference(plotter, &text);
+        }
+
+        let mut open = self.open;
+        egui::Window::new("Beat detection")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let ecg_channels = plotter.channel_labels(ChannelKind::Ecg);
+                if ecg_channels.is_empty() {
+                    ui.label("Load an ECG recording first.");
+                    return;
+                }
+                select_channel(ui, "ECG channel", &mut self.channel, &ecg_channels);
+                let Some(label) = self.channel.clone() else {
+                    return;
+                };
+
+                ui.horizontal(|ui| {
+                    if ui.button("Detect R peaks").clicked() {
+                        self.detect(plotter, &label);
+                    }
+                    if ui.button("Remove beats").clicked() {
+                        plotter.beats.remove(&label);
+                        self.performance = None;
+                        self.message = None;
+                    }
+                });
+
+                let mut editing = plotter.beat_editing.as_ref() == Some(&label);
+                if ui
+                    .checkbox(&mut editing, "Edit beats with the mouse")
+                    .changed()
+                {
+                    plotter.beat_editing = editing.then(|| label.to_owned());
+                }
+                if editing {
+                    ui.label("Click: add a beat\nCTRL + click: delete a beat\nDrag: move a beat");
+                }
+
+                if let Some(beats) = plotter.beats.get(&label) {
+                    ui.label(format!("{} beats", beats.positions.len()));
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+
+                ui.separator();
+                if ui
+                    .button("Compare with reference annotations...")
+                    .on_hover_text("Beat annotations as printed by rdann (WFDB)")
+                    .clicked()
+                {
+                    let sender = self.reference_channel.0.clone();
+                    let task = rfd::AsyncFileDialog::new().pick_file();
+                    execute(async move {
+                        if let Some(filehandle) = task.await {
+                            let raw_data = filehandle.read().await;
+                            let _ = sender.send(String::from_utf8_lossy(&raw_data).to_string());
+                        }
+                    });
+                }
+                if let Some(performance) = &self.performance {
+                    egui::Grid::new("detection_performance").show(ui, |ui| {
+                        ui.label("true positives");
+                        ui.label(performance.true_positives.to_string());
+                        ui.end_row();
+                        ui.label("false positives");
+                        ui.label(performance.false_positives.to_string());
+                        ui.end_row();
+                        ui.label("false negatives");
+                        ui.label(performance.false_negatives.to_string());
+                        ui.end_row();
+                        let percent = |value: Option<f64>| {
+                            value
+                                .map(|v| format!("{:.2} %", v * 100.0))
+                                .unwrap_or("–".to_owned())
+                        };
+                        ui.label("sensitivity");
+                        ui.label(percent(performance.sensitivity()));
+                        ui.end_row();
+                        ui.label("positive predictivity");
+                        ui.label(percent(performance.positive_predictivity()));
+                        ui.end_row();
+                    });
+                }
+            });
+        self.open = open;
+    }
+
+    fn detect(&mut self, plotter: &mut ChannelPlotter, label: &str) {
+        let Some(signal) = plotter
+            .channel_by_label(label)
+            .and_then(|c| c.uniform_signal())
+        else {
+            return;
+        };
+        let positions = detect_r_peaks(&signal);
+        self.message = Some(format!(
+            "detected {} beats at {:.0} samples/s",
+            positions.len(),
+            signal.samples_per_second
+        ));
+        plotter
+            .beats
+            .insert(label.to_owned(), BeatList::new(positions));
+        self.performance = None;
+    }
+
+    fn compare_with_reference(&mut self, plotter: &mut ChannelPlotter, text: &str) {
+        let Some(label) = self.channel.clone() else {
+            return;
+        };
+        let Some(signal) = plotter
+            .channel_by_label(&label)
+            .and_then(|c| c.uniform_signal())
+        else {
+            return;
+        };
+        let reference = parse_reference_annotations(text, signal.samples_per_second, signal.start);
+        if reference.is_empty() {
+            self.message = Some("no beat annotations found in the file".to_owned());
+            return;
+        }
+        let detected = plotter
+            .beats
+            .get(&label)
+            .map(|b| b.positions.clone())
+            .unwrap_or_default();
+        // EC57 uses a matching window of 150 ms
+        self.performance = Some(compare_with_reference(&detected, &reference, 0.15));
+    }
+}
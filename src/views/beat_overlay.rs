@@ -0,0 +1,342 @@
+use std::collections::HashSet;
+
+use egui::{Color32, Context, DragValue};
+use egui_plot::{Legend, Line, Plot, PlotPoints, Polygon};
+
+use crate::analysis::beat_template::{
+    beat_template, template_correlations, AlignedBeats, BeatTemplate, TemplateKind,
+};
+use crate::data_structures::{AnalysisRange, ChannelKind};
+use crate::views::common::{export_csv, low_quality_note, select_channel, select_range};
+use crate::ChannelPlotter;
+
+/// at most this many beats of each group are drawn, evenly spread over the range
+const MAX_DRAWN_BEATS: usize = 300;
+
+/// Window overlaying the beats of an ECG aligned on the R peaks, with their template
+pub struct BeatOverlayView {
+    pub open: bool,
+    channel: Option<String>,
+    range: AnalysisRange,
+    /// window before and after the R peak in seconds
+    pre: f64,
+    post: f64,
+    template_kind: TemplateKind,
+    exclude_outliers: bool,
+    /// beats correlating less with the median template are outliers
+    min_correlation: f64,
+    beats: AlignedBeats,
+    /// correlation of each beat with the median template of all beats
+    correlations: Vec<f64>,
+    /// indices of the beats excluded or included by clicking, inverting the outlier detection
+    toggled: HashSet<usize>,
+    template: Option<BeatTemplate>,
+    /// beat clicked last
+    selected_beat: Option<usize>,
+    message: Option<String>,
+}
+
+impl Default for BeatOverlayView {
+    fn default() -> Self {
+        BeatOverlayView {
+            open: false,
+            channel: None,
+            range: AnalysisRange::Recording,
+            pre: 0.25,
+            post: 0.45,
+            template_kind: TemplateKind::Median,
+            exclude_outliers: true,
+            min_correlation: 0.9,
+            beats: AlignedBeats::default(),
+            correlations: vec![],
+            toggled: HashSet::new(),
+            template: None,
+            selected_beat: None,
+            message: None,
+        }
+    }
+}
+
+impl BeatOverlayView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Beat overlay")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let ecg_channels: Vec<String> = plotter
+                    .channel_labels(ChannelKind::Ecg)
+                    .into_iter()
+                    .filter(|label| plotter.beats.contains_key(label))
+                    .collect();
+                if ecg_channels.is_empty() {
+                    ui.label("Detect the beats of an ECG channel first.");
+                    return;
+                }
+                select_channel(ui, "ECG channel", &mut self.channel, &ecg_channels);
+                let range = select_range(ui, "beat_overlay_range", &mut self.range, plotter);
+                if let Some(label) = &self.channel {
+                    low_quality_note(ui, plotter, label);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Window");
+                    ui.add(
+                        DragValue::new(&mut self.pre)
+                            .speed(0.01)
+                            .clamp_range(0.05..=2.0)
+                            .suffix(" s before"),
+                    );
+                    ui.add(
+                        DragValue::new(&mut self.post)
+                            .speed(0.01)
+                            .clamp_range(0.05..=2.0)
+                            .suffix(" s after the R peak"),
+                    );
+                });
+                if ui
+                    .add_enabled(range.is_some(), egui::Button::new("Align beats"))
+                    .clicked()
+                {
+                    if let (Some(label), Some(range)) = (self.channel.clone(), range) {
+                        self.align(plotter, &label, range);
+                    }
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                if self.beats.is_empty() {
+                    return;
+                }
+                ui.separator();
+
+                let mut changed = false;
+                ui.horizontal(|ui| {
+                    for kind in [TemplateKind::Median, TemplateKind::Average] {
+                        changed |= ui
+                            .radio_value(&mut self.template_kind, kind, kind.label())
+                            .changed();
+                    }
+                });
+                ui.horizontal(|ui| {
+                    changed |= ui
+                        .checkbox(
+                            &mut self.exclude_outliers,
+                            "Exclude beats correlating below",
+                        )
+                        .changed();
+                    let correlation = ui.add_enabled(
+                        self.exclude_outliers,
+                        DragValue::new(&mut self.min_correlation)
+                            .speed(0.005)
+                            .clamp_range(0.0..=1.0),
+                    );
+                    changed |= correlation.changed();
+                });
+                if ui.button("Include all beats").clicked() {
+                    self.exclude_outliers = false;
+                    self.toggled.clear();
+                    changed = true;
+                }
+                if changed {
+                    self.update_template();
+                }
+                let n_excluded = (0..self.beats.len())
+                    .filter(|idx| !self.is_included(*idx))
+                    .count();
+                ui.label(format!(
+                    "{} beats, {} excluded. Click a beat to exclude or include it.",
+                    self.beats.len(),
+                    n_excluded
+                ));
+                if let Some(idx) = self.selected_beat {
+                    let position = self.beats.positions[idx];
+                    ui.horizontal(|ui| {
+                        ui.label(format!(
+                            "Beat at {} ({})",
+                            plotter.time_formatter().format_position(position),
+                            if self.is_included(idx) {
+                                "included"
+                            } else {
+                                "excluded"
+                            }
+                        ));
+                        if ui.button("Show in recording").clicked() {
+                            plotter.set_x_bounds(position - 2.5, position + 2.5);
+                        }
+                    });
+                }
+                self.show_plot(ui);
+                export_csv(ui, "beat_template.csv", || self.to_csv());
+            });
+        self.open = open;
+    }
+
+    fn is_included(&self, idx: usize) -> bool {
+        let outlier = self.exclude_outliers
+            && self
+                .correlations
+                .get(idx)
+                .is_some_and(|c| *c < self.min_correlation);
+        outlier == self.toggled.contains(&idx)
+    }
+
+    fn align(&mut self, plotter: &mut ChannelPlotter, label: &str, (start, end): (f64, f64)) {
+        let low_quality = plotter.low_quality_ranges(label);
+        let positions: Vec<f64> = plotter
+            .beats
+            .get(label)
+            .map(|beats| {
+                beats
+                    .positions
+                    .iter()
+                    .copied()
+                    .filter(|p| *p >= start && *p <= end)
+                    .filter(|p| !low_quality.iter().any(|(s, e)| p >= s && p <= e))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let signal = plotter
+            .channel_by_label(label)
+            .and_then(|channel| channel.uniform_signal());
+        self.beats = signal
+            .map(|signal| AlignedBeats::new(&signal, &positions, self.pre, self.post))
+            .unwrap_or_default();
+        self.correlations = template_correlations(&self.beats);
+        self.toggled.clear();
+        self.selected_beat = None;
+        self.message = self
+            .beats
+            .is_empty()
+            .then(|| "No complete beat windows in the range.".to_owned());
+        self.update_template();
+    }
+
+    fn update_template(&mut self) {
+        let included: Vec<bool> = (0..self.beats.len())
+            .map(|idx| self.is_included(idx))
+            .collect();
+        self.template = beat_template(&self.beats, &included, self.template_kind);
+    }
+
+    fn show_plot(&mut self, ui: &mut egui::Ui) {
+        let offsets = &self.beats.offsets;
+        let (included, excluded): (Vec<usize>, Vec<usize>) =
+            (0..self.beats.len()).partition(|idx| self.is_included(*idx));
+        // evenly spread subset of the beats, so long recordings stay responsive
+        let drawn = |indices: &[usize]| -> Vec<usize> {
+            let step = (indices.len() / MAX_DRAWN_BEATS).max(1);
+            indices.iter().copied().step_by(step).collect()
+        };
+        let (drawn_included, drawn_excluded) = (drawn(&included), drawn(&excluded));
+        let beat_line = |idx: usize| -> PlotPoints {
+            offsets
+                .iter()
+                .zip(self.beats.windows[idx].iter())
+                .map(|(x, y)| [x * 1E3, *y])
+                .collect()
+        };
+
+        let response = Plot::new("beat_overlay_plot")
+            .height(300.0)
+            .legend(Legend::default())
+            .x_axis_label("ms from R peak")
+            .show(ui, |plot_ui| {
+                for idx in drawn_included.iter() {
+                    plot_ui.line(
+                        Line::new(beat_line(*idx))
+                            .color(Color32::GRAY.gamma_multiply(0.3))
+                            .width(1.0),
+                    );
+                }
+                for idx in drawn_excluded.iter() {
+                    plot_ui.line(
+                        Line::new(beat_line(*idx))
+                            .color(Color32::RED.gamma_multiply(0.4))
+                            .width(1.0),
+                    );
+                }
+                if let Some(template) = &self.template {
+                    // the band is drawn as quads, polygons are only filled correctly if convex
+                    for i in 1..template.offsets.len() {
+                        let (x0, x1) = (template.offsets[i - 1] * 1E3, template.offsets[i] * 1E3);
+                        plot_ui.polygon(
+                            Polygon::new(PlotPoints::new(vec![
+                                [x0, template.lower[i - 1]],
+                                [x1, template.lower[i]],
+                                [x1, template.upper[i]],
+                                [x0, template.upper[i - 1]],
+                            ]))
+                            .fill_color(Color32::from_rgba_unmultiplied(100, 150, 250, 60))
+                            .stroke(egui::Stroke::NONE),
+                        );
+                    }
+                    plot_ui.line(
+                        Line::new(
+                            template
+                                .offsets
+                                .iter()
+                                .zip(template.values.iter())
+                                .map(|(x, y)| [x * 1E3, *y])
+                                .collect::<PlotPoints>(),
+                        )
+                        .color(Color32::LIGHT_BLUE)
+                        .width(3.0)
+                        .name(format!(
+                            "{} of {} beats",
+                            self.template_kind.label(),
+                            template.n_beats
+                        )),
+                    );
+                }
+                if let Some(idx) = self.selected_beat {
+                    plot_ui.line(
+                        Line::new(beat_line(idx))
+                            .color(Color32::GOLD)
+                            .width(2.0)
+                            .name("selected beat"),
+                    );
+                }
+                if !plot_ui.response().clicked() {
+                    return None;
+                }
+                // the beat passing closest to the pointer, within 8 pixels
+                let pointer = plot_ui.pointer_coordinate()?;
+                let tolerance = 8.0 * plot_ui.transform().dvalue_dpos()[1].abs();
+                let sample = offsets.partition_point(|x| x * 1E3 < pointer.x);
+                drawn_included
+                    .iter()
+                    .chain(drawn_excluded.iter())
+                    .filter_map(|idx| {
+                        let y = self.beats.windows[*idx].get(sample)?;
+                        Some((*idx, (y - pointer.y).abs()))
+                    })
+                    .filter(|(_, distance)| *distance < tolerance)
+                    .min_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(idx, _)| idx)
+            });
+        if let Some(idx) = response.inner {
+            if !self.toggled.remove(&idx) {
+                self.toggled.insert(idx);
+            }
+            self.selected_beat = Some(idx);
+            self.update_template();
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let mut csv =
+            "offset [ms],template,lower (2.5th percentile),upper (97.5th percentile)\n".to_owned();
+        if let Some(template) = &self.template {
+            for i in 0..template.offsets.len() {
+                csv += &format!(
+                    "{},{},{},{}\n",
+                    template.offsets[i] * 1E3,
+                    template.values[i],
+                    template.lower[i],
+                    template.upper[i]
+                );
+            }
+        }
+        csv
+    }
+}
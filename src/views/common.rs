@@ -0,0 +1,124 @@
+use egui::Ui;
+
+use crate::data_structures::{AnalysisRange, AnnotationKind};
+use crate::ChannelPlotter;
+
+/// Combo box to choose one of `labels`, selects the first one if the selection isn't available
+pub fn select_channel(ui: &mut Ui, label: &str, selected: &mut Option<String>, labels: &[String]) {
+    if !selected.as_ref().is_some_and(|c| labels.contains(c)) {
+        *selected = labels.first().cloned();
+    }
+    egui::ComboBox::from_label(label)
+        .selected_text(selected.clone().unwrap_or_default())
+        .show_ui(ui, |ui| {
+            for channel in labels.iter() {
+                ui.selectable_value(selected, Some(channel.to_owned()), channel);
+            }
+        });
+}
+
+/// Combo box to choose the range an analysis is calculated for,
+/// returns the start and end of the range if it exists
+pub fn select_range(
+    ui: &mut Ui,
+    id: &str,
+    selected: &mut AnalysisRange,
+    plotter: &mut ChannelPlotter,
+) -> Option<(f64, f64)> {
+    egui::ComboBox::new(id, "Range")
+        .selected_text(selected.label())
+        .show_ui(ui, |ui| {
+            for range in [
+                AnalysisRange::Recording,
+                AnalysisRange::Visible,
+                AnalysisRange::Selection,
+            ] {
+                ui.selectable_value(selected, range, range.label());
+            }
+        });
+    if *selected == AnalysisRange::Selection {
+        select_saved_range(ui, id, plotter);
+    }
+    let range = plotter.analysis_range(*selected);
+    if range.is_none() && *selected == AnalysisRange::Selection {
+        ui.label("Select a range with SHIFT + drag in the plot.");
+    }
+    range
+}
+
+/// Combo box to make one of the named ranges the selection
+pub fn select_saved_range(ui: &mut Ui, id: &str, plotter: &mut ChannelPlotter) {
+    let ranges: Vec<(String, (f64, f64))> = plotter
+        .annotations
+        .iter()
+        .filter(|a| a.kind == AnnotationKind::NamedRange)
+        .filter_map(|a| Some((a.label.to_owned(), (a.position, a.end?))))
+        .collect();
+    if ranges.is_empty() {
+        return;
+    }
+    let selected = ranges
+        .iter()
+        .find(|(_, range)| Some(*range) == plotter.selection)
+        .map(|(label, _)| label.to_owned())
+        .unwrap_or_default();
+    egui::ComboBox::new(format!("{id}_saved"), "Named range")
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            for (label, range) in ranges {
+                if ui
+                    .selectable_label(Some(range) == plotter.selection, label)
+                    .clicked()
+                {
+                    plotter.selection = Some(range);
+                }
+            }
+        });
+}
+
+/// Note that the unusable windows of the channel's signal quality are left out, if there are any
+pub fn low_quality_note(ui: &mut Ui, plotter: &ChannelPlotter, label: &str) {
+    let ranges = plotter.low_quality_ranges(label);
+    if !ranges.is_empty() {
+        let duration: f64 = ranges.iter().map(|(start, end)| end - start).sum();
+        ui.label(format!(
+            "{:.0} s of unusable signal quality are left out.",
+            duration
+        ));
+    }
+}
+
+/// Buttons to copy `csv` to the clipboard and (on native platforms) to save it to a file
+pub fn export_csv(ui: &mut Ui, file_name: &str, csv: impl FnOnce() -> String) {
+    ui.horizontal(|ui| {
+        let copy = ui.button("Copy as CSV").clicked();
+        #[cfg(not(target_arch = "wasm32"))]
+        let save = ui.button("Save as CSV...").clicked();
+        #[cfg(target_arch = "wasm32")]
+        let save = {
+            let _ = file_name;
+            false
+        };
+        if !copy && !save {
+            return;
+        }
+        let csv = csv();
+        if copy {
+            ui.output_mut(|o| o.copied_text = csv.to_owned());
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if save {
+            let task = rfd::AsyncFileDialog::new()
+                .add_filter("CSV", &["csv"])
+                .set_file_name(file_name)
+                .save_file();
+            crate::app::execute(async move {
+                if let Some(filehandle) = task.await {
+                    if let Err(e) = std::fs::write(filehandle.path(), csv) {
+                        log::error!("could not save {}: {}", filehandle.path().display(), e);
+                    }
+                }
+            });
+        }
+    });
+}
@@ -0,0 +1,123 @@
+use egui::{Context, DragValue};
+
+use crate::analysis::resample::ChannelGaps;
+use crate::views::common::export_csv;
+use crate::ChannelPlotter;
+
+/// Window reporting the gaps (dropouts) of every channel, e.g. from lost Bluetooth packets
+#[derive(Default)]
+pub struct DataQualityView {
+    pub open: bool,
+}
+
+impl DataQualityView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Data quality")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Gap longer than");
+                    ui.add(
+                        DragValue::new(&mut plotter.gap_factor)
+                            .speed(0.1)
+                            .clamp_range(1.5..=100.0)
+                            .suffix(" × the median sample interval"),
+                    );
+                });
+                ui.checkbox(&mut plotter.show_gaps, "Shade the gaps in the plots");
+                let reports: Vec<(String, ChannelGaps)> = plotter
+                    .channels
+                    .iter_mut()
+                    .map(|c| c.get_label())
+                    .collect::<Vec<String>>()
+                    .into_iter()
+                    .filter_map(|label| {
+                        let gaps = plotter.channel_gaps(&label)?.clone();
+                        Some((label, gaps))
+                    })
+                    .collect();
+                if reports.is_empty() {
+                    ui.label("No channels loaded.");
+                    return;
+                }
+                ui.separator();
+                let time_formatter = plotter.time_formatter();
+                egui::Grid::new("data_quality_summary")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for heading in ["channel", "sample rate", "gaps", "missing"] {
+                            ui.strong(heading);
+                        }
+                        ui.end_row();
+                        for (label, gaps) in reports.iter() {
+                            ui.label(label);
+                            ui.monospace(
+                                gaps.samples_per_second
+                                    .map(|rate| format!("{:.2} Hz", rate))
+                                    .unwrap_or("–".to_owned()),
+                            );
+                            ui.monospace(gaps.gaps.len().to_string());
+                            let duration = gaps.x_range.map(|(start, end)| end - start);
+                            ui.monospace(match duration {
+                                Some(duration) if duration > 0.0 => format!(
+                                    "{:.1} s ({:.2} %)",
+                                    gaps.missing(),
+                                    gaps.missing() / duration * 100.0
+                                ),
+                                _ => format!("{:.1} s", gaps.missing()),
+                            });
+                            ui.end_row();
+                        }
+                    });
+                let mut jump_to = None;
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        for (label, gaps) in reports.iter().filter(|(_, g)| !g.gaps.is_empty()) {
+                            egui::CollapsingHeader::new(format!(
+                                "{} ({} gaps)",
+                                label,
+                                gaps.gaps.len()
+                            ))
+                            .show(ui, |ui| {
+                                for (start, end) in gaps.gaps.iter() {
+                                    let text = format!(
+                                        "{} – {} ({:.2} s)",
+                                        time_formatter.format_position(*start),
+                                        time_formatter.format_position(*end),
+                                        end - start
+                                    );
+                                    if ui.link(text).clicked() {
+                                        jump_to = Some((*start, *end));
+                                    }
+                                }
+                            });
+                        }
+                    });
+                if let Some((start, end)) = jump_to {
+                    // show the gap with some data on both sides
+                    let margin = (end - start).max(1.0);
+                    plotter.set_x_bounds(start - margin, end + margin);
+                }
+                export_csv(ui, "gaps.csv", || {
+                    let mut csv = "channel,gap start,gap end,duration [s]\n".to_owned();
+                    for (label, gaps) in reports.iter() {
+                        for (start, end) in gaps.gaps.iter() {
+                            csv += &format!(
+                                "\"{}\",{},{},{}\n",
+                                label,
+                                time_formatter.format_position(*start),
+                                time_formatter.format_position(*end),
+                                end - start
+                            );
+                        }
+                    }
+                    csv
+                });
+            });
+        self.open = open;
+    }
+}
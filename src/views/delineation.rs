@@ -0,0 +1,328 @@
+use egui::{Color32, Context};
+use egui_plot::{Legend, Line, LineStyle, Plot, PlotPoints, Points, VLine};
+
+use crate::analysis::beat_template::{beat_template, AlignedBeats, BeatTemplate, TemplateKind};
+use crate::analysis::delineation::{
+    beat_intervals, delineate_beats, delineate_template, Fiducials, Intervals, QtcFormula, Wave,
+};
+use crate::data_structures::{AnalysisRange, ChannelKind};
+use crate::time_format::TimeFormatter;
+use crate::views::common::{export_csv, low_quality_note, select_channel, select_range};
+use crate::ChannelPlotter;
+
+/// window around the R peak of the median beat in seconds
+const TEMPLATE_PRE: f64 = 0.35;
+const TEMPLATE_POST: f64 = 0.65;
+
+/// beats in the moving median of the QTc trend
+const TREND_WINDOW: usize = 30;
+
+/// Window to delineate the waves of an ECG and show the PR, QRS and QT intervals
+pub struct DelineationView {
+    pub open: bool,
+    channel: Option<String>,
+    range: AnalysisRange,
+    /// formula of the QTc trend
+    formula: QtcFormula,
+    /// median beat of the range and its wave boundaries relative to the R peak
+    median_beat: Option<(BeatTemplate, Fiducials, Intervals)>,
+    message: Option<String>,
+}
+
+impl Default for DelineationView {
+    fn default() -> Self {
+        DelineationView {
+            open: false,
+            channel: None,
+            range: AnalysisRange::Recording,
+            formula: QtcFormula::Fridericia,
+            median_beat: None,
+            message: None,
+        }
+    }
+}
+
+impl DelineationView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("ECG intervals")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let ecg_channels: Vec<String> = plotter
+                    .channel_labels(ChannelKind::Ecg)
+                    .into_iter()
+                    .filter(|label| plotter.beats.contains_key(label))
+                    .collect();
+                if ecg_channels.is_empty() {
+                    ui.label("Detect the beats of an ECG channel first.");
+                    return;
+                }
+                select_channel(ui, "ECG channel", &mut self.channel, &ecg_channels);
+                let range = select_range(ui, "delineation_range", &mut self.range, plotter);
+                let (Some(label), Some(range)) = (self.channel.clone(), range) else {
+                    return;
+                };
+                low_quality_note(ui, plotter, &label);
+                if ui
+                    .button("Delineate")
+                    .on_hover_text("Find the wave boundaries of all beats, replacing moved ones")
+                    .clicked()
+                {
+                    self.delineate(plotter, &label, range);
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let low_quality = plotter.low_quality_ranges(&label);
+                let Some(fiducials) = plotter.fiducials.get(&label) else {
+                    return;
+                };
+                let intervals: Vec<(f64, Intervals)> = fiducials
+                    .iter()
+                    .zip(beat_intervals(fiducials))
+                    .filter(|(f, _)| f.r_peak >= range.0 && f.r_peak <= range.1)
+                    .filter(|(f, _)| {
+                        !low_quality
+                            .iter()
+                            .any(|(s, e)| f.r_peak >= *s && f.r_peak <= *e)
+                    })
+                    .map(|(f, intervals)| (f.r_peak, intervals))
+                    .collect();
+
+                let mut editing = plotter.fiducial_editing.as_ref() == Some(&label);
+                if ui
+                    .checkbox(&mut editing, "Move the wave boundaries by dragging")
+                    .changed()
+                {
+                    plotter.fiducial_editing = editing.then(|| label.to_owned());
+                    if editing {
+                        plotter.beat_editing = None;
+                    }
+                }
+                ui.separator();
+
+                self.show_table(ui, &intervals);
+                ui.separator();
+                self.show_median_beat(ui);
+                ui.separator();
+                let time_formatter = plotter.time_formatter();
+                if let Some(position) = self.show_trend(ui, &intervals, time_formatter) {
+                    plotter.set_x_bounds(position - 5.0, position + 5.0);
+                }
+                export_csv(ui, "ecg_intervals.csv", || {
+                    to_csv(&intervals, &time_formatter)
+                });
+            });
+        self.open = open;
+    }
+
+    fn delineate(&mut self, plotter: &mut ChannelPlotter, label: &str, (start, end): (f64, f64)) {
+        let beats = plotter
+            .beats
+            .get(label)
+            .map(|b| b.positions.clone())
+            .unwrap_or_default();
+        let Some(signal) = plotter
+            .channel_by_label(label)
+            .and_then(|channel| channel.uniform_signal())
+        else {
+            self.message = Some("The channel has too few samples.".to_owned());
+            return;
+        };
+        plotter
+            .fiducials
+            .insert(label.to_owned(), delineate_beats(&signal, &beats));
+
+        let low_quality = plotter.low_quality_ranges(label);
+        let in_range: Vec<f64> = beats
+            .iter()
+            .copied()
+            .filter(|p| *p >= start && *p <= end)
+            .filter(|p| !low_quality.iter().any(|(s, e)| p >= s && p <= e))
+            .collect();
+        let aligned = AlignedBeats::new(&signal, &in_range, TEMPLATE_PRE, TEMPLATE_POST);
+        let mut rr: Vec<f64> = in_range.windows(2).map(|w| w[1] - w[0]).collect();
+        rr.sort_by(|a, b| a.total_cmp(b));
+        let median_rr = rr.get(rr.len() / 2).copied();
+        self.median_beat =
+            beat_template(&aligned, &vec![true; aligned.len()], TemplateKind::Median)
+                .zip(median_rr)
+                .and_then(|(template, rr)| {
+                    let fiducials = delineate_template(&template, rr)?;
+                    Some((template, fiducials, fiducials.intervals(Some(rr))))
+                });
+        self.message = None;
+    }
+
+    fn show_table(&self, ui: &mut egui::Ui, intervals: &[(f64, Intervals)]) {
+        let format = |value: Option<f64>| match value {
+            Some(value) => format!("{:.0}", value * 1E3),
+            None => "–".to_owned(),
+        };
+        let median_beat = self
+            .median_beat
+            .as_ref()
+            .map(|(_, _, intervals)| measures(intervals));
+        let beats: Vec<[Option<f64>; 8]> = intervals.iter().map(|(_, i)| measures(i)).collect();
+        egui::Grid::new("delineation_grid")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.strong("");
+                ui.strong("median beat");
+                ui.strong("median of the beats");
+                ui.strong("");
+                ui.end_row();
+                for (idx, name) in MEASURES.iter().enumerate() {
+                    let mut values: Vec<f64> = beats.iter().filter_map(|m| m[idx]).collect();
+                    values.sort_by(|a, b| a.total_cmp(b));
+                    ui.label(*name);
+                    ui.monospace(format(median_beat.and_then(|m| m[idx])));
+                    ui.monospace(format(values.get(values.len() / 2).copied()));
+                    ui.label("ms");
+                    ui.end_row();
+                }
+            });
+        ui.label(format!("{} beats in the range", intervals.len()));
+    }
+
+    fn show_median_beat(&self, ui: &mut egui::Ui) {
+        let Some((template, fiducials, _)) = &self.median_beat else {
+            return;
+        };
+        ui.label(format!("Median beat of {} beats", template.n_beats));
+        Plot::new("median_beat_plot")
+            .height(200.0)
+            .legend(Legend::default())
+            .x_axis_label("ms from R peak")
+            .show(ui, |plot_ui| {
+                plot_ui.line(
+                    Line::new(
+                        template
+                            .offsets
+                            .iter()
+                            .zip(template.values.iter())
+                            .map(|(x, y)| [x * 1E3, *y])
+                            .collect::<PlotPoints>(),
+                    )
+                    .width(2.0)
+                    .name("median beat"),
+                );
+                for wave in Wave::ALL {
+                    if let Some(position) = fiducials.get(wave) {
+                        plot_ui.vline(
+                            VLine::new(position * 1E3)
+                                .style(LineStyle::dashed_loose())
+                                .name(wave.label()),
+                        );
+                    }
+                }
+            });
+    }
+
+    /// Plot of the QTc of each beat over time, returns the position of a clicked beat
+    fn show_trend(
+        &mut self,
+        ui: &mut egui::Ui,
+        intervals: &[(f64, Intervals)],
+        time_formatter: TimeFormatter,
+    ) -> Option<f64> {
+        egui::ComboBox::from_label("QTc trend")
+            .selected_text(self.formula.label())
+            .show_ui(ui, |ui| {
+                for formula in QtcFormula::ALL {
+                    ui.selectable_value(&mut self.formula, formula, formula.label());
+                }
+            });
+        let points: Vec<[f64; 2]> = intervals
+            .iter()
+            .filter_map(|(position, i)| Some([*position, i.qtc(self.formula)? * 1E3]))
+            .collect();
+        let trend: Vec<[f64; 2]> = points
+            .windows(TREND_WINDOW.min(points.len()).max(1))
+            .map(|window| {
+                let mut values: Vec<f64> = window.iter().map(|p| p[1]).collect();
+                values.sort_by(|a, b| a.total_cmp(b));
+                [window[window.len() / 2][0], values[values.len() / 2]]
+            })
+            .collect();
+        Plot::new("qtc_trend_plot")
+            .height(150.0)
+            .legend(Legend::default())
+            .y_axis_label("QTc [ms]")
+            .x_axis_formatter(move |value, _max_chars, range| {
+                time_formatter.format_tick(value, range)
+            })
+            .label_formatter(move |_name, value| {
+                format!(
+                    "{}\n{:.0} ms",
+                    time_formatter.format_position(value.x),
+                    value.y
+                )
+            })
+            .show(ui, |plot_ui| {
+                plot_ui.points(
+                    Points::new(PlotPoints::new(points))
+                        .radius(1.5)
+                        .color(Color32::GRAY)
+                        .name("beats"),
+                );
+                plot_ui.line(
+                    Line::new(PlotPoints::new(trend))
+                        .width(2.0)
+                        .name(format!("median of {} beats", TREND_WINDOW)),
+                );
+                plot_ui
+                    .response()
+                    .clicked()
+                    .then(|| plot_ui.pointer_coordinate().map(|p| p.x))
+                    .flatten()
+            })
+            .inner
+    }
+}
+
+const MEASURES: [&str; 8] = [
+    "RR",
+    "PR",
+    "QRS",
+    "QT",
+    "QTc Bazett",
+    "QTc Fridericia",
+    "QTc Framingham",
+    "QTc Hodges",
+];
+
+/// The intervals in the order of `MEASURES`
+fn measures(intervals: &Intervals) -> [Option<f64>; 8] {
+    [
+        intervals.rr,
+        intervals.pr,
+        intervals.qrs,
+        intervals.qt,
+        intervals.qtc(QtcFormula::Bazett),
+        intervals.qtc(QtcFormula::Fridericia),
+        intervals.qtc(QtcFormula::Framingham),
+        intervals.qtc(QtcFormula::Hodges),
+    ]
+}
+
+fn to_csv(intervals: &[(f64, Intervals)], time_formatter: &TimeFormatter) -> String {
+    let mut csv = "time".to_owned();
+    for name in MEASURES {
+        csv += &format!(",{} [ms]", name);
+    }
+    csv += "\n";
+    for (position, i) in intervals {
+        csv += &time_formatter.format_position(*position);
+        for value in measures(i) {
+            csv += &format!(
+                ",{}",
+                value.map(|v| format!("{:.1}", v * 1E3)).unwrap_or_default()
+            );
+        }
+        csv += "\n";
+    }
+    csv
+}
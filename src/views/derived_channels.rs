@@ -0,0 +1,129 @@
+use egui::{Color32, Context};
+
+use crate::analysis::expression::{DerivedChannel, SYNTAX_HELP};
+use crate::ChannelPlotter;
+
+/// Window to define channels calculated from other channels with formulas
+pub struct DerivedChannelsView {
+    pub open: bool,
+    name: String,
+    unit: String,
+    formula: String,
+    message: Option<String>,
+}
+
+impl Default for DerivedChannelsView {
+    fn default() -> Self {
+        DerivedChannelsView {
+            open: false,
+            name: "Magnitude".to_owned(),
+            unit: "g".to_owned(),
+            formula: "sqrt(X^2 + Y^2 + Z^2)".to_owned(),
+            message: None,
+        }
+    }
+}
+
+impl DerivedChannelsView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Derived channels")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let mut edit = None;
+                let mut remove = None;
+                egui::Grid::new("derived_channels")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for channel in plotter.derived_channels.iter() {
+                            ui.label(format!("{} [{}]", channel.name, channel.unit));
+                            match &channel.error {
+                                Some(error) => ui
+                                    .colored_label(Color32::RED, &channel.formula)
+                                    .on_hover_text(error),
+                                None => ui.monospace(&channel.formula),
+                            };
+                            ui.horizontal(|ui| {
+                                if ui.small_button("Edit").clicked() {
+                                    edit = Some(channel.clone());
+                                }
+                                if ui.small_button("Remove").clicked() {
+                                    remove = Some(channel.name.clone());
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+                if let Some(channel) = edit {
+                    self.name = channel.name;
+                    self.unit = channel.unit;
+                    self.formula = channel.formula;
+                }
+                if let Some(name) = remove {
+                    plotter.remove_derived_channel(&name);
+                }
+                ui.separator();
+
+                egui::Grid::new("derived_channel_editor")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.end_row();
+                        ui.label("Unit");
+                        ui.text_edit_singleline(&mut self.unit);
+                        ui.end_row();
+                        ui.label("Formula");
+                        ui.text_edit_singleline(&mut self.formula)
+                            .on_hover_text(SYNTAX_HELP);
+                        ui.end_row();
+                    });
+                let labels: Vec<String> =
+                    plotter.channels.iter_mut().map(|c| c.get_label()).collect();
+                ui.menu_button("Insert channel", |ui| {
+                    for label in labels {
+                        if ui.button(&label).clicked() {
+                            self.formula += &format!("\"{label}\"");
+                            ui.close_menu();
+                        }
+                    }
+                });
+                let exists = plotter
+                    .derived_channels
+                    .iter()
+                    .any(|c| c.name == self.name.trim());
+                let button = if exists {
+                    "Update channel"
+                } else {
+                    "Add channel"
+                };
+                if ui
+                    .add_enabled(!self.name.trim().is_empty(), egui::Button::new(button))
+                    .clicked()
+                {
+                    self.message = plotter
+                        .set_derived_channel(DerivedChannel {
+                            name: self.name.trim().to_owned(),
+                            unit: self.unit.trim().to_owned(),
+                            formula: self.formula.clone(),
+                            error: None,
+                        })
+                        .err()
+                        .map(|e| e.to_string());
+                }
+                if let Some(message) = &self.message {
+                    ui.colored_label(Color32::RED, message);
+                }
+                ui.label(
+                    "The formulas are saved and recalculated whenever their channels are \
+                     loaded or their filters change.",
+                );
+                egui::CollapsingHeader::new("Syntax").show(ui, |ui| {
+                    ui.label(SYNTAX_HELP);
+                });
+            });
+        self.open = open;
+    }
+}
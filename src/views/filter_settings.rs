@@ -0,0 +1,98 @@
+use egui::{DragValue, Ui};
+
+use crate::analysis::filters::{FilterDisplay, FilterSettings};
+
+/// Editor for the filter chain of a channel, returns true if the settings changed
+pub fn show_filter_settings(ui: &mut Ui, settings: &mut FilterSettings) -> bool {
+    let before = settings.clone();
+    ui.checkbox(&mut settings.enabled, "Filter");
+    ui.add_enabled_ui(settings.enabled, |ui| {
+        optional_value(
+            ui,
+            "Moving median",
+            &mut settings.moving_median,
+            0.02,
+            0.002..=1.0,
+            " s",
+        );
+        optional_value(
+            ui,
+            "High-pass (baseline)",
+            &mut settings.high_pass,
+            0.5,
+            0.05..=10.0,
+            " Hz",
+        );
+        ui.horizontal(|ui| {
+            let mut enabled = settings.notch.is_some();
+            ui.checkbox(&mut enabled, "Notch (mains)");
+            let mut frequency = settings.notch.unwrap_or(50.0);
+            ui.radio_value(&mut frequency, 50.0, "50 Hz");
+            ui.radio_value(&mut frequency, 60.0, "60 Hz");
+            settings.notch = enabled.then_some(frequency);
+        });
+        ui.horizontal(|ui| {
+            let mut enabled = settings.band_pass.is_some();
+            ui.checkbox(&mut enabled, "Band-pass");
+            let (mut low, mut high) = settings.band_pass.unwrap_or((0.5, 40.0));
+            ui.add_enabled(
+                enabled,
+                DragValue::new(&mut low).speed(0.1).clamp_range(0.05..=high),
+            );
+            ui.label("–");
+            ui.add_enabled(
+                enabled,
+                DragValue::new(&mut high)
+                    .speed(0.1)
+                    .clamp_range(low..=500.0)
+                    .suffix(" Hz"),
+            );
+            settings.band_pass = enabled.then_some((low, high));
+        });
+        optional_value(
+            ui,
+            "Low-pass",
+            &mut settings.low_pass,
+            40.0,
+            1.0..=500.0,
+            " Hz",
+        );
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut settings.display,
+                FilterDisplay::Replace,
+                "Filtered only",
+            );
+            ui.radio_value(
+                &mut settings.display,
+                FilterDisplay::Overlay,
+                "Over raw signal",
+            );
+        });
+    });
+    *settings != before
+}
+
+/// Checkbox and value for a filter stage which is used if the value is set
+fn optional_value(
+    ui: &mut Ui,
+    label: &str,
+    value: &mut Option<f64>,
+    default: f64,
+    range: std::ops::RangeInclusive<f64>,
+    suffix: &str,
+) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        ui.checkbox(&mut enabled, label);
+        let mut v = value.unwrap_or(default);
+        ui.add_enabled(
+            enabled,
+            DragValue::new(&mut v)
+                .speed(default / 20.0)
+                .clamp_range(range)
+                .suffix(suffix),
+        );
+        *value = enabled.then_some(v);
+    });
+}
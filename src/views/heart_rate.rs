@@ -0,0 +1,365 @@
+use egui::{Color32, Context, DragValue};
+use egui_plot::{HLine, Line, LineStyle, Plot, PlotPoints, VLine};
+
+use crate::analysis::heart_rate::{
+    recovery, time_in_zones, window_statistics, HeartRateWindow, HeartRateZones, Recovery,
+};
+use crate::analysis::resample::median_sample_rate;
+use crate::data_structures::{AnalysisRange, Annotation, ChannelKind};
+use crate::time_format::TimeFormatter;
+use crate::views::common::{export_csv, select_channel, select_range};
+use crate::ChannelPlotter;
+
+/// colors of the heart rate below the zones and in zone 1 to 5
+const ZONE_COLORS: [Color32; 6] = [
+    Color32::GRAY,
+    Color32::from_rgb(100, 160, 230),
+    Color32::from_rgb(80, 180, 80),
+    Color32::from_rgb(230, 200, 40),
+    Color32::from_rgb(240, 130, 30),
+    Color32::from_rgb(220, 40, 40),
+];
+
+/// Heart rate statistics of a range
+struct HeartRateTrend {
+    points: Vec<(f64, f64)>,
+    windows: Vec<HeartRateWindow>,
+    /// seconds below the zones and in zone 1 to 5
+    zone_times: [f64; 6],
+    /// the zones the times were calculated for
+    zones: HeartRateZones,
+    /// the gap factor the times were calculated with
+    gap_factor: f64,
+    recovery: Option<Recovery>,
+}
+
+/// Window with the trend, zones and recovery of a heart rate channel
+pub struct HeartRateView {
+    pub open: bool,
+    channel: Option<String>,
+    range: AnalysisRange,
+    zones: HeartRateZones,
+    /// length of the windows of the minimum, mean and maximum in seconds
+    window: f64,
+    /// position of the annotation marking the peak, None for the highest heart rate
+    peak_mark: Option<f64>,
+    result: Option<HeartRateTrend>,
+    message: Option<String>,
+}
+
+impl Default for HeartRateView {
+    fn default() -> Self {
+        HeartRateView {
+            open: false,
+            channel: None,
+            range: AnalysisRange::Recording,
+            zones: HeartRateZones::default(),
+            window: 60.0,
+            peak_mark: None,
+            result: None,
+            message: None,
+        }
+    }
+}
+
+impl HeartRateView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Heart rate trend")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let labels = plotter.channel_labels(ChannelKind::HeartRate);
+                if labels.is_empty() {
+                    ui.label(
+                        "Load a heart rate file or derive the heart rate from the beats of an \
+                         ECG first.",
+                    );
+                    return;
+                }
+                select_channel(ui, "Heart rate", &mut self.channel, &labels);
+                let range = select_range(ui, "heart_rate_range", &mut self.range, plotter);
+                let time_formatter = plotter.time_formatter();
+                self.show_settings(ui, &plotter.annotations, time_formatter);
+                let (Some(label), Some(range)) = (self.channel.clone(), range) else {
+                    return;
+                };
+                if ui.button("Calculate").clicked() {
+                    self.calculate(plotter, &label, range);
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let Some(result) = &self.result else {
+                    return;
+                };
+                ui.separator();
+                show_summary(ui, result, &time_formatter);
+                let response = plot_trend(ui, result, time_formatter);
+                if let Some(position) = response {
+                    plotter.center_on(position);
+                }
+                export_csv(ui, "heart_rate_trend.csv", || {
+                    let mut csv =
+                        "window start,window end,min [bpm],mean [bpm],max [bpm]\n".to_owned();
+                    result.windows.iter().for_each(|w| {
+                        csv += &format!(
+                            "{},{},{},{},{}\n",
+                            time_formatter.format_position(w.start),
+                            time_formatter.format_position(w.end),
+                            w.min,
+                            w.mean,
+                            w.max
+                        );
+                    });
+                    csv
+                });
+            });
+        self.open = open;
+    }
+
+    fn show_settings(
+        &mut self,
+        ui: &mut egui::Ui,
+        annotations: &[Annotation],
+        time_formatter: TimeFormatter,
+    ) {
+        if !annotations
+            .iter()
+            .any(|a| Some(a.position) == self.peak_mark)
+        {
+            self.peak_mark = None;
+        }
+        egui::CollapsingHeader::new("Settings").show(ui, |ui| {
+            egui::Grid::new("heart_rate_settings")
+                .num_columns(2)
+                .show(ui, |ui| {
+                    ui.label("Window");
+                    ui.add(
+                        DragValue::new(&mut self.window)
+                            .speed(1.0)
+                            .clamp_range(5.0..=3600.0)
+                            .suffix(" s"),
+                    );
+                    ui.end_row();
+                    ui.label("Maximum heart rate");
+                    ui.add(
+                        DragValue::new(&mut self.zones.max_heart_rate)
+                            .clamp_range(100.0..=250.0)
+                            .suffix(" bpm"),
+                    );
+                    ui.end_row();
+                    for zone in 0..self.zones.lower_bounds.len() {
+                        // keep the bounds ascending
+                        let min = zone
+                            .checked_sub(1)
+                            .map(|z| self.zones.lower_bounds[z])
+                            .unwrap_or(0.0);
+                        let max = self
+                            .zones
+                            .lower_bounds
+                            .get(zone + 1)
+                            .copied()
+                            .unwrap_or(1.0);
+                        ui.colored_label(ZONE_COLORS[zone + 1], format!("Zone {} from", zone + 1));
+                        let mut percent = self.zones.lower_bounds[zone] * 100.0;
+                        ui.add(
+                            DragValue::new(&mut percent)
+                                .clamp_range(min * 100.0..=max * 100.0)
+                                .suffix(" % of maximum"),
+                        );
+                        self.zones.lower_bounds[zone] = percent / 100.0;
+                        ui.end_row();
+                    }
+                    ui.label("Recovery after");
+                    let mark_label = |mark: Option<f64>| match mark {
+                        None => "highest heart rate".to_owned(),
+                        Some(position) => annotations
+                            .iter()
+                            .find(|a| a.position == position)
+                            .map(|a| {
+                                format!(
+                                    "{} ({})",
+                                    a.label,
+                                    time_formatter.format_position(position)
+                                )
+                            })
+                            .unwrap_or_default(),
+                    };
+                    egui::ComboBox::from_id_source("heart_rate_peak")
+                        .selected_text(mark_label(self.peak_mark))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.peak_mark, None, mark_label(None));
+                            for annotation in annotations.iter() {
+                                ui.selectable_value(
+                                    &mut self.peak_mark,
+                                    Some(annotation.position),
+                                    mark_label(Some(annotation.position)),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text("Annotate the end of the exercise to mark the peak");
+                    ui.end_row();
+                });
+        });
+    }
+
+    fn calculate(&mut self, plotter: &mut ChannelPlotter, label: &str, (start, end): (f64, f64)) {
+        let Some(channel) = plotter.channel_by_label(label) else {
+            return;
+        };
+        let points: Vec<(f64, f64)> = channel
+            .points_to_draw(start, end)
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .filter(|(x, y)| *x >= start && *x <= end && y.is_finite())
+            .collect();
+        if points.len() < 2 {
+            self.message = Some("The range has too few heart rates.".to_owned());
+            self.result = None;
+            return;
+        }
+        let peak = self.peak_mark.or_else(|| {
+            points
+                .iter()
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(x, _)| *x)
+        });
+        let recovery = peak.and_then(|peak| recovery(&points, peak));
+        let gap_factor = plotter.gap_factor;
+        self.message = recovery
+            .is_none()
+            .then(|| "No heart rate within 10 s of the marked peak.".to_owned());
+        self.result = Some(HeartRateTrend {
+            windows: window_statistics(&points, self.window),
+            zone_times: time_in_zones(&points, &self.zones, gap_factor),
+            zones: self.zones,
+            gap_factor,
+            recovery,
+            points,
+        });
+    }
+}
+
+fn show_summary(ui: &mut egui::Ui, result: &HeartRateTrend, time_formatter: &TimeFormatter) {
+    let values: Vec<f64> = result.points.iter().map(|(_, y)| *y).collect();
+    let total: f64 = result.zone_times.iter().sum();
+    egui::Grid::new("heart_rate_summary")
+        .num_columns(3)
+        .striped(true)
+        .show(ui, |ui| {
+            ui.label("Minimum / mean / maximum");
+            ui.monospace(format!(
+                "{:.0} / {:.0} / {:.0} bpm",
+                values.iter().copied().fold(f64::INFINITY, f64::min),
+                values.iter().sum::<f64>() / values.len() as f64,
+                values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+            ));
+            ui.end_row();
+            for (zone, time) in result.zone_times.iter().enumerate() {
+                let (lower, upper) = result.zones.range(zone);
+                let name = match zone {
+                    0 => format!("Below zones (< {upper:.0} bpm)"),
+                    5 => format!("Zone 5 (≥ {lower:.0} bpm)"),
+                    zone => format!("Zone {zone} ({lower:.0}–{upper:.0} bpm)"),
+                };
+                ui.colored_label(ZONE_COLORS[zone], name);
+                ui.monospace(format!(
+                    "{:.1} min, {:.0} %",
+                    time / 60.0,
+                    time / total.max(f64::EPSILON) * 100.0
+                ));
+                ui.end_row();
+            }
+            if let Some(recovery) = &result.recovery {
+                ui.label("Peak");
+                ui.monospace(format!(
+                    "{:.0} bpm at {}",
+                    recovery.peak_heart_rate,
+                    time_formatter.format_position(recovery.peak_position)
+                ));
+                ui.end_row();
+                let drop = |after: Option<f64>, hrr: Option<f64>| match (after, hrr) {
+                    (Some(after), Some(hrr)) => format!("{hrr:.0} bpm (to {after:.0} bpm)"),
+                    _ => "no heart rate".to_owned(),
+                };
+                ui.label("Recovery after 1 min");
+                ui.monospace(drop(recovery.after_1_min, recovery.hrr1()));
+                ui.end_row();
+                ui.label("Recovery after 2 min");
+                ui.monospace(drop(recovery.after_2_min, recovery.hrr2()));
+                ui.end_row();
+            }
+        });
+}
+
+/// Heart rate colored by zone, returns the position of a click
+fn plot_trend(
+    ui: &mut egui::Ui,
+    result: &HeartRateTrend,
+    time_formatter: TimeFormatter,
+) -> Option<f64> {
+    let max_gap = median_sample_rate(&result.points)
+        .map(|rate| result.gap_factor / rate)
+        .unwrap_or(f64::INFINITY);
+    // consecutive values of the same zone are drawn as one line, broken at gaps
+    let mut segments: Vec<(usize, Vec<[f64; 2]>)> = vec![];
+    for (idx, (x, y)) in result.points.iter().enumerate() {
+        let zone = result.zones.zone(*y);
+        let after_gap = idx > 0 && x - result.points[idx - 1].0 > max_gap;
+        match segments.last_mut() {
+            Some((last_zone, points)) if !after_gap => {
+                points.push([*x, *y]);
+                if *last_zone != zone {
+                    segments.push((zone, vec![[*x, *y]]));
+                }
+            }
+            _ => segments.push((zone, vec![[*x, *y]])),
+        }
+    }
+    let zones = result.zones;
+    let peak = result.recovery.map(|r| r.peak_position);
+    Plot::new("heart_rate_trend_plot")
+        .height(200.0)
+        .x_axis_formatter(move |value, _max_chars, range| time_formatter.format_tick(value, range))
+        .label_formatter(move |_name, value| {
+            format!(
+                "{}\n{:.0} bpm",
+                time_formatter.format_position(value.x),
+                value.y
+            )
+        })
+        .show(ui, |plot_ui| {
+            for (zone, points) in segments {
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points))
+                        .color(ZONE_COLORS[zone])
+                        .width(1.5),
+                );
+            }
+            for bound in zones.lower_bounds {
+                plot_ui.hline(
+                    HLine::new(bound * zones.max_heart_rate)
+                        .style(LineStyle::dashed_loose())
+                        .color(Color32::from_gray(128)),
+                );
+            }
+            if let Some(peak) = peak {
+                for offset in [0.0, 60.0, 120.0] {
+                    plot_ui.vline(
+                        VLine::new(peak + offset)
+                            .style(LineStyle::dotted_loose())
+                            .color(Color32::from_gray(128)),
+                    );
+                }
+            }
+            plot_ui
+                .response()
+                .clicked()
+                .then(|| plot_ui.pointer_coordinate().map(|p| p.x))
+                .flatten()
+        })
+        .inner
+}
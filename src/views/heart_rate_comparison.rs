@@ -0,0 +1,227 @@
+use egui::{Color32, Context};
+use egui_plot::{HLine, Legend, LineStyle, Plot, PlotPoints, Points};
+
+use crate::analysis::agreement::{bland_altman, paired_values, Agreement, Pair};
+use crate::data_structures::ChannelKind;
+use crate::views::common::select_channel;
+use crate::ChannelPlotter;
+
+/// Window to derive RR interval and heart rate channels from the beats of an ECG
+/// and to compare them with the values reported by the device
+#[derive(Default)]
+pub struct HeartRateComparisonView {
+    pub open: bool,
+    ecg_channel: Option<String>,
+    /// channel derived from the ECG
+    channel_a: Option<String>,
+    /// channel reported by the device
+    channel_b: Option<String>,
+    pairs: Vec<Pair>,
+    agreement: Option<Agreement>,
+    message: Option<String>,
+}
+
+impl HeartRateComparisonView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Heart rate comparison")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let ecg_channels: Vec<String> = plotter
+                    .channel_labels(ChannelKind::Ecg)
+                    .into_iter()
+                    .filter(|label| plotter.beats.contains_key(label))
+                    .collect();
+                if ecg_channels.is_empty() {
+                    ui.label("Detect the beats of an ECG channel first.");
+                } else {
+                    select_channel(ui, "ECG channel", &mut self.ecg_channel, &ecg_channels);
+                    if let Some(label) = self.ecg_channel.clone() {
+                        if ui
+                            .button("Add RR interval and heart rate channels")
+                            .on_hover_text("Recalculate after correcting the beats")
+                            .clicked()
+                        {
+                            plotter.add_beat_derived_channels(&label);
+                            self.agreement = None;
+                            self.pairs.clear();
+                        }
+                    }
+                }
+                ui.separator();
+
+                let heart_rates = plotter.channel_labels(ChannelKind::HeartRate);
+                let mut candidates = heart_rates.clone();
+                candidates.extend(plotter.channel_labels(ChannelKind::RrInterval));
+                if candidates.len() < 2 {
+                    ui.label("Two heart rate or RR interval channels are needed for a comparison.");
+                    return;
+                }
+                // channels derived from beats are named after their ECG, e.g. "HR (ECG) [bpm]"
+                let ecg_names: Vec<String> = plotter
+                    .channel_labels(ChannelKind::Ecg)
+                    .iter()
+                    .filter_map(|label| Some(plotter.channel_by_label(label)?.get_name()))
+                    .collect();
+                let is_derived = |label: &String| {
+                    ecg_names
+                        .iter()
+                        .any(|name| label.contains(&format!("({name})")))
+                };
+                if self.channel_a.is_none() {
+                    self.channel_a = candidates.iter().find(|c| is_derived(c)).cloned();
+                }
+                select_channel(ui, "derived", &mut self.channel_a, &candidates);
+                if self.channel_b.is_none() || self.channel_b == self.channel_a {
+                    // preselect a device channel of the same kind as the derived one
+                    let is_heart_rate = |label: &String| heart_rates.contains(label);
+                    let kind_a = self.channel_a.as_ref().map(is_heart_rate);
+                    self.channel_b = candidates
+                        .iter()
+                        .filter(|c| Some(*c) != self.channel_a.as_ref())
+                        .min_by_key(|c| (Some(is_heart_rate(c)) != kind_a, is_derived(c)))
+                        .cloned();
+                }
+                select_channel(ui, "reference", &mut self.channel_b, &candidates);
+                if ui.button("Compare").clicked() {
+                    self.compare(plotter);
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+
+                let Some(agreement) = self.agreement else {
+                    return;
+                };
+                let unit = self
+                    .channel_a
+                    .as_ref()
+                    .and_then(|label| plotter.channel_by_label(label))
+                    .map(|c| c.get_unit())
+                    .unwrap_or_default();
+                egui::Grid::new("agreement_statistics").show(ui, |ui| {
+                    ui.label("pairs");
+                    ui.label(agreement.n.to_string());
+                    ui.end_row();
+                    ui.label("bias (derived - reference)");
+                    ui.label(format!("{:.2} {}", agreement.bias, unit));
+                    ui.end_row();
+                    ui.label("SD of the differences");
+                    ui.label(format!("{:.2} {}", agreement.sd, unit));
+                    ui.end_row();
+                    ui.label("limits of agreement");
+                    ui.label(format!(
+                        "{:.2} … {:.2} {}",
+                        agreement.lower_limit, agreement.upper_limit, unit
+                    ));
+                    ui.end_row();
+                    ui.label("mean absolute error");
+                    ui.label(format!("{:.2} {}", agreement.mean_absolute_error, unit));
+                    ui.end_row();
+                    ui.label("correlation");
+                    ui.label(format!("{:.3}", agreement.correlation));
+                    ui.end_row();
+                });
+
+                let time_formatter = plotter.time_formatter();
+                ui.label("Difference over time");
+                Plot::new("difference_plot")
+                    .height(150.0)
+                    .x_axis_formatter(move |value, _max_chars, range| {
+                        time_formatter.format_tick(value, range)
+                    })
+                    .label_formatter(move |_name, value| {
+                        format!(
+                            "{}\n{:.2}",
+                            time_formatter.format_position(value.x),
+                            value.y
+                        )
+                    })
+                    .show(ui, |plot_ui| {
+                        plot_ui.hline(HLine::new(0.0).color(Color32::GRAY));
+                        plot_ui.points(
+                            Points::new(PlotPoints::new(
+                                self.pairs
+                                    .iter()
+                                    .map(|p| [p.position, p.difference()])
+                                    .collect(),
+                            ))
+                            .radius(1.5),
+                        );
+                    });
+
+                ui.label("Bland–Altman plot");
+                Plot::new("bland_altman_plot")
+                    .height(200.0)
+                    .legend(Legend::default())
+                    .x_axis_label(format!("mean [{}]", unit))
+                    .y_axis_label(format!("difference [{}]", unit))
+                    .show(ui, |plot_ui| {
+                        plot_ui.points(
+                            Points::new(PlotPoints::new(
+                                self.pairs
+                                    .iter()
+                                    .map(|p| [p.mean(), p.difference()])
+                                    .collect(),
+                            ))
+                            .radius(1.5),
+                        );
+                        plot_ui.hline(
+                            HLine::new(agreement.bias)
+                                .color(Color32::LIGHT_BLUE)
+                                .name("bias"),
+                        );
+                        for limit in [agreement.lower_limit, agreement.upper_limit] {
+                            plot_ui.hline(
+                                HLine::new(limit)
+                                    .color(Color32::LIGHT_RED)
+                                    .style(LineStyle::dashed_loose())
+                                    .name("limits of agreement (±1.96 SD)"),
+                            );
+                        }
+                    });
+            });
+        self.open = open;
+    }
+
+    fn compare(&mut self, plotter: &mut ChannelPlotter) {
+        let (Some(a), Some(b)) = (self.channel_a.clone(), self.channel_b.clone()) else {
+            return;
+        };
+        self.agreement = None;
+        self.pairs.clear();
+        if a == b {
+            self.message = Some("select two different channels".to_owned());
+            return;
+        }
+        let unit_a = plotter.channel_by_label(&a).map(|c| c.get_unit());
+        let unit_b = plotter.channel_by_label(&b).map(|c| c.get_unit());
+        if unit_a != unit_b {
+            self.message = Some(format!(
+                "the channels have different units ({} and {})",
+                unit_a.unwrap_or_default(),
+                unit_b.unwrap_or_default()
+            ));
+            return;
+        }
+        let Some(channel_b) = plotter.channel_by_label(&b) else {
+            return;
+        };
+        let reference: Vec<(f64, f64)> = channel_b
+            .points_to_draw(f64::NEG_INFINITY, f64::INFINITY)
+            .points()
+            .iter()
+            .map(|p| (p.x, p.y))
+            .collect();
+        let Some(channel_a) = plotter.channel_by_label(&a) else {
+            return;
+        };
+        self.pairs = paired_values(channel_a, &reference);
+        self.agreement = bland_altman(&self.pairs);
+        self.message = match self.agreement {
+            Some(_) => None,
+            None => Some("the channels don't overlap in time".to_owned()),
+        };
+    }
+}
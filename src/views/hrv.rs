@@ -0,0 +1,533 @@
+use egui::{Color32, Context};
+use egui_plot::{Legend, Line, Plot, PlotPoints, Points};
+
+use crate::analysis::artefacts::{ArtefactDetection, CorrectionLog, CorrectionMethod};
+use crate::analysis::hrv::{
+    frequency_domain, nonlinear, rr_spectrum, time_domain, FrequencyDomainHrv, NonlinearHrv,
+    RrSeries, SpectrumMethod, TimeDomainHrv, HF_BAND, LF_BAND, VLF_BAND,
+};
+use crate::analysis::spectrum::Spectrum;
+use crate::data_structures::AnalysisRange;
+use crate::time_format::TimeFormatter;
+use crate::views::common::{export_csv, low_quality_note, select_channel, select_range};
+use crate::ChannelPlotter;
+
+type Rows = Vec<(&'static str, String, &'static str)>;
+
+/// All HRV measures of one RR series
+struct HrvResults {
+    series: RrSeries,
+    time_domain: Option<TimeDomainHrv>,
+    spectrum: Option<Spectrum>,
+    frequency_domain: Option<FrequencyDomainHrv>,
+    nonlinear: Option<NonlinearHrv>,
+}
+
+impl HrvResults {
+    fn new(series: RrSeries, spectrum_method: SpectrumMethod) -> HrvResults {
+        let spectrum = rr_spectrum(&series, spectrum_method);
+        HrvResults {
+            time_domain: time_domain(&series),
+            frequency_domain: spectrum.as_ref().map(frequency_domain),
+            spectrum,
+            nonlinear: nonlinear(&series),
+            series,
+        }
+    }
+
+    fn time_domain_rows(&self) -> Rows {
+        self.time_domain.map(|r| r.rows()).unwrap_or_default()
+    }
+
+    fn frequency_domain_rows(&self) -> Rows {
+        self.frequency_domain.map(|r| r.rows()).unwrap_or_default()
+    }
+
+    fn nonlinear_rows(&self) -> Rows {
+        self.nonlinear.map(|r| r.rows()).unwrap_or_default()
+    }
+}
+
+/// Window with the heart rate variability of an RR series
+pub struct HrvView {
+    pub open: bool,
+    /// label of the ECG or RR interval channel the intervals are taken from
+    source: Option<String>,
+    range: AnalysisRange,
+    /// source and range of the results
+    analysed: Option<(String, (f64, f64))>,
+    spectrum_method: SpectrumMethod,
+    original: Option<HrvResults>,
+    /// results with the artefact corrections applied, if there are any
+    corrected: Option<HrvResults>,
+    artefact_detection: ArtefactDetection,
+    correction_method: CorrectionMethod,
+    message: Option<String>,
+}
+
+impl Default for HrvView {
+    fn default() -> Self {
+        Self {
+            open: false,
+            source: None,
+            range: AnalysisRange::Recording,
+            analysed: None,
+            spectrum_method: SpectrumMethod::Welch,
+            original: None,
+            corrected: None,
+            artefact_detection: ArtefactDetection::LipponenTarvainen,
+            correction_method: CorrectionMethod::Interpolate,
+            message: None,
+        }
+    }
+}
+
+impl HrvView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Heart rate variability")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let sources = plotter.rr_sources();
+                if sources.is_empty() {
+                    ui.label(
+                        "Detect the beats of an ECG channel or load an RR interval file first.",
+                    );
+                    return;
+                }
+                select_channel(ui, "RR intervals", &mut self.source, &sources);
+                let range = select_range(ui, "hrv_range", &mut self.range, plotter);
+                let Some(source) = self.source.clone() else {
+                    return;
+                };
+                low_quality_note(ui, plotter, &source);
+                egui::CollapsingHeader::new("Artefact correction").show(ui, |ui| {
+                    self.show_artefact_correction(ui, plotter, &source);
+                });
+                if ui
+                    .add_enabled(range.is_some(), egui::Button::new("Calculate"))
+                    .clicked()
+                {
+                    if let Some(range) = range {
+                        self.analysed = Some((source.to_owned(), range));
+                        self.calculate(plotter);
+                    }
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+
+                let (Some((source, (start, end))), Some(original)) =
+                    (&self.analysed, &self.original)
+                else {
+                    return;
+                };
+                let time_formatter = plotter.time_formatter();
+                ui.label(format!(
+                    "{}\n{} – {}",
+                    source,
+                    time_formatter.format_position(*start),
+                    time_formatter.format_position(*end)
+                ));
+                ui.separator();
+
+                // the plots show the corrected series
+                let corrected = self.corrected.as_ref();
+                let shown = corrected.unwrap_or(original);
+                let mut clicked_position = None;
+                let mut spectrum_method = self.spectrum_method;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    egui::CollapsingHeader::new("Time domain")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            show_rows(
+                                ui,
+                                "hrv_time_domain",
+                                original.time_domain_rows(),
+                                corrected.map(|c| c.time_domain_rows()),
+                            );
+                        });
+
+                    egui::CollapsingHeader::new("Frequency domain")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            egui::ComboBox::from_label("Spectrum")
+                                .selected_text(spectrum_method.label())
+                                .show_ui(ui, |ui| {
+                                    for m in [SpectrumMethod::Welch, SpectrumMethod::LombScargle] {
+                                        ui.selectable_value(&mut spectrum_method, m, m.label());
+                                    }
+                                });
+                            match &shown.spectrum {
+                                Some(spectrum) => {
+                                    show_rows(
+                                        ui,
+                                        "hrv_frequency_domain",
+                                        original.frequency_domain_rows(),
+                                        corrected.map(|c| c.frequency_domain_rows()),
+                                    );
+                                    show_spectrum(ui, spectrum);
+                                }
+                                None => {
+                                    ui.label("The range is too short for a spectrum.");
+                                }
+                            }
+                        });
+
+                    egui::CollapsingHeader::new("Nonlinear")
+                        .default_open(true)
+                        .show(ui, |ui| {
+                            show_rows(
+                                ui,
+                                "hrv_nonlinear",
+                                original.nonlinear_rows(),
+                                corrected.map(|c| c.nonlinear_rows()),
+                            );
+                            if let Some(nonlinear) = &shown.nonlinear {
+                                ui.label("Click a point to show the beat in the plot.");
+                                clicked_position = show_poincare_plot(ui, &shown.series, nonlinear);
+                            }
+                        });
+                });
+                if let Some(position) = clicked_position {
+                    // show the beat with its neighbours
+                    plotter.set_x_bounds(position - 5.0, position + 5.0);
+                }
+
+                ui.separator();
+                export_csv(ui, "hrv.csv", || self.to_csv(&time_formatter));
+                if spectrum_method != self.spectrum_method {
+                    self.spectrum_method = spectrum_method;
+                    self.calculate(plotter);
+                }
+            });
+        self.open = open;
+    }
+
+    fn show_artefact_correction(
+        &mut self,
+        ui: &mut egui::Ui,
+        plotter: &mut ChannelPlotter,
+        source: &str,
+    ) {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("artefact_detection")
+                .selected_text(self.artefact_detection.label())
+                .show_ui(ui, |ui| {
+                    for detection in [
+                        ArtefactDetection::LipponenTarvainen,
+                        ArtefactDetection::Threshold(0.2),
+                    ] {
+                        let selected = self.artefact_detection.label() == detection.label();
+                        if ui.selectable_label(selected, detection.label()).clicked() && !selected {
+                            self.artefact_detection = detection;
+                        }
+                    }
+                });
+            if let ArtefactDetection::Threshold(threshold) = &mut self.artefact_detection {
+                let mut percent = *threshold * 100.0;
+                ui.add(
+                    egui::DragValue::new(&mut percent)
+                        .clamp_range(5.0..=50.0)
+                        .suffix(" % of the local median"),
+                );
+                *threshold = percent / 100.0;
+            }
+        });
+        egui::ComboBox::from_label("Correction")
+            .selected_text(self.correction_method.label())
+            .show_ui(ui, |ui| {
+                for method in [CorrectionMethod::Interpolate, CorrectionMethod::Delete] {
+                    ui.selectable_value(&mut self.correction_method, method, method.label());
+                }
+            });
+        ui.horizontal(|ui| {
+            if ui.button("Detect and correct").clicked() {
+                if let Some(series) = plotter.rr_series(source) {
+                    let log = CorrectionLog::new(
+                        &series,
+                        self.artefact_detection,
+                        self.correction_method,
+                    );
+                    plotter.set_rr_correction_log(source, Some(log));
+                    self.calculate(plotter);
+                }
+            }
+            if ui.button("Undo corrections").clicked() {
+                plotter.set_rr_correction_log(source, None);
+                self.calculate(plotter);
+            }
+        });
+
+        let time_formatter = plotter.time_formatter();
+        let Some(log) = plotter.rr_correction_log(source) else {
+            ui.label("No corrections.");
+            return;
+        };
+        let counts: Vec<String> = log
+            .counts()
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.label()))
+            .collect();
+        ui.label(format!(
+            "{} corrected intervals ({})",
+            log.corrections.len(),
+            counts.join(", ")
+        ));
+        egui::CollapsingHeader::new("Correction log").show(ui, |ui| {
+            egui::ScrollArea::vertical()
+                .max_height(150.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("correction_log")
+                        .striped(true)
+                        .show(ui, |ui| {
+                            for correction in log.corrections.iter() {
+                                ui.label(time_formatter.format_position(correction.position));
+                                ui.label(correction.kind.label());
+                                ui.monospace(format!("{:.0} ms", correction.original));
+                                ui.monospace(match correction.corrected {
+                                    Some(value) => format!("→ {:.0} ms", value),
+                                    None => "deleted".to_owned(),
+                                });
+                                ui.end_row();
+                            }
+                        });
+                });
+        });
+    }
+
+    fn calculate(&mut self, plotter: &mut ChannelPlotter) {
+        self.original = None;
+        self.corrected = None;
+        let Some((source, (start, end))) = self.analysed.clone() else {
+            return;
+        };
+        let Some(series) = plotter.rr_series(&source) else {
+            return;
+        };
+        let low_quality = plotter.low_quality_ranges(&source);
+        let series = series.range(start, end);
+        self.message =
+            (series.len() < 3).then(|| "at least three RR intervals are needed".to_owned());
+        if let Some(log) = plotter.rr_correction_log(&source) {
+            self.corrected = Some(HrvResults::new(
+                log.apply(&series).without(&low_quality),
+                self.spectrum_method,
+            ));
+        }
+        self.original = Some(HrvResults::new(
+            series.without(&low_quality),
+            self.spectrum_method,
+        ));
+    }
+
+    /// The results as `measure,value,unit` lines, with a column for the corrected values
+    fn to_csv(&self, time_formatter: &TimeFormatter) -> String {
+        let corrected = self.corrected.as_ref();
+        let mut csv = match corrected {
+            Some(_) => "measure,value,corrected,unit\n".to_owned(),
+            None => "measure,value,unit\n".to_owned(),
+        };
+        let empty = if corrected.is_some() { "," } else { "" };
+        if let Some((source, (start, end))) = &self.analysed {
+            csv += &format!("source,\"{}\",{}\n", source, empty);
+            csv += &format!(
+                "start,{},{}\n",
+                time_formatter.format_position(*start),
+                empty
+            );
+            csv += &format!("end,{},{}\n", time_formatter.format_position(*end), empty);
+            csv += &format!("spectrum,{},{}\n", self.spectrum_method.label(), empty);
+        }
+        let Some(original) = &self.original else {
+            return csv;
+        };
+        let rows = |results: &HrvResults| -> Rows {
+            let mut rows = results.time_domain_rows();
+            rows.extend(results.frequency_domain_rows());
+            rows.extend(results.nonlinear_rows());
+            rows
+        };
+        let corrected_rows = corrected.map(rows);
+        for (idx, (name, value, unit)) in rows(original).into_iter().enumerate() {
+            match &corrected_rows {
+                Some(corrected_rows) => {
+                    let corrected_value = corrected_rows
+                        .get(idx)
+                        .map(|(_, value, _)| value.as_str())
+                        .unwrap_or_default();
+                    csv += &format!("{},{},{},{}\n", name, value, corrected_value, unit);
+                }
+                None => csv += &format!("{},{},{}\n", name, value, unit),
+            }
+        }
+        csv
+    }
+}
+
+/// Plot of the power spectral density with the VLF, LF and HF bands filled
+fn show_spectrum(ui: &mut egui::Ui, spectrum: &Spectrum) {
+    let bands = [
+        ("VLF", VLF_BAND, Color32::from_rgb(150, 150, 150)),
+        ("LF", LF_BAND, Color32::from_rgb(100, 150, 250)),
+        ("HF", HF_BAND, Color32::from_rgb(250, 150, 100)),
+    ];
+    Plot::new("hrv_spectrum")
+        .height(180.0)
+        .legend(Legend::default())
+        .include_x(0.0)
+        .include_x(0.5)
+        .include_y(0.0)
+        .x_axis_label("Frequency [Hz]")
+        .y_axis_label("PSD [ms²/Hz]")
+        .show(ui, |plot_ui| {
+            let points = |low: f64, high: f64| -> Vec<[f64; 2]> {
+                spectrum
+                    .frequencies
+                    .iter()
+                    .zip(spectrum.power.iter())
+                    .filter(|(f, _)| **f >= low && **f <= high)
+                    .map(|(f, p)| [*f, *p])
+                    .collect()
+            };
+            for (name, (low, high), color) in bands {
+                plot_ui.line(
+                    Line::new(PlotPoints::new(points(low, high)))
+                        .color(color)
+                        .fill(0.0)
+                        .name(name),
+                );
+            }
+            plot_ui.line(
+                Line::new(PlotPoints::new(points(0.0, 0.5)))
+                    .color(plot_ui.ctx().style().visuals.text_color())
+                    .name("PSD"),
+            );
+        });
+}
+
+/// Table with one measure per row, with an additional column for the corrected values
+fn show_rows(ui: &mut egui::Ui, id: &str, rows: Rows, corrected: Option<Rows>) {
+    egui::Grid::new(id)
+        .num_columns(4)
+        .striped(true)
+        .show(ui, |ui| {
+            if corrected.is_some() {
+                ui.label("");
+                ui.strong("original");
+                ui.strong("corrected");
+                ui.end_row();
+            }
+            for (idx, (name, value, unit)) in rows.into_iter().enumerate() {
+                ui.label(name);
+                ui.monospace(value);
+                if let Some(corrected) = &corrected {
+                    ui.monospace(
+                        corrected
+                            .get(idx)
+                            .map(|(_, value, _)| value.as_str())
+                            .unwrap_or_default(),
+                    );
+                }
+                ui.label(unit);
+                ui.end_row();
+            }
+        });
+}
+
+/// Scatter plot of each RR interval against the next one with the SD1/SD2 ellipse,
+/// returns the position of the beat whose point was clicked
+fn show_poincare_plot(
+    ui: &mut egui::Ui,
+    series: &RrSeries,
+    nonlinear: &NonlinearHrv,
+) -> Option<f64> {
+    let rr = &series.intervals;
+    let mean = rr.iter().sum::<f64>() / rr.len() as f64;
+    let successive = series.successive();
+    let points: Vec<[f64; 2]> = successive
+        .iter()
+        .map(|idx| [rr[idx - 1], rr[*idx]])
+        .collect();
+    let (min, max) = rr
+        .iter()
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), rr| {
+            (min.min(*rr), max.max(*rr))
+        });
+
+    // the ellipse is rotated by 45°, SD2 along the identity line and SD1 across it
+    let ellipse: Vec<[f64; 2]> = (0..=100)
+        .map(|k| {
+            let angle = k as f64 / 100.0 * std::f64::consts::TAU;
+            let (along, across) = (nonlinear.sd2 * angle.cos(), nonlinear.sd1 * angle.sin());
+            let (x, y) = (
+                (along - across) / std::f64::consts::SQRT_2,
+                (along + across) / std::f64::consts::SQRT_2,
+            );
+            [mean + x, mean + y]
+        })
+        .collect();
+    let axis = |length: f64, direction: f64| {
+        let d = length / std::f64::consts::SQRT_2;
+        Line::new(PlotPoints::new(vec![
+            [mean, mean],
+            [mean + d, mean + direction * d],
+        ]))
+    };
+
+    let mut clicked_index = None;
+    Plot::new("poincare_plot")
+        .height(300.0)
+        .data_aspect(1.0)
+        .legend(Legend::default())
+        .x_axis_label("RRn [ms]")
+        .y_axis_label("RRn+1 [ms]")
+        .show(ui, |plot_ui| {
+            plot_ui.line(
+                Line::new(PlotPoints::new(vec![[min, min], [max, max]]))
+                    .color(Color32::GRAY)
+                    .style(egui_plot::LineStyle::dashed_loose()),
+            );
+            plot_ui.points(
+                Points::new(PlotPoints::new(points.clone()))
+                    .radius(2.0)
+                    .name("RR intervals"),
+            );
+            plot_ui.line(
+                Line::new(PlotPoints::new(ellipse))
+                    .color(Color32::LIGHT_BLUE)
+                    .name("ellipse"),
+            );
+            plot_ui.line(
+                axis(nonlinear.sd1, -1.0)
+                    .color(Color32::LIGHT_RED)
+                    .name("SD1"),
+            );
+            plot_ui.line(
+                axis(nonlinear.sd2, 1.0)
+                    .color(Color32::LIGHT_GREEN)
+                    .name("SD2"),
+            );
+
+            if plot_ui.response().clicked() {
+                if let Some(pointer) = plot_ui.pointer_coordinate() {
+                    // nearest point in screen distance
+                    let scale = plot_ui.transform().dvalue_dpos();
+                    clicked_index = points
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, p)| {
+                            let dx = (p[0] - pointer.x) / scale[0];
+                            let dy = (p[1] - pointer.y) / scale[1];
+                            (idx, dx * dx + dy * dy)
+                        })
+                        .filter(|(_, distance)| *distance < 100.0)
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(idx, _)| idx);
+                }
+            }
+        });
+    // the point (RRn, RRn+1) belongs to the beat ending RRn+1
+    clicked_index.map(|idx| series.positions[successive[idx]])
+}
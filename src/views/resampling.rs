@@ -0,0 +1,92 @@
+use egui::{Context, DragValue};
+
+use crate::analysis::resample::ResamplingMethod;
+use crate::views::common::select_channel;
+use crate::ChannelPlotter;
+
+/// Window to add a channel with a fixed sample rate resampled from another channel
+pub struct ResamplingView {
+    pub open: bool,
+    channel: Option<String>,
+    samples_per_second: f64,
+    method: ResamplingMethod,
+    message: Option<String>,
+}
+
+impl Default for ResamplingView {
+    fn default() -> Self {
+        ResamplingView {
+            open: false,
+            channel: None,
+            samples_per_second: 250.0,
+            method: ResamplingMethod::Cubic,
+            message: None,
+        }
+    }
+}
+
+impl ResamplingView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Resampling")
+            .open(&mut open)
+            .default_width(300.0)
+            .show(ctx, |ui| {
+                let labels: Vec<String> =
+                    plotter.channels.iter_mut().map(|c| c.get_label()).collect();
+                if labels.is_empty() {
+                    ui.label("No channels loaded.");
+                    return;
+                }
+                select_channel(ui, "Channel", &mut self.channel, &labels);
+                let Some(label) = self.channel.clone() else {
+                    return;
+                };
+                let current_rate = plotter
+                    .channel_gaps(&label)
+                    .and_then(|gaps| gaps.samples_per_second);
+                if let Some(rate) = current_rate {
+                    ui.label(format!("median sample rate: {:.1} Hz", rate));
+                }
+
+                ui.horizontal(|ui| {
+                    ui.label("New sample rate");
+                    ui.add(
+                        DragValue::new(&mut self.samples_per_second)
+                            .speed(1.0)
+                            .clamp_range(0.1..=10_000.0)
+                            .suffix(" Hz"),
+                    );
+                });
+                egui::ComboBox::from_label("Interpolation")
+                    .selected_text(self.method.label())
+                    .show_ui(ui, |ui| {
+                        for method in [
+                            ResamplingMethod::Linear,
+                            ResamplingMethod::Cubic,
+                            ResamplingMethod::Sinc,
+                        ] {
+                            ui.selectable_value(&mut self.method, method, method.label());
+                        }
+                    });
+                ui.label("Dropouts are left empty instead of being interpolated.");
+
+                if ui.button("Add resampled channel").clicked() {
+                    self.message = Some(
+                        match plotter.add_resampled_channel(
+                            &label,
+                            self.samples_per_second,
+                            self.method,
+                        ) {
+                            Some(new_label) => format!("Added {}", new_label),
+                            None => "The channel has too few samples.".to_owned(),
+                        },
+                    );
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+            });
+        self.open = open;
+    }
+}
@@ -0,0 +1,200 @@
+use egui::{Color32, Context, DragValue};
+use egui_plot::{Legend, Line, Plot, PlotPoints, Points};
+
+use crate::analysis::respiration::{RespirationEstimate, Surrogate};
+use crate::data_structures::ChannelKind;
+use crate::views::common::{export_csv, low_quality_note, select_channel};
+use crate::ChannelPlotter;
+
+/// Window estimating the respiration rate from the beats of an ECG and the chest movement
+pub struct RespirationView {
+    pub open: bool,
+    channel: Option<String>,
+    /// accelerometer channel of the chest movement, None to use the ECG only
+    chest: Option<String>,
+    /// length of the windows the rate is estimated in, in seconds
+    window: f64,
+    /// seconds between the windows
+    step: f64,
+    result: Option<Vec<RespirationEstimate>>,
+    message: Option<String>,
+}
+
+impl Default for RespirationView {
+    fn default() -> Self {
+        RespirationView {
+            open: false,
+            channel: None,
+            chest: None,
+            window: 32.0,
+            step: 5.0,
+            result: None,
+            message: None,
+        }
+    }
+}
+
+impl RespirationView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Respiration")
+            .open(&mut open)
+            .default_width(450.0)
+            .show(ctx, |ui| {
+                let ecg_channels: Vec<String> = plotter
+                    .channel_labels(ChannelKind::Ecg)
+                    .into_iter()
+                    .filter(|label| plotter.beats.contains_key(label))
+                    .collect();
+                if ecg_channels.is_empty() {
+                    ui.label("Detect the beats of an ECG channel first.");
+                    return;
+                }
+                select_channel(ui, "ECG channel", &mut self.channel, &ecg_channels);
+                let mut chest_channels = plotter.channel_labels(ChannelKind::Acceleration);
+                chest_channels.extend(plotter.channel_labels(ChannelKind::Activity));
+                if !self
+                    .chest
+                    .as_ref()
+                    .is_some_and(|c| chest_channels.contains(c))
+                {
+                    self.chest = None;
+                }
+                egui::ComboBox::from_label("Chest movement")
+                    .selected_text(self.chest.clone().unwrap_or("None".to_owned()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.chest, None, "None");
+                        for channel in chest_channels.iter() {
+                            ui.selectable_value(&mut self.chest, Some(channel.to_owned()), channel);
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Accelerometer axis along the breathing movement of a chest strap, \
+                         e.g. the axis pointing out of the chest",
+                    );
+                ui.horizontal(|ui| {
+                    ui.label("Window");
+                    ui.add(
+                        DragValue::new(&mut self.window)
+                            .speed(0.5)
+                            .clamp_range(16.0..=300.0)
+                            .suffix(" s"),
+                    );
+                    ui.label("Step");
+                    ui.add(
+                        DragValue::new(&mut self.step)
+                            .speed(0.5)
+                            .clamp_range(1.0..=300.0)
+                            .suffix(" s"),
+                    );
+                });
+                let Some(label) = self.channel.clone() else {
+                    return;
+                };
+                low_quality_note(ui, plotter, &label);
+                if ui
+                    .button("Add respiration rate channel")
+                    .on_hover_text(
+                        "Fuse the rates of the R amplitude, RSA, QRS area and chest movement \
+                         where they agree",
+                    )
+                    .clicked()
+                {
+                    self.result = plotter.add_respiration_channel(
+                        &label,
+                        self.chest.as_deref(),
+                        self.window,
+                        self.step,
+                    );
+                    self.message = match &self.result {
+                        None => Some("The channels have too few samples.".to_owned()),
+                        Some(estimates) if estimates.iter().all(|e| e.fused.is_none()) => {
+                            Some("The respiration surrogates don't agree in any window.".to_owned())
+                        }
+                        Some(_) => None,
+                    };
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let Some(result) = &self.result else {
+                    return;
+                };
+                ui.separator();
+                let fused: Vec<f64> = result.iter().filter_map(|e| e.fused).collect();
+                if !fused.is_empty() {
+                    ui.label(format!(
+                        "Mean respiration rate {:.1} breaths/min, {:.0} % of the windows fused",
+                        fused.iter().sum::<f64>() / fused.len() as f64,
+                        fused.len() as f64 / result.len() as f64 * 100.0
+                    ));
+                }
+                let time_formatter = plotter.time_formatter();
+                let response = Plot::new("respiration_plot")
+                    .height(200.0)
+                    .legend(Legend::default())
+                    .x_axis_formatter(move |value, _max_chars, range| {
+                        time_formatter.format_tick(value, range)
+                    })
+                    .label_formatter(move |name, value| {
+                        format!(
+                            "{}\n{}\n{:.1} breaths/min",
+                            name,
+                            time_formatter.format_position(value.x),
+                            value.y
+                        )
+                    })
+                    .show(ui, |plot_ui| {
+                        for (idx, surrogate) in Surrogate::ALL.iter().enumerate() {
+                            let points: Vec<[f64; 2]> = result
+                                .iter()
+                                .filter_map(|e| Some([e.position, e.rates[idx]?]))
+                                .collect();
+                            if !points.is_empty() {
+                                plot_ui.points(
+                                    Points::new(PlotPoints::new(points))
+                                        .radius(1.5)
+                                        .name(surrogate.label()),
+                                );
+                            }
+                        }
+                        let fused: Vec<[f64; 2]> = result
+                            .iter()
+                            .filter_map(|e| Some([e.position, e.fused?]))
+                            .collect();
+                        plot_ui.line(
+                            Line::new(PlotPoints::new(fused))
+                                .color(Color32::RED)
+                                .width(2.0)
+                                .name("fused"),
+                        );
+                        plot_ui
+                            .response()
+                            .clicked()
+                            .then(|| plot_ui.pointer_coordinate().map(|p| p.x))
+                            .flatten()
+                    });
+                if let Some(position) = response.inner {
+                    plotter.center_on(position);
+                }
+                export_csv(ui, "respiration.csv", || {
+                    let mut csv = "window center".to_owned();
+                    for surrogate in Surrogate::ALL {
+                        csv += &format!(",{} [breaths/min]", surrogate.label());
+                    }
+                    csv += ",fused [breaths/min]\n";
+                    let value = |rate: Option<f64>| rate.map(|r| r.to_string()).unwrap_or_default();
+                    for estimate in result.iter() {
+                        csv += &time_formatter.format_position(estimate.position);
+                        for rate in estimate.rates {
+                            csv += &format!(",{}", value(rate));
+                        }
+                        csv += &format!(",{}\n", value(estimate.fused));
+                    }
+                    csv
+                });
+            });
+        self.open = open;
+    }
+}
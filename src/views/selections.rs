@@ -0,0 +1,126 @@
+use egui::Context;
+
+use crate::data_structures::AnnotationKind;
+use crate::views::common::export_csv;
+use crate::ChannelPlotter;
+
+/// Window to manage the selection and the named ranges
+#[derive(Default)]
+pub struct SelectionsView {
+    pub open: bool,
+    /// name for saving the selection
+    name: String,
+}
+
+impl SelectionsView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Selections")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                let time_formatter = plotter.time_formatter();
+                match plotter.selection {
+                    Some((start, end)) => {
+                        ui.label(format!(
+                            "Selection {} – {} ({:.1} s)",
+                            time_formatter.format_position(start),
+                            time_formatter.format_position(end),
+                            end - start
+                        ));
+                        ui.horizontal(|ui| {
+                            if ui.button("Zoom to selection").clicked() {
+                                plotter.zoom_to_selection();
+                            }
+                            if ui.button("Clear").clicked() {
+                                plotter.selection = None;
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            if self.name.is_empty() {
+                                self.name = plotter.next_selection_name();
+                            }
+                            ui.text_edit_singleline(&mut self.name);
+                            if ui.button("Save as named range").clicked() {
+                                plotter.save_selection(self.name.trim().to_owned());
+                                self.name.clear();
+                            }
+                        });
+                        export_csv(ui, "selection.csv", || {
+                            let mut csv = "channel,time,value\n".to_owned();
+                            for channel in plotter.channels.iter_mut() {
+                                let label = channel.get_label();
+                                for point in channel.points_to_draw(start, end).points() {
+                                    csv += &format!(
+                                        "\"{}\",{},{}\n",
+                                        label,
+                                        time_formatter.format_position(point.x),
+                                        point.y
+                                    );
+                                }
+                            }
+                            csv
+                        });
+                    }
+                    None => {
+                        ui.label(
+                            "Select a range with SHIFT + drag in the plot, or choose a named \
+                             range below.",
+                        );
+                    }
+                }
+                ui.separator();
+
+                let mut select = None;
+                let mut remove = None;
+                egui::ScrollArea::vertical()
+                    .max_height(250.0)
+                    .show(ui, |ui| {
+                        egui::Grid::new("named_ranges")
+                            .num_columns(3)
+                            .striped(true)
+                            .show(ui, |ui| {
+                                for (idx, annotation) in plotter.annotations.iter_mut().enumerate()
+                                {
+                                    let Some(end) = annotation.end else {
+                                        continue;
+                                    };
+                                    if annotation.kind != AnnotationKind::NamedRange {
+                                        continue;
+                                    }
+                                    ui.text_edit_singleline(&mut annotation.label);
+                                    ui.label(format!(
+                                        "{} – {}",
+                                        time_formatter.format_position(annotation.position),
+                                        time_formatter.format_position(end)
+                                    ));
+                                    ui.horizontal(|ui| {
+                                        if ui.small_button("Select").clicked() {
+                                            select = Some((annotation.position, end));
+                                        }
+                                        if ui.small_button("Delete").clicked() {
+                                            remove = Some(idx);
+                                        }
+                                    });
+                                    ui.end_row();
+                                }
+                            });
+                    });
+                if !plotter
+                    .annotations
+                    .iter()
+                    .any(|a| a.kind == AnnotationKind::NamedRange)
+                {
+                    ui.label("No named ranges yet.");
+                }
+                if let Some(range) = select {
+                    plotter.selection = Some(range);
+                    plotter.zoom_to_selection();
+                }
+                if let Some(idx) = remove {
+                    plotter.annotations.remove(idx);
+                }
+            });
+        self.open = open;
+    }
+}
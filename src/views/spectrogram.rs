@@ -0,0 +1,275 @@
+use egui::{Color32, ColorImage, DragValue, TextureHandle, TextureOptions, Ui};
+use egui_plot::{Plot, PlotImage, PlotPoint};
+
+use crate::analysis::resample::UniformSignal;
+use crate::analysis::spectrum::{spectrogram, WindowFunction};
+use crate::data_structures::DrawableChannel;
+use crate::grid_helper::clock_grid_spacer;
+use crate::time_format::TimeFormatter;
+
+/// at most this many segments are calculated for the visible range, the step is increased beyond
+const MAX_COLUMNS: usize = 2048;
+
+/// Scrolling spectrogram of a channel, drawn below the channel in a plot linked to the time axis
+pub struct SpectrogramPlot {
+    pub window: WindowFunction,
+    /// samples per segment, a power of two
+    pub segment_length: usize,
+    /// overlap of the segments, 0 to < 1
+    pub overlap: f64,
+    /// range of the color scale below the maximum, in dB
+    pub dynamic_range: f64,
+    /// the channel with a fixed sample rate and the channel generation it was created for
+    signal: Option<(u64, UniformSignal)>,
+    image: Option<SpectrogramImage>,
+}
+
+/// The spectrogram of a range as a texture, with everything it was calculated from
+struct SpectrogramImage {
+    texture: TextureHandle,
+    /// x range the calculation covers
+    covered: (f64, f64),
+    /// x range of the first and last segment
+    extent: (f64, f64),
+    max_frequency: f64,
+    settings: (WindowFunction, usize, f64, f64, u64),
+}
+
+impl Default for SpectrogramPlot {
+    fn default() -> Self {
+        SpectrogramPlot {
+            window: WindowFunction::Hann,
+            segment_length: 256,
+            overlap: 0.5,
+            dynamic_range: 60.0,
+            signal: None,
+            image: None,
+        }
+    }
+}
+
+impl SpectrogramPlot {
+    /// Editor for the STFT parameters
+    pub fn show_settings(&mut self, ui: &mut Ui) {
+        egui::ComboBox::from_label("Window")
+            .selected_text(self.window.label())
+            .show_ui(ui, |ui| {
+                for window in [
+                    WindowFunction::Rectangular,
+                    WindowFunction::Hann,
+                    WindowFunction::Hamming,
+                    WindowFunction::Blackman,
+                ] {
+                    ui.selectable_value(&mut self.window, window, window.label());
+                }
+            });
+        egui::ComboBox::from_label("Segment length")
+            .selected_text(format!("{} samples", self.segment_length))
+            .show_ui(ui, |ui| {
+                for length in [64, 128, 256, 512, 1024, 2048, 4096] {
+                    ui.selectable_value(
+                        &mut self.segment_length,
+                        length,
+                        format!("{} samples", length),
+                    );
+                }
+            });
+        ui.add(egui::Slider::new(&mut self.overlap, 0.0..=0.9).text("Overlap"));
+        ui.horizontal(|ui| {
+            ui.label("Dynamic range");
+            ui.add(
+                DragValue::new(&mut self.dynamic_range)
+                    .speed(1.0)
+                    .clamp_range(10.0..=150.0)
+                    .suffix(" dB"),
+            );
+        });
+    }
+
+    /// Draw the spectrogram of `channel` for the x range of the linked plots,
+    /// returns the position of the pointer if the plot is hovered
+    #[allow(clippy::too_many_arguments)]
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        id: String,
+        channel: &mut dyn DrawableChannel,
+        generation: u64,
+        height: f32,
+        time_formatter: TimeFormatter,
+        utc_offset: f64,
+        show_x_axis: bool,
+    ) -> Option<f64> {
+        let up_to_date = matches!(&self.signal, Some((g, _)) if *g == generation);
+        if !up_to_date {
+            self.signal = channel.uniform_signal().map(|s| (generation, s));
+            self.image = None;
+        }
+        let label = channel.get_label();
+        let mut plot = Plot::new(id)
+            .height(height)
+            .set_margin_fraction(egui::Vec2 { x: 0.1, y: 0.0 })
+            .auto_bounds_y()
+            .include_y(0.0)
+            .label_formatter(move |_, value| {
+                format!(
+                    "{:.1} Hz\n{}",
+                    value.y,
+                    time_formatter.format_position(value.x)
+                )
+            })
+            .link_axis("ecg", true, false)
+            .link_cursor("ecg", true, false)
+            .x_grid_spacer(clock_grid_spacer(utc_offset))
+            .x_axis_formatter(move |value, _max_chars, range| {
+                time_formatter.format_tick(value, range)
+            })
+            .show_axes([show_x_axis, true])
+            .y_axis_label(format!("{} Hz", label));
+        if show_x_axis {
+            plot = plot.x_axis_label(time_formatter.axis_label());
+        }
+        if let Some(image) = &self.image {
+            plot = plot.include_y(image.max_frequency);
+        }
+
+        plot.show(ui, |plot_ui| {
+            let bounds = plot_ui.plot_bounds();
+            let (start, end) = (bounds.min()[0], bounds.max()[0]);
+            if start.is_finite() && end.is_finite() && end > start {
+                self.update_image(plot_ui.ctx(), &label, generation, start, end);
+            }
+            if let Some(image) = &self.image {
+                let (first, last) = image.extent;
+                plot_ui.image(PlotImage::new(
+                    &image.texture,
+                    PlotPoint::new((first + last) / 2.0, image.max_frequency / 2.0),
+                    [(last - first) as f32, image.max_frequency as f32],
+                ));
+            }
+            plot_ui
+                .response()
+                .hovered()
+                .then(|| plot_ui.pointer_coordinate().map(|p| p.x))
+                .flatten()
+        })
+        .inner
+    }
+
+    /// Recalculate the image if the settings changed or the visible range isn't covered
+    /// with enough resolution, the image covers one screen width to each side
+    fn update_image(
+        &mut self,
+        ctx: &egui::Context,
+        label: &str,
+        generation: u64,
+        start: f64,
+        end: f64,
+    ) {
+        let settings = (
+            self.window,
+            self.segment_length,
+            self.overlap,
+            self.dynamic_range,
+            generation,
+        );
+        let width = end - start;
+        let up_to_date = self.image.as_ref().is_some_and(|image| {
+            let (covered_start, covered_end) = image.covered;
+            image.settings == settings
+                && covered_start <= start
+                && covered_end >= end
+                && covered_end - covered_start < 6.0 * width
+        });
+        if up_to_date {
+            return;
+        }
+        let Some((_, signal)) = self.signal.as_ref().filter(|(_, s)| !s.samples.is_empty()) else {
+            return;
+        };
+        let covered = (start - width, end + width);
+        let first = signal.index(covered.0);
+        let last = signal.index(covered.1);
+        let samples = &signal.samples[first..=last.max(first)];
+        let overlap_step = (self.segment_length as f64 * (1.0 - self.overlap)).round() as usize;
+        let step = overlap_step.max(samples.len() / MAX_COLUMNS).max(1);
+        let spectrogram = spectrogram(
+            samples,
+            signal.samples_per_second,
+            self.segment_length,
+            step,
+            self.window,
+        );
+        let (Some(first_time), Some(last_time), Some(max_frequency)) = (
+            spectrogram.times.first(),
+            spectrogram.times.last(),
+            spectrogram.frequencies.last(),
+        ) else {
+            self.image = None;
+            return;
+        };
+
+        let decibels = |p: f64| 10.0 * p.max(1E-30).log10();
+        let max = spectrogram
+            .power
+            .iter()
+            .flatten()
+            .map(|p| decibels(*p))
+            .fold(f64::NEG_INFINITY, f64::max);
+        let n_bins = spectrogram.frequencies.len();
+        let mut pixels = vec![Color32::TRANSPARENT; spectrogram.power.len() * n_bins];
+        spectrogram
+            .power
+            .iter()
+            .enumerate()
+            .for_each(|(column, power)| {
+                power.iter().enumerate().for_each(|(bin, p)| {
+                    let value = 1.0 - (max - decibels(*p)) / self.dynamic_range;
+                    // the highest frequency is the top row
+                    pixels[(n_bins - 1 - bin) * spectrogram.power.len() + column] =
+                        color_map(value.clamp(0.0, 1.0));
+                });
+            });
+        let image = ColorImage {
+            size: [spectrogram.power.len(), n_bins],
+            pixels,
+        };
+        let offset = signal.position(first);
+        let half_step = step as f64 / signal.samples_per_second / 2.0;
+        self.image = Some(SpectrogramImage {
+            texture: ctx.load_texture(
+                format!("spectrogram {}", label),
+                image,
+                TextureOptions::LINEAR,
+            ),
+            covered,
+            extent: (
+                offset + first_time - half_step,
+                offset + last_time + half_step,
+            ),
+            max_frequency: *max_frequency,
+            settings,
+        });
+    }
+}
+
+/// Dark blue over green to yellow for `value` from 0 to 1, similar to viridis
+fn color_map(value: f64) -> Color32 {
+    const STOPS: [(f64, [f64; 3]); 5] = [
+        (0.0, [68.0, 1.0, 84.0]),
+        (0.25, [59.0, 82.0, 139.0]),
+        (0.5, [33.0, 145.0, 140.0]),
+        (0.75, [94.0, 201.0, 98.0]),
+        (1.0, [253.0, 231.0, 37.0]),
+    ];
+    let idx = STOPS
+        .iter()
+        .position(|(stop, _)| *stop >= value)
+        .unwrap_or(STOPS.len() - 1)
+        .max(1);
+    let (v0, c0) = STOPS[idx - 1];
+    let (v1, c1) = STOPS[idx];
+    let t = (value - v0) / (v1 - v0);
+    let channel = |i: usize| (c0[i] + (c1[i] - c0[i]) * t).round() as u8;
+    Color32::from_rgb(channel(0), channel(1), channel(2))
+}
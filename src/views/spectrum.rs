@@ -0,0 +1,263 @@
+use egui::Context;
+use egui_plot::{Legend, Line, Plot, PlotPoints, VLine};
+
+use crate::analysis::spectrum::{
+    equivalent_noise_bandwidth, welch_with_window, Spectrum, WindowFunction,
+};
+use crate::data_structures::AnalysisRange;
+use crate::views::common::{export_csv, select_channel, select_range};
+use crate::ChannelPlotter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SpectrumScale {
+    /// power spectral density in unit² / Hz
+    Density,
+    /// power spectral density in dB
+    Decibel,
+    /// amplitude of a sinusoid at the frequency, in unit
+    Magnitude,
+}
+
+impl SpectrumScale {
+    fn label(&self) -> &'static str {
+        match self {
+            SpectrumScale::Density => "PSD",
+            SpectrumScale::Decibel => "PSD (dB)",
+            SpectrumScale::Magnitude => "Magnitude",
+        }
+    }
+
+    fn unit(&self, unit: &str) -> String {
+        match self {
+            SpectrumScale::Density => format!("{}²/Hz", unit),
+            SpectrumScale::Decibel => format!("dB {}²/Hz", unit),
+            SpectrumScale::Magnitude => unit.to_owned(),
+        }
+    }
+}
+
+/// The spectrum of a channel over a range
+struct SpectrumResult {
+    label: String,
+    unit: String,
+    spectrum: Spectrum,
+    /// equivalent noise bandwidth of the window in Hz, converts densities to amplitudes
+    noise_bandwidth: f64,
+}
+
+impl SpectrumResult {
+    fn value(&self, power: f64, scale: SpectrumScale) -> f64 {
+        match scale {
+            SpectrumScale::Density => power,
+            SpectrumScale::Decibel => 10.0 * power.max(1E-30).log10(),
+            SpectrumScale::Magnitude => (2.0 * power * self.noise_bandwidth).sqrt(),
+        }
+    }
+}
+
+/// Window with the power spectrum (Welch) of a channel over the recording, view or selection
+pub struct SpectrumView {
+    pub open: bool,
+    channel: Option<String>,
+    range: AnalysisRange,
+    window: WindowFunction,
+    /// samples per segment, None for one segment over the whole range
+    segment_length: Option<usize>,
+    overlap: f64,
+    scale: SpectrumScale,
+    result: Option<SpectrumResult>,
+    message: Option<String>,
+}
+
+impl Default for SpectrumView {
+    fn default() -> Self {
+        SpectrumView {
+            open: false,
+            channel: None,
+            range: AnalysisRange::Selection,
+            window: WindowFunction::Hann,
+            segment_length: Some(1024),
+            overlap: 0.5,
+            scale: SpectrumScale::Decibel,
+            result: None,
+            message: None,
+        }
+    }
+}
+
+impl SpectrumView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Spectrum")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                let labels: Vec<String> =
+                    plotter.channels.iter_mut().map(|c| c.get_label()).collect();
+                if labels.is_empty() {
+                    ui.label("No channels loaded.");
+                    return;
+                }
+                select_channel(ui, "Channel", &mut self.channel, &labels);
+                let range = select_range(ui, "spectrum_range", &mut self.range, plotter);
+                egui::ComboBox::from_label("Window")
+                    .selected_text(self.window.label())
+                    .show_ui(ui, |ui| {
+                        for window in [
+                            WindowFunction::Rectangular,
+                            WindowFunction::Hann,
+                            WindowFunction::Hamming,
+                            WindowFunction::Blackman,
+                        ] {
+                            ui.selectable_value(&mut self.window, window, window.label());
+                        }
+                    });
+                egui::ComboBox::from_label("Segment length")
+                    .selected_text(match self.segment_length {
+                        Some(length) => format!("{} samples", length),
+                        None => "whole range".to_owned(),
+                    })
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.segment_length, None, "whole range");
+                        for length in [256, 512, 1024, 2048, 4096, 8192, 16384] {
+                            ui.selectable_value(
+                                &mut self.segment_length,
+                                Some(length),
+                                format!("{} samples", length),
+                            );
+                        }
+                    });
+                ui.add_enabled(
+                    self.segment_length.is_some(),
+                    egui::Slider::new(&mut self.overlap, 0.0..=0.9).text("Overlap"),
+                );
+                let (Some(label), Some(range)) = (self.channel.clone(), range) else {
+                    return;
+                };
+                if ui.button("Calculate").clicked() {
+                    self.calculate(plotter, &label, range);
+                }
+                if let Some(message) = &self.message {
+                    ui.label(message);
+                }
+                let Some(result) = &self.result else {
+                    return;
+                };
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    for scale in [
+                        SpectrumScale::Density,
+                        SpectrumScale::Decibel,
+                        SpectrumScale::Magnitude,
+                    ] {
+                        ui.radio_value(&mut self.scale, scale, scale.label());
+                    }
+                });
+                let scale = self.scale;
+                // the DC bin is left out, it only holds what remains after removing the mean
+                let peak = result
+                    .spectrum
+                    .frequencies
+                    .get(1)
+                    .and_then(|low| result.spectrum.peak_frequency(*low, f64::INFINITY));
+                if let Some(peak) = peak {
+                    ui.label(format!("{}: peak at {:.2} Hz", result.label, peak));
+                }
+                let unit = scale.unit(&result.unit);
+                let points: PlotPoints = result
+                    .spectrum
+                    .frequencies
+                    .iter()
+                    .zip(result.spectrum.power.iter())
+                    .skip(1)
+                    .map(|(f, p)| [*f, result.value(*p, scale)])
+                    .collect();
+                let y_label = unit.to_owned();
+                Plot::new("spectrum_plot")
+                    .height(250.0)
+                    .legend(Legend::default())
+                    .x_axis_label("Hz")
+                    .y_axis_label(y_label)
+                    .label_formatter(move |_, value| {
+                        format!("{:.2} Hz\n{:.4} {}", value.x, value.y, unit)
+                    })
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(points).name(scale.label()));
+                        if let Some(peak) = peak {
+                            plot_ui.vline(
+                                VLine::new(peak)
+                                    .style(egui_plot::LineStyle::dashed_loose())
+                                    .name("peak"),
+                            );
+                        }
+                    });
+                export_csv(ui, "spectrum.csv", || self.to_csv());
+            });
+        self.open = open;
+    }
+
+    fn calculate(&mut self, plotter: &mut ChannelPlotter, label: &str, range: (f64, f64)) {
+        self.result = None;
+        let Some(channel) = plotter.channel_by_label(label) else {
+            return;
+        };
+        let unit = channel.get_unit();
+        let Some(signal) = channel.uniform_signal() else {
+            self.message = Some("The channel has too few samples.".to_owned());
+            return;
+        };
+        let samples = &signal.samples[signal.index(range.0)..=signal.index(range.1)];
+        let segment_length = self
+            .segment_length
+            .unwrap_or(samples.len())
+            .min(samples.len());
+        let spectrum = welch_with_window(
+            samples,
+            signal.samples_per_second,
+            segment_length,
+            self.overlap,
+            0,
+            self.window,
+        );
+        if spectrum.frequencies.is_empty() {
+            self.message = Some("No range without gaps is long enough for a segment.".to_owned());
+            return;
+        }
+        self.message = None;
+        self.result = Some(SpectrumResult {
+            label: label.to_owned(),
+            unit,
+            spectrum,
+            noise_bandwidth: equivalent_noise_bandwidth(
+                &self.window.coefficients(segment_length),
+                signal.samples_per_second,
+            ),
+        });
+    }
+
+    fn to_csv(&self) -> String {
+        let Some(result) = &self.result else {
+            return String::new();
+        };
+        let mut csv = format!(
+            "frequency [Hz],PSD [{}],magnitude [{}]\n",
+            SpectrumScale::Density.unit(&result.unit),
+            result.unit
+        );
+        result
+            .spectrum
+            .frequencies
+            .iter()
+            .zip(result.spectrum.power.iter())
+            .for_each(|(f, p)| {
+                csv += &format!(
+                    "{},{},{}\n",
+                    f,
+                    p,
+                    result.value(*p, SpectrumScale::Magnitude)
+                );
+            });
+        csv
+    }
+}
@@ -0,0 +1,171 @@
+use egui::Context;
+
+use crate::analysis::statistics::{statistics, ChannelStatistics, PERCENTILES};
+use crate::data_structures::AnalysisRange;
+use crate::views::common::{export_csv, select_range};
+use crate::ChannelPlotter;
+
+/// Window with descriptive statistics of the shown channels over a range,
+/// recalculated every frame so it follows panning and zooming
+pub struct StatisticsView {
+    pub open: bool,
+    range: AnalysisRange,
+}
+
+impl Default for StatisticsView {
+    fn default() -> Self {
+        StatisticsView {
+            open: false,
+            range: AnalysisRange::Visible,
+        }
+    }
+}
+
+/// Rows of the table: name and formatted value of each measure
+fn measures(stats: &ChannelStatistics) -> Vec<(String, String)> {
+    let optional = |value: Option<f64>, format: &dyn Fn(f64) -> String| {
+        value.map(format).unwrap_or("–".to_owned())
+    };
+    let mut rows = vec![
+        ("Samples".to_owned(), stats.count.to_string()),
+        ("Duration".to_owned(), format!("{:.1} s", stats.duration)),
+        (
+            "Effective sample rate".to_owned(),
+            format!("{:.2} Hz", stats.effective_sample_rate),
+        ),
+        (
+            "Nominal sample rate".to_owned(),
+            optional(stats.nominal_sample_rate, &|r| format!("{:.2} Hz", r)),
+        ),
+        (
+            "Jitter".to_owned(),
+            optional(stats.jitter, &|j| format!("{:.2} ms", j * 1E3)),
+        ),
+        (
+            "Gaps".to_owned(),
+            format!("{} ({:.1} s)", stats.gaps, stats.gap_duration),
+        ),
+        ("Min".to_owned(), format!("{:.4}", stats.min)),
+        ("Max".to_owned(), format!("{:.4}", stats.max)),
+        ("Mean".to_owned(), format!("{:.4}", stats.mean)),
+        ("Std".to_owned(), format!("{:.4}", stats.std)),
+        ("RMS".to_owned(), format!("{:.4}", stats.rms)),
+    ];
+    for (p, value) in PERCENTILES.iter().zip(stats.percentiles) {
+        rows.push((format!("P{:.0}", p), format!("{:.4}", value)));
+    }
+    rows
+}
+
+impl StatisticsView {
+    pub fn show(&mut self, ctx: &Context, plotter: &mut ChannelPlotter) {
+        let mut open = self.open;
+        egui::Window::new("Statistics")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                if plotter.channels.is_empty() {
+                    ui.label("No channels loaded.");
+                    return;
+                }
+                let Some((start, end)) =
+                    select_range(ui, "statistics_range", &mut self.range, plotter)
+                else {
+                    return;
+                };
+                let hidden = plotter.hidden_channels.clone();
+                let gap_factor = plotter.gap_factor;
+                let columns: Vec<(String, Option<ChannelStatistics>)> = plotter
+                    .channels
+                    .iter_mut()
+                    .map(|channel| (channel.get_label(), channel))
+                    .filter(|(label, _)| !hidden.contains(label))
+                    .map(|(label, channel)| {
+                        let points: Vec<(f64, f64)> = channel
+                            .points_to_draw(start, end)
+                            .points()
+                            .iter()
+                            .map(|p| (p.x, p.y))
+                            .filter(|(x, _)| *x >= start && *x <= end)
+                            .collect();
+                        (label, statistics(&points, gap_factor))
+                    })
+                    .collect();
+                if columns.is_empty() {
+                    ui.label("All channels are hidden.");
+                    return;
+                }
+                let names: Vec<String> = columns
+                    .iter()
+                    .find_map(|(_, stats)| stats.as_ref())
+                    .map(|stats| measures(stats).into_iter().map(|(name, _)| name).collect())
+                    .unwrap_or_default();
+                let values: Vec<Vec<String>> = columns
+                    .iter()
+                    .map(|(_, stats)| {
+                        stats
+                            .as_ref()
+                            .map(|s| measures(s).into_iter().map(|(_, v)| v).collect())
+                            .unwrap_or_default()
+                    })
+                    .collect();
+                egui::ScrollArea::both().max_height(400.0).show(ui, |ui| {
+                    egui::Grid::new("statistics_table")
+                        .num_columns(columns.len() + 1)
+                        .striped(true)
+                        .show(ui, |ui| {
+                            ui.label("");
+                            for (label, _) in columns.iter() {
+                                ui.strong(label);
+                            }
+                            ui.end_row();
+                            for (row, name) in names.iter().enumerate() {
+                                ui.label(name);
+                                for column in values.iter() {
+                                    ui.monospace(column.get(row).map_or("no values", |v| v));
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+                export_csv(ui, "statistics.csv", || {
+                    let mut csv = "channel,samples,duration [s],effective sample rate [Hz],\
+                                   nominal sample rate [Hz],jitter [s],gaps,gap duration [s],\
+                                   min,max,mean,std,RMS"
+                        .to_owned();
+                    for p in PERCENTILES {
+                        csv += &format!(",P{:.0}", p);
+                    }
+                    csv += "\n";
+                    let optional = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+                    for (label, stats) in columns.iter() {
+                        let Some(s) = stats else {
+                            continue;
+                        };
+                        csv += &format!(
+                            "\"{}\",{},{},{},{},{},{},{},{},{},{},{},{}",
+                            label,
+                            s.count,
+                            s.duration,
+                            s.effective_sample_rate,
+                            optional(s.nominal_sample_rate),
+                            optional(s.jitter),
+                            s.gaps,
+                            s.gap_duration,
+                            s.min,
+                            s.max,
+                            s.mean,
+                            s.std,
+                            s.rms
+                        );
+                        for value in s.percentiles {
+                            csv += &format!(",{}", value);
+                        }
+                        csv += "\n";
+                    }
+                    csv
+                });
+            });
+        self.open = open;
+    }
+}